@@ -308,6 +308,7 @@ pub fn build(b: *Builder) !void {
     zeam_network.addImport("@zeam/types", zeam_types);
     zeam_network.addImport("@zeam/utils", zeam_utils);
     zeam_network.addImport("@zeam/params", zeam_params);
+    zeam_network.addImport("@zeam/metrics", zeam_metrics);
     zeam_network.addImport("xev", xev);
     zeam_network.addImport("ssz", ssz);
     zeam_network.addImport("multiformats", multiformats);
@@ -88,6 +88,9 @@ pub const NodeOptions = struct {
     node_registry: *node_lib.NodeNameRegistry,
     checkpoint_sync_url: ?[]const u8 = null,
     attestation_committee_count: ?u64 = null,
+    /// File the network keeps known peers in across restarts, `peers.json` in the data directory
+    /// if not set.
+    peer_store_path: ?[]const u8 = null,
 
     pub fn deinit(self: *NodeOptions, allocator: std.mem.Allocator) void {
         for (self.bootnodes) |b| allocator.free(b);
@@ -178,6 +181,8 @@ pub const Node = struct {
             .local_private_key = options.local_priv_key,
             .node_registry = options.node_registry,
             .attestation_committee_count = chain_config.spec.attestation_committee_count,
+            .data_dir = options.database_path,
+            .peer_store_path = options.peer_store_path,
         }, options.logger_config.logger(.network));
         errdefer self.network.deinit();
         self.clock = try Clock.init(allocator, chain_config.genesis.genesis_time, &self.loop);
@@ -27,6 +27,16 @@ fn getTimestamp() i128 {
 pub var metrics = metrics_lib.initializeNoop(Metrics);
 var g_initialized: bool = false;
 
+/// Metrics rendered outside of this registry, e.g. by the rust libp2p glue. Their text is
+/// appended to the output of `writeMetrics`.
+pub const ExternalMetricsSource = struct {
+    ptr: *anyopaque,
+    writeFn: *const fn (ptr: *anyopaque, writer: *std.Io.Writer) anyerror!void,
+};
+
+var g_external_source: ?ExternalMetricsSource = null;
+var g_external_source_mutex: std.Thread.Mutex = .{};
+
 const Metrics = struct {
     chain_onblock_duration_seconds: ChainHistogram,
     block_processing_duration_seconds: BlockProcessingHistogram,
@@ -340,15 +350,44 @@ pub fn init(allocator: std.mem.Allocator) !void {
     g_initialized = true;
 }
 
+/// Registers the source whose metrics are appended to `writeMetrics`, replacing any previous one.
+pub fn setExternalMetricsSource(source: ExternalMetricsSource) void {
+    if (comptime isZKVM()) return;
+
+    g_external_source_mutex.lock();
+    defer g_external_source_mutex.unlock();
+    g_external_source = source;
+}
+
+/// Removes the external source registered with `ptr`, waiting for a scrape using it to finish.
+pub fn clearExternalMetricsSource(ptr: *anyopaque) void {
+    if (comptime isZKVM()) return;
+
+    g_external_source_mutex.lock();
+    defer g_external_source_mutex.unlock();
+    if (g_external_source) |source| {
+        if (source.ptr == ptr) g_external_source = null;
+    }
+}
+
 /// Writes metrics to a writer (for Prometheus endpoint).
 pub fn writeMetrics(writer: *std.Io.Writer) !void {
     if (!g_initialized) return error.NotInitialized;
 
     // For ZKVM targets, write no metrics
-    if (isZKVM()) {
+    if (comptime isZKVM()) {
         try writer.writeAll("# Metrics disabled for ZKVM target\n");
         return;
     }
 
     try metrics_lib.write(&metrics, writer);
+
+    g_external_source_mutex.lock();
+    defer g_external_source_mutex.unlock();
+    if (g_external_source) |source| {
+        // A failing external source must not break the node's own metrics.
+        source.writeFn(source.ptr, writer) catch |err| {
+            std.log.warn("failed to write external metrics: {any}", .{err});
+        };
+    }
 }
@@ -10,7 +10,9 @@ const multiaddr_mod = @import("multiaddr");
 const Multiaddr = multiaddr_mod.Multiaddr;
 const uvarint = multiformats.uvarint;
 const zeam_utils = @import("@zeam/utils");
+const zeam_metrics = @import("@zeam/metrics");
 
+const consensus_params = @import("@zeam/params");
 const interface = @import("./interface.zig");
 const NetworkInterface = interface.NetworkInterface;
 const snappyz = @import("snappyz");
@@ -25,6 +27,8 @@ const ServerStreamError = error{
 
 const MAX_RPC_MESSAGE_SIZE: usize = 4 * 1024 * 1024;
 const MAX_VARINT_BYTES: usize = uvarint.bufferSize(usize);
+/// Most versions `LeanSupportedProtocol.versionTags` offers for one request.
+const MAX_PROTOCOL_VERSIONS: usize = 2;
 
 const FrameDecodeError = error{
     EmptyFrame,
@@ -168,16 +172,19 @@ fn serverStreamSendResponse(ptr: *anyopaque, response: *const interface.ReqRespR
     };
     defer allocator.free(encoded);
 
-    const framed = snappyframesz.encode(allocator, encoded) catch |err| {
-        ctx.zigHandler.logger.err(
-            "network-{d}:: Failed to snappy-frame {s} response for peer={s}{f} channel={d}: {any}",
-            .{ ctx.zigHandler.params.networkId, response_method_name, ctx.peer_id, node_name, ctx.channel_id, err },
-        );
-        return err;
-    };
-    defer allocator.free(framed);
+    // With decompress_rpc_payloads the glue snappy-frames the SSZ bytes itself
+    const frame = if (ctx.zigHandler.params.decompress_rpc_payloads) try allocator.dupe(u8, encoded) else blk: {
+        const framed = snappyframesz.encode(allocator, encoded) catch |err| {
+            ctx.zigHandler.logger.err(
+                "network-{d}:: Failed to snappy-frame {s} response for peer={s}{f} channel={d}: {any}",
+                .{ ctx.zigHandler.params.networkId, response_method_name, ctx.peer_id, node_name, ctx.channel_id, err },
+            );
+            return err;
+        };
+        defer allocator.free(framed);
 
-    const frame = try buildResponseFrame(allocator, 0, encoded.len, framed);
+        break :blk try buildResponseFrame(allocator, 0, encoded.len, framed);
+    };
     defer allocator.free(frame);
 
     ctx.zigHandler.logger.debug(
@@ -212,6 +219,7 @@ fn serverStreamSendError(ptr: *anyopaque, code: u32, message: []const u8) anyerr
     send_rpc_error_response(
         ctx.zigHandler.params.networkId,
         ctx.channel_id,
+        code,
         owned_message.ptr,
     );
 
@@ -298,14 +306,30 @@ fn deserializeGossipMessage(
     return message_data;
 }
 
-export fn handleMsgFromRustBridge(zigHandler: *EthLibp2p, topic_str: [*:0]const u8, message_ptr: [*]const u8, message_len: usize, sender_peer_id: [*:0]const u8) void {
+/// Outcome of validating a gossip message, reported back to gossipsub via `report_gossip_validation`.
+/// Must stay in sync with the acceptance codes decoded by the rust bridge.
+const GossipValidationResult = enum(u32) {
+    accept = 0,
+    reject = 1,
+    ignore = 2,
+};
+
+const TopicMeshEvent = enum(u32) {
+    joined = 0,
+    left = 1,
+};
+
+export fn handleMsgFromRustBridge(zigHandler: *EthLibp2p, topic_str: [*:0]const u8, message_ptr: [*]const u8, message_len: usize, sender_peer_id: [*:0]const u8, message_id: [*:0]const u8) void {
+    const result = processGossipFromRustBridge(zigHandler, topic_str, message_ptr[0..message_len], sender_peer_id);
+    report_gossip_validation(zigHandler.params.networkId, message_id, @intFromEnum(result));
+}
+
+fn processGossipFromRustBridge(zigHandler: *EthLibp2p, topic_str: [*:0]const u8, message_bytes: []const u8, sender_peer_id: [*:0]const u8) GossipValidationResult {
     const topic = interface.LeanNetworkTopic.decode(zigHandler.allocator, topic_str) catch |err| {
         zigHandler.logger.err("Ignoring Invalid topic_id={s} sent in handleMsgFromRustBridge: {any}", .{ std.mem.span(topic_str), err });
-        return;
+        return .reject;
     };
 
-    const message_bytes: []const u8 = message_ptr[0..message_len];
-
     const uncompressed_message = snappyz.decode(zigHandler.allocator, message_bytes) catch |e| {
         zigHandler.logger.err("Error in snappyz decoding the message for topic={s}: {any}", .{ std.mem.span(topic_str), e });
         if (writeFailedBytes(message_bytes, "snappyz_decode", zigHandler.allocator, null, zigHandler.logger)) |filename| {
@@ -313,7 +337,7 @@ export fn handleMsgFromRustBridge(zigHandler: *EthLibp2p, topic_str: [*:0]const
         } else {
             zigHandler.logger.err("Snappyz decode failed - could not create debug file", .{});
         }
-        return;
+        return .reject;
     };
     defer zigHandler.allocator.free(uncompressed_message);
     var message: interface.GossipMessage = switch (topic.gossip_topic.kind) {
@@ -323,11 +347,11 @@ export fn handleMsgFromRustBridge(zigHandler: *EthLibp2p, topic_str: [*:0]const
             uncompressed_message,
             zigHandler.allocator,
             zigHandler.logger,
-        ) orelse return },
+        ) orelse return .reject },
         .attestation => blk: {
             const subnet_id = topic.gossip_topic.subnet_id orelse {
                 zigHandler.logger.err("attestation topic missing subnet id: {s}", .{std.mem.span(topic_str)});
-                return;
+                return .reject;
             };
             const msg = deserializeGossipMessage(
                 types.SignedAttestation,
@@ -335,7 +359,7 @@ export fn handleMsgFromRustBridge(zigHandler: *EthLibp2p, topic_str: [*:0]const
                 uncompressed_message,
                 zigHandler.allocator,
                 zigHandler.logger,
-            ) orelse return;
+            ) orelse return .reject;
             break :blk .{ .attestation = .{ .subnet_id = subnet_id, .message = msg } };
         },
         .aggregation => .{ .aggregation = deserializeGossipMessage(
@@ -344,7 +368,7 @@ export fn handleMsgFromRustBridge(zigHandler: *EthLibp2p, topic_str: [*:0]const
             uncompressed_message,
             zigHandler.allocator,
             zigHandler.logger,
-        ) orelse return },
+        ) orelse return .reject },
     };
     defer message.deinit();
 
@@ -413,7 +437,9 @@ export fn handleMsgFromRustBridge(zigHandler: *EthLibp2p, topic_str: [*:0]const
     // TODO: figure out why scheduling on the loop is not working
     zigHandler.gossipHandler.onGossip(&message, sender_peer_id_slice, false) catch |e| {
         zigHandler.logger.err("onGossip handling of message failed with error e={any} from sender_peer_id={s}{f}", .{ e, sender_peer_id_slice, node_name });
+        return .ignore;
     };
+    return .accept;
 }
 
 export fn handleRPCRequestFromRustBridge(
@@ -433,45 +459,51 @@ export fn handleRPCRequestFromRustBridge(
             "network-{d}:: Unsupported RPC protocol from peer={s}{f} on channel={d}: {s}",
             .{ zigHandler.params.networkId, peer_id_slice, node_name, channel_id, protocol_slice },
         );
-        send_rpc_error_response(zigHandler.params.networkId, channel_id, "Unsupported RPC protocol");
+        send_rpc_error_response(zigHandler.params.networkId, channel_id, 1, "Unsupported RPC protocol");
         return;
     };
 
     const request_frame: []const u8 = request_ptr[0..request_len];
 
-    const request_frame_info = parseRequestFrame(request_frame) catch |err| {
-        zigHandler.logger.err(
-            "network-{d}:: Invalid RPC request frame from peer={s}{f} protocol={s}: {any}",
-            .{ zigHandler.params.networkId, peer_id_slice, node_name, protocol_slice, err },
-        );
-        send_rpc_error_response(zigHandler.params.networkId, channel_id, "Invalid RPC request frame");
-        return;
-    };
+    // With decompress_rpc_payloads the glue already checked and decompressed the snappy frame
+    var decoded_request: ?[]u8 = null;
+    defer if (decoded_request) |bytes| zigHandler.allocator.free(bytes);
+    const request_bytes: []const u8 = if (zigHandler.params.decompress_rpc_payloads) request_frame else blk: {
+        const request_frame_info = parseRequestFrame(request_frame) catch |err| {
+            zigHandler.logger.err(
+                "network-{d}:: Invalid RPC request frame from peer={s}{f} protocol={s}: {any}",
+                .{ zigHandler.params.networkId, peer_id_slice, node_name, protocol_slice, err },
+            );
+            send_rpc_error_response(zigHandler.params.networkId, channel_id, 1, "Invalid RPC request frame");
+            return;
+        };
 
-    const request_bytes = snappyframesz.decode(zigHandler.allocator, request_frame_info.payload) catch |err| {
-        zigHandler.logger.err(
-            "network-{d}:: Failed to decode snappy-framed RPC request from peer={s}{f} protocol={s}: {any}",
-            .{ zigHandler.params.networkId, peer_id_slice, node_name, protocol_slice, err },
-        );
-        send_rpc_error_response(zigHandler.params.networkId, channel_id, "Failed to decode RPC request");
-        return;
+        const decoded = snappyframesz.decode(zigHandler.allocator, request_frame_info.payload) catch |err| {
+            zigHandler.logger.err(
+                "network-{d}:: Failed to decode snappy-framed RPC request from peer={s}{f} protocol={s}: {any}",
+                .{ zigHandler.params.networkId, peer_id_slice, node_name, protocol_slice, err },
+            );
+            send_rpc_error_response(zigHandler.params.networkId, channel_id, 1, "Failed to decode RPC request");
+            return;
+        };
+        decoded_request = decoded;
+        if (decoded.len != request_frame_info.declared_len) {
+            zigHandler.logger.err(
+                "network-{d}:: Invalid RPC request length from peer={s}{f} protocol={s}: declared={d} decoded={d}",
+                .{
+                    zigHandler.params.networkId,
+                    peer_id_slice,
+                    node_name,
+                    protocol_slice,
+                    request_frame_info.declared_len,
+                    decoded.len,
+                },
+            );
+            send_rpc_error_response(zigHandler.params.networkId, channel_id, 1, "Invalid RPC request length");
+            return;
+        }
+        break :blk decoded;
     };
-    defer zigHandler.allocator.free(request_bytes);
-    if (request_bytes.len != request_frame_info.declared_len) {
-        zigHandler.logger.err(
-            "network-{d}:: Invalid RPC request length from peer={s}{f} protocol={s}: declared={d} decoded={d}",
-            .{
-                zigHandler.params.networkId,
-                peer_id_slice,
-                node_name,
-                protocol_slice,
-                request_frame_info.declared_len,
-                request_bytes.len,
-            },
-        );
-        send_rpc_error_response(zigHandler.params.networkId, channel_id, "Invalid RPC request length");
-        return;
-    }
 
     const method = rpc_protocol;
     var request = interface.ReqRespRequest.deserialize(zigHandler.allocator, method, request_bytes) catch |err| {
@@ -485,7 +517,7 @@ export fn handleRPCRequestFromRustBridge(
         } else {
             zigHandler.logger.err("RPC {s} deserialization failed - could not create debug file from peer={s}{f}", .{ label, peer_id_slice, node_name });
         }
-        send_rpc_error_response(zigHandler.params.networkId, channel_id, "Failed to deserialize RPC request");
+        send_rpc_error_response(zigHandler.params.networkId, channel_id, 1, "Failed to deserialize RPC request");
         return;
     };
     defer request.deinit();
@@ -536,7 +568,7 @@ export fn handleRPCRequestFromRustBridge(
             const msg = std.fmt.allocPrint(zigHandler.allocator, "Handler error: {any}", .{e}) catch null;
             if (msg) |owned| {
                 defer zigHandler.allocator.free(owned);
-                stream.sendError(1, owned) catch |send_err| {
+                stream.sendError(2, owned) catch |send_err| {
                     zigHandler.logger.err(
                         "network-{d}:: Failed to send RPC error response for peer={s}{f} channel={d}: {any}",
                         .{ zigHandler.params.networkId, peer_id_slice, node_name, channel_id, send_err },
@@ -571,6 +603,7 @@ export fn handleRPCResponseFromRustBridge(
     protocol_id: [*:0]const u8,
     response_ptr: [*]const u8,
     response_len: usize,
+    context_bytes: ?*const [4]u8,
 ) void {
     const protocol_slice = std.mem.span(protocol_id);
     const peer_id_slice = std.mem.span(peer_id);
@@ -607,68 +640,74 @@ export fn handleRPCResponseFromRustBridge(
 
     const response_frame = response_ptr[0..response_len];
 
-    const parsed_frame = parseResponseFrame(response_frame) catch |err| {
-        zigHandler.notifyRpcErrorFmt(
-            request_id,
-            method,
-            2,
-            "Invalid response frame (protocol={s}): {any}",
-            .{ protocol.protocolId(), err },
-        );
-        return;
-    };
+    // With decompress_rpc_payloads the glue already checked and decompressed the success chunk
+    var decoded_response: ?[]u8 = null;
+    defer if (decoded_response) |bytes| zigHandler.allocator.free(bytes);
+    const response_bytes: []const u8 = if (zigHandler.params.decompress_rpc_payloads) response_frame else blk: {
+        const parsed_frame = parseResponseFrame(response_frame) catch |err| {
+            zigHandler.notifyRpcErrorFmt(
+                request_id,
+                method,
+                2,
+                "Invalid response frame (protocol={s}): {any}",
+                .{ protocol.protocolId(), err },
+            );
+            return;
+        };
 
-    if (parsed_frame.code != 0) {
-        zigHandler.logger.warn(
-            "network-{d}:: RPC error response for request_id={d} protocol={s} code={d} from peer={s}{f}",
-            .{ zigHandler.params.networkId, request_id, protocol.protocolId(), parsed_frame.code, callback_peer_id, callback_node_name },
-        );
+        if (parsed_frame.code != 0) {
+            zigHandler.logger.warn(
+                "network-{d}:: RPC error response for request_id={d} protocol={s} code={d} from peer={s}{f}",
+                .{ zigHandler.params.networkId, request_id, protocol.protocolId(), parsed_frame.code, callback_peer_id, callback_node_name },
+            );
 
-        const owned_message = zigHandler.allocator.dupe(u8, parsed_frame.payload) catch |dup_err| {
-            zigHandler.logger.err(
-                "network-{d}:: Failed to duplicate RPC error payload for request_id={d} from peer={s}{f}: {any}",
-                .{ zigHandler.params.networkId, request_id, callback_peer_id, callback_node_name, dup_err },
+            const owned_message = zigHandler.allocator.dupe(u8, parsed_frame.payload) catch |dup_err| {
+                zigHandler.logger.err(
+                    "network-{d}:: Failed to duplicate RPC error payload for request_id={d} from peer={s}{f}: {any}",
+                    .{ zigHandler.params.networkId, request_id, callback_peer_id, callback_node_name, dup_err },
+                );
+                zigHandler.notifyRpcErrorFmt(
+                    request_id,
+                    method,
+                    @intCast(parsed_frame.code),
+                    "Failed to duplicate RPC error payload (protocol={s})",
+                    .{protocol_slice},
+                );
+                return;
+            };
+
+            zigHandler.notifyRpcErrorWithOwnedMessage(
+                request_id,
+                method,
+                @intCast(parsed_frame.code),
+                owned_message,
             );
+            return;
+        }
+
+        const decoded = snappyframesz.decode(zigHandler.allocator, parsed_frame.payload) catch |err| {
             zigHandler.notifyRpcErrorFmt(
                 request_id,
                 method,
-                @intCast(parsed_frame.code),
-                "Failed to duplicate RPC error payload (protocol={s})",
-                .{protocol_slice},
+                2,
+                "Failed to decode snappy-framed response (protocol={s}): {any}",
+                .{ protocol.protocolId(), err },
             );
             return;
         };
-
-        zigHandler.notifyRpcErrorWithOwnedMessage(
-            request_id,
-            method,
-            @intCast(parsed_frame.code),
-            owned_message,
-        );
-        return;
-    }
-
-    const response_bytes = snappyframesz.decode(zigHandler.allocator, parsed_frame.payload) catch |err| {
-        zigHandler.notifyRpcErrorFmt(
-            request_id,
-            method,
-            2,
-            "Failed to decode snappy-framed response (protocol={s}): {any}",
-            .{ protocol.protocolId(), err },
-        );
-        return;
+        decoded_response = decoded;
+        if (decoded.len != parsed_frame.declared_len) {
+            zigHandler.notifyRpcErrorFmt(
+                request_id,
+                method,
+                2,
+                "Response length mismatch (protocol={s}): declared {d} decoded {d}",
+                .{ protocol.protocolId(), parsed_frame.declared_len, decoded.len },
+            );
+            return;
+        }
+        break :blk decoded;
     };
-    defer zigHandler.allocator.free(response_bytes);
-    if (response_bytes.len != parsed_frame.declared_len) {
-        zigHandler.notifyRpcErrorFmt(
-            request_id,
-            method,
-            2,
-            "Response length mismatch (protocol={s}): declared {d} decoded {d}",
-            .{ protocol.protocolId(), parsed_frame.declared_len, response_bytes.len },
-        );
-        return;
-    }
 
     const response_union = interface.ReqRespResponse.deserialize(zigHandler.allocator, method, response_bytes) catch |err| {
         zigHandler.notifyRpcErrorFmt(
@@ -682,6 +721,7 @@ export fn handleRPCResponseFromRustBridge(
     };
 
     var event = interface.ReqRespResponseEvent.initSuccess(request_id, method, response_union);
+    if (context_bytes) |digest| event.context_bytes = digest.*;
     defer event.deinit(zigHandler.allocator);
 
     zigHandler.logger.debug(
@@ -737,6 +777,8 @@ export fn handleRPCEndOfStreamFromRustBridge(
     }
 }
 
+/// Fails a pending request. `code` is the response code of the peer's error chunk, or one outside
+/// the spec's range for failures without one: 408 when the request timed out, 500 otherwise.
 export fn handleRPCErrorFromRustBridge(
     zigHandler: *EthLibp2p,
     request_id: u64,
@@ -853,6 +895,61 @@ export fn handlePeerConnectionFailedFromRustBridge(
     };
 }
 
+export fn handlePeerStatusFromRustBridge(
+    zigHandler: *EthLibp2p,
+    peer_id: [*:0]const u8,
+    finalized_root: *const [32]u8,
+    finalized_slot: u64,
+    head_root: *const [32]u8,
+    head_slot: u64,
+) void {
+    const peer_id_slice = std.mem.span(peer_id);
+    const status = types.Status{
+        .finalized_root = finalized_root.*,
+        .finalized_slot = finalized_slot,
+        .head_root = head_root.*,
+        .head_slot = head_slot,
+    };
+
+    zigHandler.peerEventHandler.onPeerStatus(peer_id_slice, status) catch |e| {
+        zigHandler.logger.err("network-{d}:: Error handling peer status event: {any}", .{ zigHandler.params.networkId, e });
+    };
+}
+
+export fn handlePeerGoodbyeFromRustBridge(
+    zigHandler: *EthLibp2p,
+    peer_id: [*:0]const u8,
+    reason: u64,
+) void {
+    const peer_id_slice = std.mem.span(peer_id);
+
+    zigHandler.peerEventHandler.onPeerGoodbye(peer_id_slice, @enumFromInt(reason)) catch |e| {
+        zigHandler.logger.err("network-{d}:: Error handling peer goodbye event: {any}", .{ zigHandler.params.networkId, e });
+    };
+}
+
+export fn handleTopicMeshEventFromRustBridge(
+    zigHandler: *EthLibp2p,
+    topic: [*:0]const u8,
+    peer_id: [*:0]const u8,
+    event: u32,
+) void {
+    const topic_slice = std.mem.span(topic);
+    const peer_id_slice = std.mem.span(peer_id);
+    const node_name = zigHandler.node_registry.getNodeNameFromPeerId(peer_id_slice);
+    const mesh_event = std.meta.intToEnum(TopicMeshEvent, event) catch {
+        zigHandler.logger.err("network-{d}:: Invalid topic mesh event={d} from rust bridge", .{ zigHandler.params.networkId, event });
+        return;
+    };
+    zigHandler.logger.info("network-{d}:: Peer {s}{f} {s} mesh of topic={s}", .{
+        zigHandler.params.networkId,
+        peer_id_slice,
+        node_name,
+        @tagName(mesh_event),
+        topic_slice,
+    });
+}
+
 // Receive plain log lines from the Rust bridge and emit using Zeam logger with proper node scope
 export fn handleLogFromRustBridge(
     zigHandler: *EthLibp2p,
@@ -871,7 +968,7 @@ export fn handleLogFromRustBridge(
     }
 }
 
-export fn releaseStartNetworkParams(zig_handler: *EthLibp2p, local_private_key: [*:0]const u8, listen_addresses: [*:0]const u8, connect_addresses: [*:0]const u8, topics: [*:0]const u8) void {
+export fn releaseStartNetworkParams(zig_handler: *EthLibp2p, local_private_key: [*:0]const u8, listen_addresses: [*:0]const u8, connect_addresses: [*:0]const u8, topics: [*:0]const u8, peer_score_params: [*:0]const u8, transport_config: [*:0]const u8, gossipsub_config: [*:0]const u8, req_resp_config: [*:0]const u8, bootnodes: [*:0]const u8, peer_store_path: [*:0]const u8) void {
     const listen_slice = std.mem.span(listen_addresses);
     zig_handler.allocator.free(listen_slice);
 
@@ -883,6 +980,24 @@ export fn releaseStartNetworkParams(zig_handler: *EthLibp2p, local_private_key:
 
     const private_key_slice = std.mem.span(local_private_key);
     zig_handler.allocator.free(private_key_slice);
+
+    const peer_score_slice = std.mem.span(peer_score_params);
+    zig_handler.allocator.free(peer_score_slice);
+
+    const transport_config_slice = std.mem.span(transport_config);
+    zig_handler.allocator.free(transport_config_slice);
+
+    const gossipsub_config_slice = std.mem.span(gossipsub_config);
+    zig_handler.allocator.free(gossipsub_config_slice);
+
+    const req_resp_config_slice = std.mem.span(req_resp_config);
+    zig_handler.allocator.free(req_resp_config_slice);
+
+    const bootnodes_slice = std.mem.span(bootnodes);
+    zig_handler.allocator.free(bootnodes_slice);
+
+    const peer_store_path_slice = std.mem.span(peer_store_path);
+    zig_handler.allocator.free(peer_store_path_slice);
 }
 
 pub extern fn create_and_run_network(
@@ -892,17 +1007,73 @@ pub extern fn create_and_run_network(
     listen_addresses: [*:0]const u8,
     connect_addresses: [*:0]const u8,
     topics: [*:0]const u8,
+    peer_score_params: [*:0]const u8,
+    transport_config: [*:0]const u8,
+    gossipsub_config: [*:0]const u8,
+    req_resp_config: [*:0]const u8,
+    target_peers: u32,
+    max_peers: u32,
+    discovery_port: u16,
+    bootnodes: [*:0]const u8,
+    peer_store_path: [*:0]const u8,
+    decompress_rpc_payloads: bool,
 ) void;
+pub extern fn generate_local_enr(
+    handle: *EthLibp2p,
+    local_private_key: [*:0]const u8,
+    listen_addresses: [*:0]const u8,
+    ip: ?[*:0]const u8,
+    udp_port: u16,
+    buf: [*]u8,
+    buf_len: usize,
+) usize;
+/// Outcome of `wait_for_network_ready`, must stay in sync with `Readiness` in the rust bridge.
+pub const NetworkReadiness = enum(u32) {
+    ready = 0,
+    timed_out = 1,
+    /// The start failed, e.g. on an invalid address, key or config. The rust bridge logs why.
+    failed = 2,
+    _,
+};
 pub extern fn wait_for_network_ready(
     network_id: u32,
     timeout_ms: u64,
-) bool;
+) NetworkReadiness;
+pub extern fn stop_network(network_id: u32) bool;
 pub extern fn publish_msg_to_rust_bridge(
     networkId: u32,
     topic_str: [*:0]const u8,
     message_ptr: [*]const u8,
     message_len: usize,
 ) void;
+pub extern fn report_gossip_validation(
+    networkId: u32,
+    message_id: [*:0]const u8,
+    acceptance: u32,
+) void;
+pub extern fn subscribe_topic(networkId: u32, topic: [*:0]const u8) bool;
+pub extern fn unsubscribe_topic(networkId: u32, topic: [*:0]const u8) bool;
+pub extern fn ban_peer(networkId: u32, peer_id: [*:0]const u8, duration_secs: u64) bool;
+pub extern fn unban_peer(networkId: u32, peer_id: [*:0]const u8) bool;
+pub extern fn cancel_rpc_request(networkId: u32, request_id: u64) bool;
+pub extern fn disconnect_peer(networkId: u32, peer_id: [*:0]const u8, reason: u64) bool;
+pub extern fn set_local_status(
+    networkId: u32,
+    finalized_root: *const [32]u8,
+    finalized_slot: u64,
+    head_root: *const [32]u8,
+    head_slot: u64,
+) bool;
+// These return the length of their document and only write it if it fits in `buf`. The document
+// can grow between two calls, so callers retry with a buffer of the returned length until it fits.
+pub extern fn list_peers(networkId: u32, buf: [*]u8, buf_len: usize) usize;
+pub extern fn get_network_info(networkId: u32, buf: [*]u8, buf_len: usize) usize;
+pub extern fn get_metrics(networkId: u32, buf: [*]u8, buf_len: usize) usize;
+pub extern fn get_peer_score(
+    networkId: u32,
+    peer_id: [*:0]const u8,
+    score_out: *f64,
+) bool;
 pub extern fn send_rpc_request(
     networkId: u32,
     peer_id: [*:0]const u8,
@@ -910,6 +1081,14 @@ pub extern fn send_rpc_request(
     request_ptr: [*]const u8,
     request_len: usize,
 ) callconv(.c) u64;
+pub extern fn send_rpc_request_versions(
+    networkId: u32,
+    peer_id: [*:0]const u8,
+    protocol_tags: [*]const u32,
+    request_ptrs: [*]const [*]const u8,
+    request_lens: [*]const usize,
+    count: usize,
+) callconv(.c) u64;
 pub extern fn send_rpc_response_chunk(
     networkId: u32,
     channel_id: u64,
@@ -920,6 +1099,7 @@ pub extern fn send_rpc_end_of_stream(networkId: u32, channel_id: u64) callconv(.
 pub extern fn send_rpc_error_response(
     networkId: u32,
     channel_id: u64,
+    code: u32,
     message_ptr: [*:0]const u8,
 ) callconv(.c) void;
 
@@ -929,8 +1109,39 @@ pub const EthLibp2pParams = struct {
     local_private_key: []const u8,
     listen_addresses: []const Multiaddr,
     connect_peers: ?[]const Multiaddr,
+    /// Peers to connect to given as `enr:` records, dialed next to `connect_peers`.
+    connect_enrs: ?[]const []const u8 = null,
     node_registry: *const NodeNameRegistry,
     attestation_committee_count: types.SubnetId,
+    /// JSON gossipsub peer scoring parameters, see `PeerScoreConfig` in the rust glue.
+    /// Null selects the default scoring.
+    peer_score_params: ?[]const u8 = null,
+    /// JSON transport selection (tcp, quic or both), muxers, security and dial timeout, see
+    /// `TransportConfig` in the rust glue. Null selects TCP and QUIC with noise.
+    transport_config: ?[]const u8 = null,
+    /// JSON gossipsub mesh degrees, heartbeat, history and cache settings, see `GossipsubConfig`
+    /// in the rust glue. Null passes only the preset's seconds per slot, from which the glue
+    /// derives its defaults.
+    gossipsub_config: ?[]const u8 = null,
+    /// JSON req/resp settings, the fork digests used as context bytes, the outbound requests
+    /// in flight per peer and protocol and the inbound rate limits, see `ReqRespConfig` in the
+    /// rust glue. Null passes the fork digest derived from the network name and the defaults.
+    req_resp_config: ?[]const u8 = null,
+    /// Peer counts enforced by the rust peer manager, 0 selects its defaults.
+    target_peers: u32 = 0,
+    max_peers: u32 = 0,
+    /// UDP port of discv5 peer discovery, 0 disables discovery.
+    discovery_port: u16 = 0,
+    /// ENRs (`enr:` base64 strings) used to bootstrap discovery.
+    bootnodes: ?[]const []const u8 = null,
+    /// Data directory of the node, the peer store is kept in its `peers.json` by default.
+    data_dir: ?[]const u8 = null,
+    /// File the rust glue persists successfully dialed peers to and seeds dialing from on the
+    /// next start, overriding the one in `data_dir`. With neither, peers are kept in memory only.
+    peer_store_path: ?[]const u8 = null,
+    /// Exchange plain SSZ req/resp payloads with the rust glue, which then handles the snappy
+    /// framing and its checksums.
+    decompress_rpc_payloads: bool = false,
 };
 
 pub const EthLibp2p = struct {
@@ -940,12 +1151,22 @@ pub const EthLibp2p = struct {
     reqrespHandler: interface.ReqRespRequestHandler,
     params: EthLibp2pParams,
     rustBridgeThread: ?Thread = null,
+    // Last status set with `setLocalStatus`, passed to the rust glue whenever the network starts
+    local_status: ?types.Status = null,
     rpcCallbacks: std.AutoHashMapUnmanaged(u64, interface.ReqRespRequestCallback),
     logger: zeam_utils.ModuleLogger,
     node_registry: *const NodeNameRegistry,
 
     const Self = @This();
 
+    /// Fork digest of the network until the chain config defines forks: like the topics, it is
+    /// derived from the network name, as the first bytes of its sha256.
+    fn forkDigest(network_name: []const u8) [4]u8 {
+        var hash: [std.crypto.hash.sha2.Sha256.digest_length]u8 = undefined;
+        std.crypto.hash.sha2.Sha256.hash(network_name, &hash, .{});
+        return hash[0..4].*;
+    }
+
     fn getAttestationSubnetCount(committee_count: types.SubnetId) !usize {
         if (committee_count == 0) return error.InvalidAttestationCommitteeCount;
         return @intCast(committee_count);
@@ -977,8 +1198,20 @@ pub const EthLibp2p = struct {
                 .local_private_key = params.local_private_key,
                 .listen_addresses = params.listen_addresses,
                 .connect_peers = params.connect_peers,
+                .connect_enrs = params.connect_enrs,
                 .node_registry = params.node_registry,
                 .attestation_committee_count = params.attestation_committee_count,
+                .peer_score_params = params.peer_score_params,
+                .transport_config = params.transport_config,
+                .gossipsub_config = params.gossipsub_config,
+                .req_resp_config = params.req_resp_config,
+                .target_peers = params.target_peers,
+                .max_peers = params.max_peers,
+                .discovery_port = params.discovery_port,
+                .bootnodes = params.bootnodes,
+                .data_dir = params.data_dir,
+                .peer_store_path = params.peer_store_path,
+                .decompress_rpc_payloads = params.decompress_rpc_payloads,
             },
             .gossipHandler = gossip_handler,
             .peerEventHandler = peer_event_handler,
@@ -990,6 +1223,7 @@ pub const EthLibp2p = struct {
     }
 
     pub fn deinit(self: *Self) void {
+        self.stop();
         self.gossipHandler.deinit();
         self.peerEventHandler.deinit();
 
@@ -1012,11 +1246,28 @@ pub const EthLibp2p = struct {
 
     pub fn run(self: *Self) !void {
         const listen_addresses_str = try multiaddrsToString(self.allocator, self.params.listen_addresses);
-        const connect_peers_str = if (self.params.connect_peers) |peers|
-            try multiaddrsToString(self.allocator, peers)
+        const connect_peers_str = try self.connectAddressesToString();
+        const local_private_key = try self.allocator.dupeZ(u8, self.params.local_private_key);
+        const peer_score_params = try self.allocator.dupeZ(u8, self.params.peer_score_params orelse "");
+        const transport_config = try self.allocator.dupeZ(u8, self.params.transport_config orelse "");
+        const gossipsub_config = if (self.params.gossipsub_config) |config|
+            try self.allocator.dupeZ(u8, config)
+        else
+            try std.fmt.allocPrintSentinel(self.allocator, "{{\"seconds_per_slot\":{d}}}", .{consensus_params.SECONDS_PER_SLOT}, 0);
+        const req_resp_config = if (self.params.req_resp_config) |config|
+            try self.allocator.dupeZ(u8, config)
+        else
+            try std.fmt.allocPrintSentinel(self.allocator, "{{\"fork_digest\":\"0x{s}\"}}", .{&std.fmt.bytesToHex(forkDigest(self.params.network_name), .lower)}, 0);
+        const bootnodes_str = if (self.params.bootnodes) |bootnodes|
+            try std.mem.joinZ(self.allocator, ",", bootnodes)
+        else
+            try self.allocator.dupeZ(u8, "");
+        const peer_store_path = if (self.params.peer_store_path) |path|
+            try self.allocator.dupeZ(u8, path)
+        else if (self.params.data_dir) |data_dir|
+            try std.fmt.allocPrintSentinel(self.allocator, "{s}/peers.json", .{data_dir}, 0)
         else
             try self.allocator.dupeZ(u8, "");
-        const local_private_key = try self.allocator.dupeZ(u8, self.params.local_private_key);
 
         var topics_list: std.ArrayList([]const u8) = .empty;
         defer {
@@ -1050,19 +1301,188 @@ pub const EthLibp2p = struct {
         }
         const topics_str = try std.mem.joinZ(self.allocator, ",", topics_list.items);
 
-        self.rustBridgeThread = try Thread.spawn(.{}, create_and_run_network, .{ self.params.networkId, self, local_private_key.ptr, listen_addresses_str.ptr, connect_peers_str.ptr, topics_str.ptr });
+        self.rustBridgeThread = try Thread.spawn(.{}, create_and_run_network, .{ self.params.networkId, self, local_private_key.ptr, listen_addresses_str.ptr, connect_peers_str.ptr, topics_str.ptr, peer_score_params.ptr, transport_config.ptr, gossipsub_config.ptr, req_resp_config.ptr, self.params.target_peers, self.params.max_peers, self.params.discovery_port, bootnodes_str.ptr, peer_store_path.ptr, self.params.decompress_rpc_payloads });
 
         // Wait for the network to be fully initialized before returning
         // Use a 10 second timeout to avoid hanging indefinitely
         const timeout_ms: u64 = 10000;
         self.logger.debug("network-{d}:: Waiting for network initialization to complete...", .{self.params.networkId});
 
-        if (!wait_for_network_ready(self.params.networkId, timeout_ms)) {
-            self.logger.err("network-{d}:: Network failed to initialize within {d}ms timeout", .{ self.params.networkId, timeout_ms });
-            return error.NetworkInitializationTimeout;
+        switch (wait_for_network_ready(self.params.networkId, timeout_ms)) {
+            .ready => {},
+            .timed_out => {
+                self.logger.err("network-{d}:: Network failed to initialize within {d}ms timeout", .{ self.params.networkId, timeout_ms });
+                return error.NetworkInitializationTimeout;
+            },
+            .failed, _ => {
+                self.logger.err("network-{d}:: Network failed to start, see the rust bridge error above", .{self.params.networkId});
+                self.rustBridgeThread.?.join();
+                self.rustBridgeThread = null;
+                return error.NetworkStartFailed;
+            },
         }
 
         self.logger.info("network-{d}:: Network initialization complete, ready to send/receive messages", .{self.params.networkId});
+        if (self.local_status) |status| try self.setLocalStatus(status);
+        zeam_metrics.setExternalMetricsSource(.{ .ptr = self, .writeFn = writeRustMetrics });
+    }
+
+    /// Stops the rust network and joins its bridge thread, releasing the swarm so the same
+    /// network id can be started again.
+    pub fn stop(self: *Self) void {
+        const thread = self.rustBridgeThread orelse return;
+        self.rustBridgeThread = null;
+        zeam_metrics.clearExternalMetricsSource(self);
+
+        if (stop_network(self.params.networkId)) {
+            thread.join();
+            self.logger.info("network-{d}:: Network stopped", .{self.params.networkId});
+        } else {
+            // The network never became ready; its thread exits on its own.
+            self.logger.warn("network-{d}:: No running network to stop", .{self.params.networkId});
+            thread.detach();
+        }
+    }
+
+    /// Subscribes to a gossip topic while the network is running, e.g. when rotating attestation
+    /// subnets or transitioning forks.
+    pub fn subscribeTopic(self: *Self, gossip_topic: interface.GossipTopic) !void {
+        var topic = try interface.LeanNetworkTopic.init(self.allocator, gossip_topic, .ssz_snappy, self.params.network_name);
+        defer topic.deinit();
+        const topic_str = try topic.encodeZ();
+        defer self.allocator.free(topic_str);
+
+        if (!subscribe_topic(self.params.networkId, topic_str.ptr)) return error.NetworkNotRunning;
+    }
+
+    pub fn unsubscribeTopic(self: *Self, gossip_topic: interface.GossipTopic) !void {
+        var topic = try interface.LeanNetworkTopic.init(self.allocator, gossip_topic, .ssz_snappy, self.params.network_name);
+        defer topic.deinit();
+        const topic_str = try topic.encodeZ();
+        defer self.allocator.free(topic_str);
+
+        if (!unsubscribe_topic(self.params.networkId, topic_str.ptr)) return error.NetworkNotRunning;
+    }
+
+    /// Bans a peer for `duration_secs` seconds (0 selects the rust default) and disconnects it.
+    pub fn banPeer(self: *Self, peer_id: []const u8, duration_secs: u64) !void {
+        const peer_id_cstr = try self.allocator.dupeZ(u8, peer_id);
+        defer self.allocator.free(peer_id_cstr);
+
+        if (!ban_peer(self.params.networkId, peer_id_cstr.ptr, duration_secs)) return error.BanPeerFailed;
+    }
+
+    pub fn unbanPeer(self: *Self, peer_id: []const u8) !void {
+        const peer_id_cstr = try self.allocator.dupeZ(u8, peer_id);
+        defer self.allocator.free(peer_id_cstr);
+
+        if (!unban_peer(self.params.networkId, peer_id_cstr.ptr)) return error.UnbanPeerFailed;
+    }
+
+    /// Sends a goodbye/1 request with `reason` to a peer and disconnects it.
+    pub fn disconnectPeer(self: *Self, peer_id: []const u8, reason: interface.GoodbyeReason) !void {
+        const peer_id_cstr = try self.allocator.dupeZ(u8, peer_id);
+        defer self.allocator.free(peer_id_cstr);
+
+        if (!disconnect_peer(self.params.networkId, peer_id_cstr.ptr, @intFromEnum(reason))) return error.DisconnectPeerFailed;
+    }
+
+    /// Sets the status the rust glue exchanges with every connected peer. Once set, the glue runs
+    /// the Status handshake and answers Status requests itself, and reports the status of peers
+    /// through `PeerEventHandler.onPeerStatus`. A status set before `run()` is passed to the glue
+    /// once the network is ready.
+    pub fn setLocalStatus(self: *Self, status: types.Status) !void {
+        self.local_status = status;
+        if (self.rustBridgeThread == null) return;
+        if (!set_local_status(self.params.networkId, &status.finalized_root, status.finalized_slot, &status.head_root, status.head_slot)) {
+            return error.SetLocalStatusFailed;
+        }
+    }
+
+    /// Returns the JSON peer list of the rust peer manager. The caller owns the returned slice.
+    pub fn listPeers(self: *Self, allocator: Allocator) ![]u8 {
+        var buf = try allocator.alloc(u8, 4096);
+        errdefer allocator.free(buf);
+
+        while (true) {
+            const len = list_peers(self.params.networkId, buf.ptr, buf.len);
+            if (len == 0) return error.NetworkNotRunning;
+            if (len <= buf.len) return allocator.realloc(buf, len);
+            buf = try allocator.realloc(buf, len);
+        }
+    }
+
+    /// Builds and signs the ENR of this node from its key, listen addresses and discovery port.
+    /// `ip` overrides the advertised ip, which is required when listening on a wildcard address.
+    /// The caller owns the returned `enr:` string.
+    pub fn localEnr(self: *Self, allocator: Allocator, ip: ?[]const u8) ![]u8 {
+        const listen_addresses_str = try multiaddrsToString(self.allocator, self.params.listen_addresses);
+        defer self.allocator.free(listen_addresses_str);
+        const local_private_key = try self.allocator.dupeZ(u8, self.params.local_private_key);
+        defer self.allocator.free(local_private_key);
+        const ip_str = if (ip) |value| try self.allocator.dupeZ(u8, value) else null;
+        defer if (ip_str) |value| self.allocator.free(value);
+
+        var buf = try allocator.alloc(u8, 512);
+        errdefer allocator.free(buf);
+
+        while (true) {
+            const len = generate_local_enr(self, local_private_key.ptr, listen_addresses_str.ptr, if (ip_str) |value| value.ptr else null, self.params.discovery_port, buf.ptr, buf.len);
+            if (len == 0) return error.InvalidEnrParams;
+            if (len <= buf.len) return allocator.realloc(buf, len);
+            buf = try allocator.realloc(buf, len);
+        }
+    }
+
+    /// Returns a JSON document with the local peer id, listen addresses, connected peers (direction,
+    /// addresses, agent version, ping RTT, protocols) and the mesh peers of every topic.
+    /// The caller owns the returned slice.
+    pub fn getNetworkInfo(self: *Self, allocator: Allocator) ![]u8 {
+        var buf = try allocator.alloc(u8, 8192);
+        errdefer allocator.free(buf);
+
+        while (true) {
+            // Peers can connect or finish identify between calls and grow the document
+            const len = get_network_info(self.params.networkId, buf.ptr, buf.len);
+            if (len == 0) return error.NetworkNotRunning;
+            if (len <= buf.len) return allocator.realloc(buf, len);
+            buf = try allocator.realloc(buf, len);
+        }
+    }
+
+    /// Returns the libp2p and req/resp metrics of the rust network in the OpenMetrics text format.
+    /// The caller owns the returned slice.
+    pub fn getMetrics(self: *Self, allocator: Allocator) ![]u8 {
+        var buf = try allocator.alloc(u8, 64 * 1024);
+        errdefer allocator.free(buf);
+
+        while (true) {
+            const len = get_metrics(self.params.networkId, buf.ptr, buf.len);
+            if (len == 0) return error.NetworkNotRunning;
+            if (len <= buf.len) return allocator.realloc(buf, len);
+            buf = try allocator.realloc(buf, len);
+        }
+    }
+
+    fn writeRustMetrics(ptr: *anyopaque, writer: *std.Io.Writer) !void {
+        const self: *Self = @ptrCast(@alignCast(ptr));
+        const text = try self.getMetrics(self.allocator);
+        defer self.allocator.free(text);
+
+        // The node serves the Prometheus text format, which has no EOF marker.
+        const eof_marker = "# EOF\n";
+        const body = if (std.mem.endsWith(u8, text, eof_marker)) text[0 .. text.len - eof_marker.len] else text;
+        try writer.writeAll(body);
+    }
+
+    /// Returns the current gossipsub score of a peer, or null if it is not connected or scoring is disabled.
+    pub fn getPeerScore(self: *Self, peer_id: []const u8) !?f64 {
+        const peer_id_cstr = try self.allocator.dupeZ(u8, peer_id);
+        defer self.allocator.free(peer_id_cstr);
+
+        var score: f64 = 0;
+        if (!get_peer_score(self.params.networkId, peer_id_cstr.ptr, &score)) return null;
+        return score;
     }
 
     pub fn publish(ptr: *anyopaque, data: *const interface.GossipMessage) anyerror!void {
@@ -1118,30 +1538,42 @@ pub const EthLibp2p = struct {
 
         defer self.allocator.free(encoded_message);
 
-        const framed_payload = snappyframesz.encode(self.allocator, encoded_message) catch |err| {
-            self.logger.err(
-                "network-{d}:: Failed to snappy-frame RPC request payload for peer={s}{f} protocol_tag={d}: {any}",
-                .{ self.params.networkId, peer_id, node_name, protocol_tag, err },
-            );
-            return err;
-        };
-        defer self.allocator.free(framed_payload);
+        // With decompress_rpc_payloads the glue snappy-frames the SSZ bytes itself
+        const frame = if (self.params.decompress_rpc_payloads) try self.allocator.dupe(u8, encoded_message) else blk: {
+            const framed_payload = snappyframesz.encode(self.allocator, encoded_message) catch |err| {
+                self.logger.err(
+                    "network-{d}:: Failed to snappy-frame RPC request payload for peer={s}{f} protocol_tag={d}: {any}",
+                    .{ self.params.networkId, peer_id, node_name, protocol_tag, err },
+                );
+                return err;
+            };
+            defer self.allocator.free(framed_payload);
 
-        const frame = buildRequestFrame(self.allocator, encoded_message.len, framed_payload) catch |err| {
-            self.logger.err(
-                "network-{d}:: Failed to build RPC request frame for peer={s}{f} protocol_tag={d}: {any}",
-                .{ self.params.networkId, peer_id, node_name, protocol_tag, err },
-            );
-            return err;
+            break :blk buildRequestFrame(self.allocator, encoded_message.len, framed_payload) catch |err| {
+                self.logger.err(
+                    "network-{d}:: Failed to build RPC request frame for peer={s}{f} protocol_tag={d}: {any}",
+                    .{ self.params.networkId, peer_id, node_name, protocol_tag, err },
+                );
+                return err;
+            };
         };
         defer self.allocator.free(frame);
 
-        const request_id = send_rpc_request(
+        // Every version of a message shares its request encoding, offer them all newest first
+        const version_tags = method.versionTags();
+        var frame_ptrs: [MAX_PROTOCOL_VERSIONS][*]const u8 = undefined;
+        var frame_lens: [MAX_PROTOCOL_VERSIONS]usize = undefined;
+        for (0..version_tags.len) |i| {
+            frame_ptrs[i] = frame.ptr;
+            frame_lens[i] = frame.len;
+        }
+        const request_id = send_rpc_request_versions(
             self.params.networkId,
             peer_id_cstr.ptr,
-            protocol_tag,
-            frame.ptr,
-            frame.len,
+            version_tags.ptr,
+            &frame_ptrs,
+            &frame_lens,
+            version_tags.len,
         );
 
         if (request_id == 0) {
@@ -1167,6 +1599,17 @@ pub const EthLibp2p = struct {
         return request_id;
     }
 
+    /// Aborts an RPC request sent with `sendRPCRequest`. Its callback is dropped without being
+    /// notified, the rust glue resets the stream and reports nothing more for the request.
+    pub fn cancelRPCRequest(self: *Self, request_id: u64) !void {
+        if (self.rpcCallbacks.fetchRemove(request_id)) |entry| {
+            var callback = entry.value;
+            callback.deinit();
+        }
+
+        if (!cancel_rpc_request(self.params.networkId, request_id)) return error.NetworkNotRunning;
+    }
+
     fn notifyRpcErrorWithOwnedMessage(
         self: *Self,
         request_id: u64,
@@ -1236,6 +1679,12 @@ pub const EthLibp2p = struct {
         return self.peerEventHandler.subscribe(handler);
     }
 
+    /// The glue serves a single node, so the status applies to every subscriber.
+    pub fn setSubscriberLocalStatus(ptr: *anyopaque, _: interface.OnPeerEventCbHandler, status: types.Status) anyerror!void {
+        const self: *Self = @ptrCast(@alignCast(ptr));
+        return self.setLocalStatus(status);
+    }
+
     pub fn getNetworkInterface(self: *Self) NetworkInterface {
         return .{
             .gossip = .{
@@ -1253,10 +1702,27 @@ pub const EthLibp2p = struct {
             .peers = .{
                 .ptr = self,
                 .subscribeFn = subscribePeerEvents,
+                .setLocalStatusFn = setSubscriberLocalStatus,
             },
         };
     }
 
+    /// Joins the connect multiaddrs and ENRs into the comma-separated list expected by the rust glue.
+    fn connectAddressesToString(self: *Self) ![:0]u8 {
+        const multiaddrs_str = if (self.params.connect_peers) |peers|
+            try multiaddrsToString(self.allocator, peers)
+        else
+            try self.allocator.dupeZ(u8, "");
+        const enrs = self.params.connect_enrs orelse return multiaddrs_str;
+        defer self.allocator.free(multiaddrs_str);
+
+        var parts: std.ArrayList([]const u8) = .empty;
+        defer parts.deinit(self.allocator);
+        if (multiaddrs_str.len > 0) try parts.append(self.allocator, multiaddrs_str);
+        try parts.appendSlice(self.allocator, enrs);
+        return std.mem.joinZ(self.allocator, ",", parts.items);
+    }
+
     fn multiaddrsToString(allocator: Allocator, addrs: []const Multiaddr) ![:0]u8 {
         if (addrs.len == 0) {
             return try allocator.dupeZ(u8, "");
@@ -33,9 +33,24 @@ pub const DisconnectionReason = enum(u32) {
     error_ = 3,
 };
 
+// Reason code of a goodbye/1 request, peers may send values not listed here
+pub const GoodbyeReason = enum(u64) {
+    client_shutdown = 1,
+    irrelevant_network = 2,
+    fault = 3,
+    too_many_peers = 129,
+    bad_score = 250,
+    banned = 251,
+    _,
+};
+
 const topic_prefix = "leanconsensus";
 const lean_blocks_by_root_protocol = "/leanconsensus/req/blocks_by_root/1/ssz_snappy";
 const lean_status_protocol = "/leanconsensus/req/status/1/ssz_snappy";
+const lean_blocks_by_range_protocol = "/leanconsensus/req/blocks_by_range/1/ssz_snappy";
+// v2 of the block protocols carries the fork digest of every block as context bytes
+const lean_blocks_by_root_v2_protocol = "/leanconsensus/req/blocks_by_root/2/ssz_snappy";
+const lean_blocks_by_range_v2_protocol = "/leanconsensus/req/blocks_by_range/2/ssz_snappy";
 
 fn freeJsonValue(val: *json.Value, allocator: Allocator) void {
     switch (val.*) {
@@ -98,10 +113,17 @@ pub const PeerEvents = struct {
     // ptr to the implementation
     ptr: *anyopaque,
     subscribeFn: *const fn (ptr: *anyopaque, handler: OnPeerEventCbHandler) anyerror!void,
+    setLocalStatusFn: *const fn (ptr: *anyopaque, handler: OnPeerEventCbHandler, status: types.Status) anyerror!void,
 
     pub fn subscribe(self: PeerEvents, handler: OnPeerEventCbHandler) anyerror!void {
         return self.subscribeFn(self.ptr, handler);
     }
+
+    /// Sets the chain status of the subscriber `handler`. The backend exchanges it in the Status
+    /// handshake with every connected peer and reports their statuses through `onPeerStatus`.
+    pub fn setLocalStatus(self: PeerEvents, handler: OnPeerEventCbHandler, status: types.Status) anyerror!void {
+        return self.setLocalStatusFn(self.ptr, handler, status);
+    }
 };
 
 pub const NetworkInterface = struct {
@@ -354,14 +376,36 @@ pub const GossipMessage = union(GossipTopicKind) {
     }
 };
 
+// The tag values are shared with the rust bridge as the tags of the v1 protocols, keep them and
+// `versionTags` in sync with its `LeanSupportedProtocol`
 pub const LeanSupportedProtocol = enum {
     blocks_by_root,
     status,
+    blocks_by_range,
 
     pub fn protocolId(self: LeanSupportedProtocol) []const u8 {
         return switch (self) {
             .blocks_by_root => lean_blocks_by_root_protocol,
             .status => lean_status_protocol,
+            .blocks_by_range => lean_blocks_by_range_protocol,
+        };
+    }
+
+    /// Protocol ids of every version of the message, newest first.
+    pub fn protocolIds(self: LeanSupportedProtocol) []const []const u8 {
+        return switch (self) {
+            .blocks_by_root => &.{ lean_blocks_by_root_v2_protocol, lean_blocks_by_root_protocol },
+            .status => &.{lean_status_protocol},
+            .blocks_by_range => &.{ lean_blocks_by_range_v2_protocol, lean_blocks_by_range_protocol },
+        };
+    }
+
+    /// Rust bridge tags of the versions offered for a request, in the order of `protocolIds`.
+    pub fn versionTags(self: LeanSupportedProtocol) []const u32 {
+        return switch (self) {
+            .blocks_by_root => &.{ 3, 0 },
+            .status => &.{1},
+            .blocks_by_range => &.{ 4, 2 },
         };
     }
 
@@ -369,30 +413,26 @@ pub const LeanSupportedProtocol = enum {
         return @tagName(self);
     }
 
+    /// Maps the protocol id of any version to its message.
     pub fn fromSlice(slice: []const u8) ?LeanSupportedProtocol {
         const protocols = comptime std.enums.values(LeanSupportedProtocol);
         inline for (protocols) |value| {
-            if (std.mem.eql(u8, slice, value.protocolId())) return value;
+            for (value.protocolIds()) |protocol_id| {
+                if (std.mem.eql(u8, slice, protocol_id)) return value;
+            }
         }
         return null;
     }
 
     pub fn fromProtocolId(protocol_id: []const u8) !LeanSupportedProtocol {
-        if (std.mem.eql(u8, protocol_id, lean_status_protocol)) {
-            return .status;
-        }
-
-        if (std.mem.eql(u8, protocol_id, lean_blocks_by_root_protocol)) {
-            return .blocks_by_root;
-        }
-
-        return error.UnsupportedProtocol;
+        return fromSlice(protocol_id) orelse error.UnsupportedProtocol;
     }
 };
 
 pub const ReqRespRequest = union(LeanSupportedProtocol) {
     blocks_by_root: types.BlockByRootRequest,
     status: types.Status,
+    blocks_by_range: types.BlocksByRangeRequest,
 
     const Self = @This();
 
@@ -400,6 +440,11 @@ pub const ReqRespRequest = union(LeanSupportedProtocol) {
         switch (self) {
             .blocks_by_root => try writer.writeAll("ReqRespRequest{ blocks_by_root }"),
             .status => try writer.writeAll("ReqRespRequest{ status }"),
+            .blocks_by_range => |request| try writer.print("ReqRespRequest{{ blocks_by_range: start_slot={d} count={d} step={d} }}", .{
+                request.start_slot,
+                request.count,
+                request.step,
+            }),
         }
     }
 
@@ -407,6 +452,7 @@ pub const ReqRespRequest = union(LeanSupportedProtocol) {
         return switch (self.*) {
             .status => |status| status.toJson(allocator),
             .blocks_by_root => |request| request.toJson(allocator),
+            .blocks_by_range => |request| request.toJson(allocator),
         };
     }
 
@@ -470,13 +516,14 @@ pub const ReqRespRequest = union(LeanSupportedProtocol) {
 pub const ReqRespResponse = union(LeanSupportedProtocol) {
     blocks_by_root: types.SignedBlockWithAttestation,
     status: types.Status,
+    blocks_by_range: types.SignedBlockWithAttestation,
 
     const Self = @This();
 
     pub fn toJson(self: *const ReqRespResponse, allocator: Allocator) !json.Value {
         return switch (self.*) {
             .status => |status| status.toJson(allocator),
-            .blocks_by_root => |block| block.toJson(allocator),
+            .blocks_by_root, .blocks_by_range => |block| block.toJson(allocator),
         };
     }
 
@@ -514,7 +561,7 @@ pub const ReqRespResponse = union(LeanSupportedProtocol) {
     pub fn deinit(self: *ReqRespResponse) void {
         switch (self.*) {
             .status => {},
-            .blocks_by_root => |*block| block.deinit(),
+            .blocks_by_root, .blocks_by_range => |*block| block.deinit(),
         }
     }
 };
@@ -567,6 +614,8 @@ pub const ReqRespResponseEvent = struct {
     method: LeanSupportedProtocol,
     request_id: u64,
     payload: Payload,
+    /// Fork digest of a success chunk, for protocols with context bytes.
+    context_bytes: ?[4]u8 = null,
 
     const Payload = union(enum) {
         success: ReqRespResponse,
@@ -758,12 +807,18 @@ const MessagePublishWrapper = struct {
 pub const OnPeerConnectedCbType = *const fn (*anyopaque, peer_id: []const u8, direction: PeerDirection) anyerror!void;
 pub const OnPeerDisconnectedCbType = *const fn (*anyopaque, peer_id: []const u8, direction: PeerDirection, reason: DisconnectionReason) anyerror!void;
 pub const OnPeerConnectionFailedCbType = *const fn (*anyopaque, peer_id: []const u8, direction: PeerDirection, result: ConnectionResult) anyerror!void;
+pub const OnPeerStatusCbType = *const fn (*anyopaque, peer_id: []const u8, status: types.Status) anyerror!void;
+pub const OnPeerGoodbyeCbType = *const fn (*anyopaque, peer_id: []const u8, reason: GoodbyeReason) anyerror!void;
 
 pub const OnPeerEventCbHandler = struct {
     ptr: *anyopaque,
     onPeerConnectedCb: OnPeerConnectedCbType,
     onPeerDisconnectedCb: OnPeerDisconnectedCbType,
     onPeerConnectionFailedCb: ?OnPeerConnectionFailedCbType = null,
+    /// Status of a peer learned by the network's own Status handshake.
+    onPeerStatusCb: ?OnPeerStatusCbType = null,
+    /// Reason of a peer's goodbye/1 request, received right before it disconnects.
+    onPeerGoodbyeCb: ?OnPeerGoodbyeCbType = null,
 
     pub fn onPeerConnected(self: OnPeerEventCbHandler, peer_id: []const u8, direction: PeerDirection) anyerror!void {
         return self.onPeerConnectedCb(self.ptr, peer_id, direction);
@@ -778,6 +833,18 @@ pub const OnPeerEventCbHandler = struct {
             return cb(self.ptr, peer_id, direction, result);
         }
     }
+
+    pub fn onPeerStatus(self: OnPeerEventCbHandler, peer_id: []const u8, status: types.Status) anyerror!void {
+        if (self.onPeerStatusCb) |cb| {
+            return cb(self.ptr, peer_id, status);
+        }
+    }
+
+    pub fn onPeerGoodbye(self: OnPeerEventCbHandler, peer_id: []const u8, reason: GoodbyeReason) anyerror!void {
+        if (self.onPeerGoodbyeCb) |cb| {
+            return cb(self.ptr, peer_id, reason);
+        }
+    }
 };
 
 pub const PeerEventHandler = struct {
@@ -835,6 +902,26 @@ pub const PeerEventHandler = struct {
             };
         }
     }
+
+    pub fn onPeerStatus(self: *Self, peer_id: []const u8, status: types.Status) anyerror!void {
+        const node_name = self.node_registry.getNodeNameFromPeerId(peer_id);
+        self.logger.debug("network-{d}:: PeerEventHandler.onPeerStatus peer_id={s}{f} head_slot={d} finalized_slot={d}, handlers={d}", .{ self.networkId, peer_id, node_name, status.head_slot, status.finalized_slot, self.handlers.items.len });
+        for (self.handlers.items) |handler| {
+            handler.onPeerStatus(peer_id, status) catch |e| {
+                self.logger.err("network-{d}:: onPeerStatus handler error={any}", .{ self.networkId, e });
+            };
+        }
+    }
+
+    pub fn onPeerGoodbye(self: *Self, peer_id: []const u8, reason: GoodbyeReason) anyerror!void {
+        const node_name = self.node_registry.getNodeNameFromPeerId(peer_id);
+        self.logger.debug("network-{d}:: PeerEventHandler.onPeerGoodbye peer_id={s}{f} reason={d}, handlers={d}", .{ self.networkId, peer_id, node_name, @intFromEnum(reason), self.handlers.items.len });
+        for (self.handlers.items) |handler| {
+            handler.onPeerGoodbye(peer_id, reason) catch |e| {
+                self.logger.err("network-{d}:: onPeerGoodbye handler error={any}", .{ self.networkId, e });
+            };
+        }
+    }
 };
 
 pub const GenericGossipHandler = struct {
@@ -975,6 +1062,19 @@ test GossipTopic {
     try std.testing.expectError(error.InvalidDecoding, GossipTopic.decode("invalid"));
 }
 
+test LeanSupportedProtocol {
+    try std.testing.expectEqual(LeanSupportedProtocol.blocks_by_root, LeanSupportedProtocol.fromSlice("/leanconsensus/req/blocks_by_root/2/ssz_snappy").?);
+    try std.testing.expectEqual(LeanSupportedProtocol.blocks_by_range, try LeanSupportedProtocol.fromProtocolId("/leanconsensus/req/blocks_by_range/1/ssz_snappy"));
+    try std.testing.expectError(error.UnsupportedProtocol, LeanSupportedProtocol.fromProtocolId("/leanconsensus/req/status/2/ssz_snappy"));
+
+    // The v1 tag of every message is its enum value and comes last
+    for (std.enums.values(LeanSupportedProtocol)) |protocol| {
+        const tags = protocol.versionTags();
+        try std.testing.expectEqual(protocol.protocolIds().len, tags.len);
+        try std.testing.expectEqual(@as(u32, @intFromEnum(protocol)), tags[tags.len - 1]);
+    }
+}
+
 test LeanNetworkTopic {
     const allocator = std.testing.allocator;
 
@@ -24,6 +24,7 @@ pub const PeerEventHandler = interfaceFactory.PeerEventHandler;
 pub const PeerDirection = interfaceFactory.PeerDirection;
 pub const ConnectionResult = interfaceFactory.ConnectionResult;
 pub const DisconnectionReason = interfaceFactory.DisconnectionReason;
+pub const GoodbyeReason = interfaceFactory.GoodbyeReason;
 pub const GenericGossipHandler = interfaceFactory.GenericGossipHandler;
 pub const ReqRespServerStream = interfaceFactory.ReqRespServerStream;
 pub const OnReqRespResponseCbHandler = interfaceFactory.OnReqRespResponseCbHandler;
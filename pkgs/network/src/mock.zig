@@ -46,6 +46,7 @@ pub const Mock = struct {
         peer_id: ?[]u8 = null,
         req_handler: ?interface.OnReqRespRequestCbHandler = null,
         event_handler: ?interface.OnPeerEventCbHandler = null,
+        local_status: ?types.Status = null,
 
         fn isReady(self: *const Peer) bool {
             return self.req_handler != null and self.event_handler != null and self.peer_id != null;
@@ -93,7 +94,7 @@ pub const Mock = struct {
                 .status => |status_req| {
                     task.payload = .{ .success = interface.ReqRespResponse{ .status = status_req } };
                 },
-                .blocks_by_root => {
+                .blocks_by_root, .blocks_by_range => {
                     task.payload = .{ .failure = .{ .code = 1, .message = "mock peer has no block data" } };
                 },
             }
@@ -420,6 +421,25 @@ pub const Mock = struct {
         peer_b.event_handler.?.onPeerConnected(peer_a_id, .inbound) catch |e| {
             self.logger.err("mock:: Failed delivering onPeerConnected to peer {s}: {any}", .{ peer_a_id, e });
         };
+
+        self.exchangeStatus(idx_a, idx_b);
+    }
+
+    /// Reports the local status of each peer of a connected pair to the other one, like the
+    /// Status handshake of the rust glue. Peers without a local status are skipped.
+    fn exchangeStatus(self: *Self, idx_a: usize, idx_b: usize) void {
+        self.reportStatus(idx_a, idx_b);
+        self.reportStatus(idx_b, idx_a);
+    }
+
+    fn reportStatus(self: *Self, from_idx: usize, to_idx: usize) void {
+        // Copy everything out first, the handler may add peers and move `self.peers`
+        const from = self.peers.items[from_idx];
+        const status = from.local_status orelse return;
+        const handler = self.peers.items[to_idx].event_handler orelse return;
+        handler.onPeerStatus(from.peer_id.?, status) catch |e| {
+            self.logger.err("mock:: Failed delivering onPeerStatus of peer {s}: {any}", .{ from.peer_id.?, e });
+        };
     }
 
     fn maybeConnectPeers(self: *Self, idx: usize) void {
@@ -442,6 +462,11 @@ pub const Mock = struct {
                 try types.sszClone(self.allocator, types.SignedBlockWithAttestation, block_resp, &cloned_block);
                 break :blk interface.ReqRespResponse{ .blocks_by_root = cloned_block };
             },
+            .blocks_by_range => |block_resp| blk: {
+                var cloned_block: types.SignedBlockWithAttestation = undefined;
+                try types.sszClone(self.allocator, types.SignedBlockWithAttestation, block_resp, &cloned_block);
+                break :blk interface.ReqRespResponse{ .blocks_by_range = cloned_block };
+            },
         };
     }
 
@@ -453,6 +478,7 @@ pub const Mock = struct {
                 try types.sszClone(self.allocator, types.BlockByRootRequest, block_req, &cloned_request);
                 break :blk interface.ReqRespRequest{ .blocks_by_root = cloned_request };
             },
+            .blocks_by_range => |range_req| interface.ReqRespRequest{ .blocks_by_range = range_req },
         };
     }
 
@@ -729,6 +755,20 @@ pub const Mock = struct {
         self.maybeConnectPeers(entry.idx);
     }
 
+    pub fn setSubscriberLocalStatus(ptr: *anyopaque, handler: interface.OnPeerEventCbHandler, status: types.Status) anyerror!void {
+        const self: *Self = @ptrCast(@alignCast(ptr));
+        const entry = try self.getOrCreatePeerEntry(handler.ptr);
+        const first_status = entry.peer.local_status == null;
+        entry.peer.local_status = status;
+        if (!first_status) return;
+
+        // Like the rust glue, peers connected before the first status get their handshake now
+        var pair_it = self.connectedPairs.keyIterator();
+        while (pair_it.next()) |pair| {
+            if (pair.a == entry.idx or pair.b == entry.idx) self.exchangeStatus(pair.a, pair.b);
+        }
+    }
+
     pub fn getNetworkInterface(self: *Self) NetworkInterface {
         return .{
             .gossip = .{
@@ -746,6 +786,7 @@ pub const Mock = struct {
             .peers = .{
                 .ptr = self,
                 .subscribeFn = subscribePeerEvents,
+                .setLocalStatusFn = setSubscriberLocalStatus,
             },
         };
     }
@@ -904,7 +945,7 @@ test "Mock status RPC between peers" {
                     try stream.sendResponse(&response);
                     try stream.finish();
                 },
-                .blocks_by_root => {
+                .blocks_by_root, .blocks_by_range => {
                     try stream.sendError(1, "unsupported");
                 },
             }
@@ -915,7 +956,7 @@ test "Mock status RPC between peers" {
             switch (event.payload) {
                 .success => |resp| switch (resp) {
                     .status => |status_resp| self.received_status = status_resp,
-                    .blocks_by_root => {
+                    .blocks_by_root, .blocks_by_range => {
                         self.failures += 1;
                     },
                 },
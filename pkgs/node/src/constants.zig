@@ -13,6 +13,10 @@ pub const MAX_FUTURE_SLOT_TOLERANCE = 1;
 // When fetching parent blocks, we stop after this many levels to avoid infinite loops
 pub const MAX_BLOCK_FETCH_DEPTH = 512;
 
+// Head slot distance to a peer above which sync requests blocks_by_range batches instead of
+// walking back from the peer's head block by root
+pub const RANGE_SYNC_MIN_DISTANCE: u64 = 32;
+
 // Maximum number of blocks to keep in the fetched blocks cache
 // This prevents unbounded memory growth from malicious peers sending orphaned blocks
 pub const MAX_CACHED_BLOCKS = 1024;
@@ -31,16 +31,38 @@ pub const BlockByRootContext = struct {
     }
 };
 
+pub const BlocksByRangeContext = struct {
+    peer_id: []const u8,
+    start_slot: types.Slot,
+    count: u64,
+    step: u64,
+    // blocks received so far and the slot of the last one, to check the response stream
+    received: u64 = 0,
+    last_slot: ?types.Slot = null,
+
+    pub fn deinit(self: *BlocksByRangeContext, allocator: Allocator) void {
+        allocator.free(self.peer_id);
+    }
+};
+
 pub const PendingRPC = union(enum) {
     status: StatusRequestContext,
     blocks_by_root: BlockByRootContext,
+    blocks_by_range: BlocksByRangeContext,
 
     pub fn deinit(self: *PendingRPC, allocator: Allocator) void {
         switch (self.*) {
             .status => |*ctx| ctx.deinit(allocator),
             .blocks_by_root => |*ctx| ctx.deinit(allocator),
+            .blocks_by_range => |*ctx| ctx.deinit(allocator),
         }
     }
+
+    pub fn peerId(self: *const PendingRPC) []const u8 {
+        return switch (self.*) {
+            inline else => |*ctx| ctx.peer_id,
+        };
+    }
 };
 
 pub const PendingRPCEntry = struct {
@@ -182,6 +204,23 @@ pub const Network = struct {
         return request_id;
     }
 
+    pub fn requestBlocksByRange(
+        self: *Self,
+        peer_id: []const u8,
+        range: types.BlocksByRangeRequest,
+        callback: ?networks.OnReqRespResponseCbHandler,
+    ) !u64 {
+        if (range.count == 0) return error.NoBlocksRequested;
+        if (range.step == 0) return error.InvalidBlocksByRangeStep;
+
+        var request = networks.ReqRespRequest{ .blocks_by_range = range };
+        errdefer request.deinit();
+
+        const request_id = try self.backend.reqresp.sendRequest(peer_id, &request, callback);
+        request.deinit();
+        return request_id;
+    }
+
     pub fn selectPeer(self: *Self) ?[]const u8 {
         const peer_count = self.connected_peers.count();
         if (peer_count == 0) return null;
@@ -249,10 +288,7 @@ pub const Network = struct {
             defer request_ids_to_remove.deinit(self.allocator);
 
             while (rpc_it.next()) |rpc_entry| {
-                const pending_peer_id = switch (rpc_entry.value_ptr.request) {
-                    .status => |*ctx| ctx.peer_id,
-                    .blocks_by_root => |*ctx| ctx.peer_id,
-                };
+                const pending_peer_id = rpc_entry.value_ptr.request.peerId();
                 if (std.mem.eql(u8, pending_peer_id, peer_id)) {
                     // If we can't allocate, skip this request (should be rare)
                     request_ids_to_remove.append(self.allocator, rpc_entry.key_ptr.*) catch continue;
@@ -530,6 +566,38 @@ pub const Network = struct {
         return request_id;
     }
 
+    /// Requests `count` slots starting at `start_slot` from `peer_id`, capped at MAX_REQUEST_BLOCKS.
+    pub fn sendBlocksByRangeRequest(
+        self: *Self,
+        peer_id: []const u8,
+        start_slot: types.Slot,
+        count: u64,
+        handler: networks.OnReqRespResponseCbHandler,
+    ) !u64 {
+        const range = types.BlocksByRangeRequest{
+            .start_slot = start_slot,
+            .count = @min(count, params.MAX_REQUEST_BLOCKS),
+            .step = 1,
+        };
+
+        const peer_copy = try self.allocator.dupe(u8, peer_id);
+        var pending = PendingRPC{ .blocks_by_range = .{
+            .peer_id = peer_copy,
+            .start_slot = range.start_slot,
+            .count = range.count,
+            .step = range.step,
+        } };
+        errdefer pending.deinit(self.allocator);
+
+        const request_id = try self.requestBlocksByRange(peer_id, range, handler);
+        try self.pending_rpc_requests.put(request_id, PendingRPCEntry{
+            .request = pending,
+            .created_at = std.time.timestamp(),
+        });
+
+        return request_id;
+    }
+
     pub fn ensureBlocksByRootRequest(
         self: *Self,
         roots: []const types.Root,
@@ -574,7 +642,7 @@ pub const Network = struct {
                         _ = self.removePendingBlockRoot(root);
                     }
                 },
-                .status => {},
+                .status, .blocks_by_range => {},
             }
             rpc_entry.deinit(self.allocator);
         }
@@ -24,6 +24,7 @@ const constants = @import("./constants.zig");
 const forkchoice = @import("./forkchoice.zig");
 
 const BlockByRootContext = networkFactory.BlockByRootContext;
+const BlocksByRangeContext = networkFactory.BlocksByRangeContext;
 pub const NodeNameRegistry = networks.NodeNameRegistry;
 
 const ZERO_HASH = types.ZERO_HASH;
@@ -52,6 +53,8 @@ pub const BeamNode = struct {
     last_interval: isize,
     logger: zeam_utils.ModuleLogger,
     node_registry: *const NodeNameRegistry,
+    // Chain status last passed to the network for the Status handshake
+    local_status: ?types.Status = null,
 
     const Self = @This();
 
@@ -108,6 +111,7 @@ pub const BeamNode = struct {
             .last_interval = -1,
             .logger = opts.logger_config.logger(.node),
             .node_registry = opts.node_registry,
+            .local_status = null,
         };
 
         chain.setPruneCachedBlocksCallback(self, pruneCachedBlocksCallback);
@@ -309,6 +313,7 @@ pub const BeamNode = struct {
             }
         };
         self.handleGossipProcessingResult(result);
+        self.updateLocalStatus();
     }
 
     fn handleGossipProcessingResult(self: *Self, result: chainFactory.GossipProcessingResult) void {
@@ -613,104 +618,183 @@ pub const BeamNode = struct {
                 });
             }
 
-            // Skip STF re-processing if the block is already known to fork choice
-            // (e.g. the checkpoint sync anchor block — it is the trust root and does not
-            // need state-transition re-processing; re-processing it would cause an infinite
-            // fetch loop because onBlock would always see it as "already processed").
-            if (self.chain.forkChoice.hasBlock(block_root)) {
-                self.logger.debug(
-                    "block 0x{x} is already known to fork choice, skipping re-processing",
-                    .{&block_root},
-                );
-                self.processCachedDescendants(block_root);
-                return;
-            }
+            self.importFetchedBlock(block_ctx.peer_id, block_root, current_depth, signed_block);
+        } else |err| {
+            self.logger.warn("failed to compute block root from RPC response from peer={s}{f}: {any}", .{ block_ctx.peer_id, self.node_registry.getNodeNameFromPeerId(block_ctx.peer_id), err });
+        }
+    }
 
-            // Try to add the block to the chain
-            const missing_roots = self.chain.onBlock(signed_block.*, .{}) catch |err| {
-                // Check if the error is due to missing parent
-                if (err == chainFactory.BlockProcessingError.MissingPreState) {
-                    // Check if we've hit the max depth
-                    if (current_depth >= constants.MAX_BLOCK_FETCH_DEPTH) {
-                        self.logger.warn(
-                            "Reached max block fetch depth ({d}) for block 0x{x}, discarding",
-                            .{ constants.MAX_BLOCK_FETCH_DEPTH, &block_root },
-                        );
-                        return;
-                    }
+    fn processBlocksByRangeChunk(self: *Self, range_ctx: *BlocksByRangeContext, signed_block: *const types.SignedBlockWithAttestation) !void {
+        const slot = signed_block.message.block.slot;
+        const end_slot = range_ctx.start_slot + range_ctx.count * range_ctx.step;
+        // Chunks must be within the requested range, on the requested step and in ascending slot order
+        const in_range = slot >= range_ctx.start_slot and slot < end_slot and (slot - range_ctx.start_slot) % range_ctx.step == 0;
+        const ascending = if (range_ctx.last_slot) |last_slot| slot > last_slot else true;
+        if (range_ctx.received >= range_ctx.count or !in_range or !ascending) {
+            self.logger.warn("discarding unexpected blocks-by-range chunk slot={d} from peer {s}{f} (start_slot={d} count={d} step={d} received={d})", .{
+                slot,
+                range_ctx.peer_id,
+                self.node_registry.getNodeNameFromPeerId(range_ctx.peer_id),
+                range_ctx.start_slot,
+                range_ctx.count,
+                range_ctx.step,
+                range_ctx.received,
+            });
+            return;
+        }
+        range_ctx.received += 1;
+        range_ctx.last_slot = slot;
 
-                    // Cache this block and fetch parent
-                    if (self.cacheBlockAndFetchParent(block_root, signed_block.*, current_depth + 1)) |parent_root| {
-                        self.logger.debug(
-                            "Cached block 0x{x} at depth {d}, fetching parent 0x{x}",
-                            .{
-                                &block_root,
-                                current_depth,
-                                &parent_root,
-                            },
-                        );
-                    } else |cache_err| {
-                        if (cache_err == CacheBlockError.PreFinalized) {
-                            // Block is pre-finalized - prune any cached descendants waiting for this parent
-                            self.logger.info(
-                                "block 0x{x} is pre-finalized (slot={d}), pruning cached descendants",
-                                .{
-                                    &block_root,
-                                    signed_block.message.block.slot,
-                                },
-                            );
-                            _ = self.network.pruneCachedBlocks(block_root, null);
-                        } else {
-                            self.logger.warn("failed to cache block 0x{x}: {any}", .{
-                                &block_root,
-                                cache_err,
-                            });
-                        }
-                    }
+        var block_root: types.Root = undefined;
+        if (zeam_utils.hashTreeRoot(types.BeamBlock, signed_block.message.block, &block_root, self.allocator)) |_| {
+            self.importFetchedBlock(range_ctx.peer_id, block_root, 0, signed_block);
+        } else |err| {
+            self.logger.warn("failed to compute block root from RPC response from peer={s}{f}: {any}", .{ range_ctx.peer_id, self.node_registry.getNodeNameFromPeerId(range_ctx.peer_id), err });
+        }
+    }
+
+    /// Imports a block fetched over req/resp. A block whose parent is unknown is cached and its
+    /// parent fetched by root, up to MAX_BLOCK_FETCH_DEPTH.
+    fn importFetchedBlock(self: *Self, peer_id: []const u8, block_root: types.Root, current_depth: u32, signed_block: *const types.SignedBlockWithAttestation) void {
+        // Skip STF re-processing if the block is already known to fork choice
+        // (e.g. the checkpoint sync anchor block — it is the trust root and does not
+        // need state-transition re-processing; re-processing it would cause an infinite
+        // fetch loop because onBlock would always see it as "already processed").
+        if (self.chain.forkChoice.hasBlock(block_root)) {
+            self.logger.debug(
+                "block 0x{x} is already known to fork choice, skipping re-processing",
+                .{&block_root},
+            );
+            self.processCachedDescendants(block_root);
+            return;
+        }
+
+        // Try to add the block to the chain
+        const missing_roots = self.chain.onBlock(signed_block.*, .{}) catch |err| {
+            // Check if the error is due to missing parent
+            if (err == chainFactory.BlockProcessingError.MissingPreState) {
+                // Check if we've hit the max depth
+                if (current_depth >= constants.MAX_BLOCK_FETCH_DEPTH) {
+                    self.logger.warn(
+                        "Reached max block fetch depth ({d}) for block 0x{x}, discarding",
+                        .{ constants.MAX_BLOCK_FETCH_DEPTH, &block_root },
+                    );
                     return;
                 }
 
-                if (err == forkchoice.ForkChoiceError.PreFinalizedSlot) {
-                    self.logger.info(
-                        "discarding pre-finalized block 0x{x} from peer {s}{f}, pruning cached descendants",
+                // Cache this block and fetch parent
+                if (self.cacheBlockAndFetchParent(block_root, signed_block.*, current_depth + 1)) |parent_root| {
+                    self.logger.debug(
+                        "Cached block 0x{x} at depth {d}, fetching parent 0x{x}",
                         .{
                             &block_root,
-                            block_ctx.peer_id,
-                            self.node_registry.getNodeNameFromPeerId(block_ctx.peer_id),
+                            current_depth,
+                            &parent_root,
                         },
                     );
-                    _ = self.network.pruneCachedBlocks(block_root, null);
-                    return;
+                } else |cache_err| {
+                    if (cache_err == CacheBlockError.PreFinalized) {
+                        // Block is pre-finalized - prune any cached descendants waiting for this parent
+                        self.logger.info(
+                            "block 0x{x} is pre-finalized (slot={d}), pruning cached descendants",
+                            .{
+                                &block_root,
+                                signed_block.message.block.slot,
+                            },
+                        );
+                        _ = self.network.pruneCachedBlocks(block_root, null);
+                    } else {
+                        self.logger.warn("failed to cache block 0x{x}: {any}", .{
+                            &block_root,
+                            cache_err,
+                        });
+                    }
                 }
+                return;
+            }
 
-                self.logger.warn("failed to import block fetched via RPC 0x{x} from peer {s}{f}: {any}", .{
-                    &block_root,
-                    block_ctx.peer_id,
-                    self.node_registry.getNodeNameFromPeerId(block_ctx.peer_id),
-                    err,
-                });
+            if (err == forkchoice.ForkChoiceError.PreFinalizedSlot) {
+                self.logger.info(
+                    "discarding pre-finalized block 0x{x} from peer {s}{f}, pruning cached descendants",
+                    .{
+                        &block_root,
+                        peer_id,
+                        self.node_registry.getNodeNameFromPeerId(peer_id),
+                    },
+                );
+                _ = self.network.pruneCachedBlocks(block_root, null);
                 return;
-            };
-            defer self.allocator.free(missing_roots);
+            }
 
-            self.logger.debug(
-                "Successfully processed block 0x{x}, checking for cached descendants",
-                .{&block_root},
-            );
+            self.logger.warn("failed to import block fetched via RPC 0x{x} from peer {s}{f}: {any}", .{
+                &block_root,
+                peer_id,
+                self.node_registry.getNodeNameFromPeerId(peer_id),
+                err,
+            });
+            return;
+        };
+        defer self.allocator.free(missing_roots);
 
-            // Store aggregated signature proofs from this block so they can be reused
-            // in future block production. This is the same followup done for gossiped blocks.
-            self.chain.onBlockFollowup(true, signed_block);
+        self.logger.debug(
+            "Successfully processed block 0x{x}, checking for cached descendants",
+            .{&block_root},
+        );
 
-            // Block was successfully added, try to process any cached descendants
-            self.processCachedDescendants(block_root);
+        // Store aggregated signature proofs from this block so they can be reused
+        // in future block production. This is the same followup done for gossiped blocks.
+        self.chain.onBlockFollowup(true, signed_block);
 
-            // Fetch any missing attestation head blocks
-            self.fetchBlockByRoots(missing_roots, 0) catch |err| {
-                self.logger.warn("failed to fetch {d} missing block(s): {any}", .{ missing_roots.len, err });
-            };
-        } else |err| {
-            self.logger.warn("failed to compute block root from RPC response from peer={s}{f}: {any}", .{ block_ctx.peer_id, self.node_registry.getNodeNameFromPeerId(block_ctx.peer_id), err });
+        // Block was successfully added, try to process any cached descendants
+        self.processCachedDescendants(block_root);
+        self.updateLocalStatus();
+
+        // Fetch any missing attestation head blocks
+        self.fetchBlockByRoots(missing_roots, 0) catch |err| {
+            self.logger.warn("failed to fetch {d} missing block(s): {any}", .{ missing_roots.len, err });
+        };
+    }
+
+    /// Records the latest status of a peer and starts syncing from it if it is ahead of us.
+    fn processPeerStatus(self: *Self, peer_id: []const u8, status: types.Status) void {
+        if (!self.network.setPeerLatestStatus(peer_id, status)) {
+            self.logger.warn("status received for unknown peer {s}{f}", .{
+                peer_id,
+                self.node_registry.getNodeNameFromPeerId(peer_id),
+            });
+        }
+
+        // Proactive initial sync: if peer's finalized slot is ahead of us, request their head block
+        // This triggers parent syncing which will fetch all blocks back to our current state
+        // We compare finalized slots (not head slots) because finalized is more reliable for sync decisions
+        const sync_status = self.chain.getSyncStatus();
+        switch (sync_status) {
+            .behind_peers => |info| {
+                // Only sync from this peer if their finalized slot is ahead of ours
+                if (status.finalized_slot > self.chain.forkChoice.fcStore.latest_finalized.slot and
+                    status.head_slot > info.head_slot + constants.RANGE_SYNC_MIN_DISTANCE)
+                {
+                    // Far behind: download the missing slots in batches instead of one parent at a time
+                    self.requestBlocksByRangeBatch(peer_id, info.head_slot + 1, status.head_slot);
+                } else if (status.finalized_slot > self.chain.forkChoice.fcStore.latest_finalized.slot) {
+                    self.logger.info("peer {s}{f} is ahead (peer_finalized_slot={d} > our_head_slot={d}), initiating sync by requesting head block 0x{x}", .{
+                        peer_id,
+                        self.node_registry.getNodeNameFromPeerId(peer_id),
+                        status.finalized_slot,
+                        info.head_slot,
+                        &status.head_root,
+                    });
+                    const roots = [_]types.Root{status.head_root};
+                    self.fetchBlockByRoots(&roots, 0) catch |err| {
+                        self.logger.warn("failed to initiate sync by fetching head block from peer {s}{f}: {any}", .{
+                            peer_id,
+                            self.node_registry.getNodeNameFromPeerId(peer_id),
+                            err,
+                        });
+                    };
+                }
+            },
+            .synced, .no_peers, .fc_initing => {},
         }
     }
 
@@ -721,10 +805,7 @@ pub const BeamNode = struct {
             return;
         };
         const ctx_ptr = &entry_ptr.request;
-        const peer_id = switch (ctx_ptr.*) {
-            .status => |*ctx| ctx.peer_id,
-            .blocks_by_root => |*ctx| ctx.peer_id,
-        };
+        const peer_id = ctx_ptr.peerId();
         const node_name = self.node_registry.getNodeNameFromPeerId(peer_id);
 
         switch (event.payload) {
@@ -738,40 +819,7 @@ pub const BeamNode = struct {
                                 status_resp.head_slot,
                                 status_resp.finalized_slot,
                             });
-                            if (!self.network.setPeerLatestStatus(status_ctx.peer_id, status_resp)) {
-                                self.logger.warn("status response received for unknown peer {s}{f}", .{
-                                    status_ctx.peer_id,
-                                    self.node_registry.getNodeNameFromPeerId(status_ctx.peer_id),
-                                });
-                            }
-
-                            // Proactive initial sync: if peer's finalized slot is ahead of us, request their head block
-                            // This triggers parent syncing which will fetch all blocks back to our current state
-                            // We compare finalized slots (not head slots) because finalized is more reliable for sync decisions
-                            const sync_status = self.chain.getSyncStatus();
-                            switch (sync_status) {
-                                .behind_peers => |info| {
-                                    // Only sync from this peer if their finalized slot is ahead of ours
-                                    if (status_resp.finalized_slot > self.chain.forkChoice.fcStore.latest_finalized.slot) {
-                                        self.logger.info("peer {s}{f} is ahead (peer_finalized_slot={d} > our_head_slot={d}), initiating sync by requesting head block 0x{x}", .{
-                                            status_ctx.peer_id,
-                                            self.node_registry.getNodeNameFromPeerId(status_ctx.peer_id),
-                                            status_resp.finalized_slot,
-                                            info.head_slot,
-                                            &status_resp.head_root,
-                                        });
-                                        const roots = [_]types.Root{status_resp.head_root};
-                                        self.fetchBlockByRoots(&roots, 0) catch |err| {
-                                            self.logger.warn("failed to initiate sync by fetching head block from peer {s}{f}: {any}", .{
-                                                status_ctx.peer_id,
-                                                self.node_registry.getNodeNameFromPeerId(status_ctx.peer_id),
-                                                err,
-                                            });
-                                        };
-                                    }
-                                },
-                                .synced, .no_peers, .fc_initing => {},
-                            }
+                            self.processPeerStatus(status_ctx.peer_id, status_resp);
                         },
                         else => {
                             self.logger.warn("status response did not match tracked request_id={d} from peer={s}{f}", .{ request_id, peer_id, node_name });
@@ -793,6 +841,22 @@ pub const BeamNode = struct {
                         },
                     }
                 },
+                .blocks_by_range => |block_resp| {
+                    switch (ctx_ptr.*) {
+                        .blocks_by_range => |*range_ctx| {
+                            self.logger.debug("received blocks-by-range chunk slot={d} from peer {s}{f}", .{
+                                block_resp.message.block.slot,
+                                range_ctx.peer_id,
+                                self.node_registry.getNodeNameFromPeerId(range_ctx.peer_id),
+                            });
+
+                            try self.processBlocksByRangeChunk(range_ctx, &block_resp);
+                        },
+                        else => {
+                            self.logger.warn("blocks-by-range response did not match tracked request_id={d} from peer={s}{f}", .{ request_id, peer_id, node_name });
+                        },
+                    }
+                },
             },
             .failure => |err_payload| {
                 switch (ctx_ptr.*) {
@@ -812,15 +876,68 @@ pub const BeamNode = struct {
                             err_payload.message,
                         });
                     },
+                    .blocks_by_range => |range_ctx| {
+                        self.logger.warn("blocks-by-range request to peer {s}{f} failed ({d}): {s}", .{
+                            range_ctx.peer_id,
+                            self.node_registry.getNodeNameFromPeerId(range_ctx.peer_id),
+                            err_payload.code,
+                            err_payload.message,
+                        });
+                    },
                 }
                 self.network.finalizePendingRequest(request_id);
             },
             .completed => {
-                self.network.finalizePendingRequest(request_id);
+                switch (ctx_ptr.*) {
+                    .blocks_by_range => |range_ctx| {
+                        // Copy what is needed to continue before finalize frees the context
+                        const next_start_slot = range_ctx.start_slot + range_ctx.count * range_ctx.step;
+                        const made_progress = range_ctx.received > 0;
+                        const peer_copy = try self.allocator.dupe(u8, range_ctx.peer_id);
+                        defer self.allocator.free(peer_copy);
+
+                        self.network.finalizePendingRequest(request_id);
+                        if (made_progress) self.continueRangeSync(peer_copy, next_start_slot);
+                    },
+                    else => self.network.finalizePendingRequest(request_id),
+                }
             },
         }
     }
 
+    /// Requests the next range batch from a peer that is still ahead of our head.
+    fn continueRangeSync(self: *Self, peer_id: []const u8, start_slot: types.Slot) void {
+        const peer_info = self.network.connected_peers.get(peer_id) orelse return;
+        const peer_status = peer_info.latest_status orelse return;
+        const head_slot = self.chain.forkChoice.getHead().slot;
+        if (peer_status.head_slot <= head_slot + constants.RANGE_SYNC_MIN_DISTANCE) return;
+
+        self.requestBlocksByRangeBatch(peer_id, @max(start_slot, head_slot + 1), peer_status.head_slot);
+    }
+
+    fn requestBlocksByRangeBatch(self: *Self, peer_id: []const u8, start_slot: types.Slot, peer_head_slot: types.Slot) void {
+        if (peer_head_slot < start_slot) return;
+        const count = peer_head_slot - start_slot + 1;
+
+        const request_id = self.network.sendBlocksByRangeRequest(peer_id, start_slot, count, self.getReqRespResponseHandler()) catch |err| {
+            self.logger.warn("failed to request blocks by range start_slot={d} from peer {s}{f}: {any}", .{
+                start_slot,
+                peer_id,
+                self.node_registry.getNodeNameFromPeerId(peer_id),
+                err,
+            });
+            return;
+        };
+        self.logger.info("requested blocks by range start_slot={d} count={d} from peer {s}{f} (peer_head_slot={d}, request_id={d})", .{
+            start_slot,
+            @min(count, params.MAX_REQUEST_BLOCKS),
+            peer_id,
+            self.node_registry.getNodeNameFromPeerId(peer_id),
+            peer_head_slot,
+            request_id,
+        });
+    }
+
     pub fn onReqRespResponse(ptr: *anyopaque, event: *const networks.ReqRespResponseEvent) anyerror!void {
         const self: *Self = @ptrCast(@alignCast(ptr));
         try self.handleReqRespResponse(event);
@@ -870,7 +987,74 @@ pub const BeamNode = struct {
                 try responder.sendResponse(&response);
                 try responder.finish();
             },
+            .blocks_by_range => |request| {
+                self.logger.debug(
+                    "node-{d}:: Handling blocks_by_range request start_slot={d} count={d} step={d}",
+                    .{ self.nodeId, request.start_slot, request.count, request.step },
+                );
+
+                if (request.count == 0 or request.step == 0) {
+                    try responder.sendError(1, "blocks_by_range count and step must be non-zero");
+                    return;
+                }
+
+                const roots = try self.getCanonicalBlockRootsByRange(request);
+                defer self.allocator.free(roots);
+
+                for (roots) |root| {
+                    if (self.chain.db.loadBlock(database.DbBlocksNamespace, root)) |signed_block_value| {
+                        var signed_block = signed_block_value;
+                        defer signed_block.deinit();
+
+                        var response = networks.ReqRespResponse{ .blocks_by_range = undefined };
+                        try types.sszClone(self.allocator, types.SignedBlockWithAttestation, signed_block, &response.blocks_by_range);
+                        defer response.deinit();
+
+                        try responder.sendResponse(&response);
+                    } else {
+                        self.logger.warn(
+                            "node-{d}:: Canonical block root=0x{x} not found",
+                            .{ self.nodeId, &root },
+                        );
+                    }
+                }
+
+                try responder.finish();
+            },
+        }
+    }
+
+    /// Roots of the canonical blocks in the requested range in ascending slot order, capped at
+    /// MAX_REQUEST_BLOCKS slots. Finalized slots are read from the finalized slot index and later
+    /// ones from the chain walked back from the head. Empty slots are skipped.
+    fn getCanonicalBlockRootsByRange(self: *Self, request: types.BlocksByRangeRequest) ![]types.Root {
+        const count = @min(request.count, params.MAX_REQUEST_BLOCKS);
+        const finalized_slot = self.chain.forkChoice.getLatestFinalized().slot;
+
+        var unfinalized_roots = std.AutoHashMap(types.Slot, types.Root).init(self.allocator);
+        defer unfinalized_roots.deinit();
+        var root = self.chain.forkChoice.getHead().blockRoot;
+        while (self.chain.forkChoice.getBlock(root)) |block| {
+            if (block.slot <= finalized_slot or block.slot < request.start_slot) break;
+            try unfinalized_roots.put(block.slot, root);
+            root = block.parentRoot;
+        }
+
+        var roots: std.ArrayList(types.Root) = .empty;
+        errdefer roots.deinit(self.allocator);
+        var i: u64 = 0;
+        while (i < count) : (i += 1) {
+            const offset = std.math.mul(u64, i, request.step) catch break;
+            const slot = std.math.add(types.Slot, request.start_slot, offset) catch break;
+            const block_root = if (slot <= finalized_slot)
+                self.chain.db.loadFinalizedSlotIndex(database.DbFinalizedSlotsNamespace, slot)
+            else
+                unfinalized_roots.get(slot);
+            if (block_root) |canonical_root| {
+                try roots.append(self.allocator, canonical_root);
+            }
         }
+        return roots.toOwnedSlice(self.allocator);
     }
     pub fn getOnReqRespRequestCbHandler(self: *Self) networks.OnReqRespRequestCbHandler {
         return .{
@@ -942,26 +1126,6 @@ pub const BeamNode = struct {
         // Record metrics
         zeam_metrics.metrics.lean_peer_connection_events_total.incr(.{ .direction = @tagName(direction), .result = "success" }) catch {};
         zeam_metrics.metrics.lean_connected_peers.set(@intCast(self.network.getPeerCount()));
-
-        const handler = self.getReqRespResponseHandler();
-        const status = self.chain.getStatus();
-
-        const request_id = self.network.sendStatusToPeer(peer_id, status, handler) catch |err| {
-            self.logger.warn("failed to send status request to peer {s}{f} {any}", .{
-                peer_id,
-                self.node_registry.getNodeNameFromPeerId(peer_id),
-                err,
-            });
-            return;
-        };
-
-        self.logger.info("sent status request to peer {s}{f}: request_id={d}, head_slot={d}, finalized_slot={d}", .{
-            peer_id,
-            self.node_registry.getNodeNameFromPeerId(peer_id),
-            request_id,
-            status.head_slot,
-            status.finalized_slot,
-        });
     }
 
     pub fn onPeerDisconnected(ptr: *anyopaque, peer_id: []const u8, direction: networks.PeerDirection, reason: networks.DisconnectionReason) !void {
@@ -995,12 +1159,25 @@ pub const BeamNode = struct {
         zeam_metrics.metrics.lean_peer_connection_events_total.incr(.{ .direction = @tagName(direction), .result = @tagName(result) }) catch {};
     }
 
+    pub fn onPeerStatus(ptr: *anyopaque, peer_id: []const u8, status: types.Status) !void {
+        const self: *Self = @ptrCast(@alignCast(ptr));
+
+        self.logger.info("received status of peer {s}{f} head_slot={d}, finalized_slot={d}", .{
+            peer_id,
+            self.node_registry.getNodeNameFromPeerId(peer_id),
+            status.head_slot,
+            status.finalized_slot,
+        });
+        self.processPeerStatus(peer_id, status);
+    }
+
     pub fn getPeerEventHandler(self: *Self) networks.OnPeerEventCbHandler {
         return .{
             .ptr = self,
             .onPeerConnectedCb = onPeerConnected,
             .onPeerDisconnectedCb = onPeerDisconnected,
             .onPeerConnectionFailedCb = onPeerConnectionFailed,
+            .onPeerStatusCb = onPeerStatus,
         };
     }
 
@@ -1122,6 +1299,26 @@ pub const BeamNode = struct {
         }
 
         self.last_interval = itime_intervals;
+        self.updateLocalStatus();
+    }
+
+    /// Passes the chain status to the network whenever the head or finalized checkpoint changed,
+    /// the network exchanges it in the Status handshake with every connected peer.
+    fn updateLocalStatus(self: *Self) void {
+        const status = self.chain.getStatus();
+        if (self.local_status) |local_status| {
+            if (std.meta.eql(local_status, status)) return;
+        }
+
+        self.network.backend.peers.setLocalStatus(self.getPeerEventHandler(), status) catch |err| {
+            self.logger.warn("failed to set the local status head_slot={d} finalized_slot={d}: {any}", .{
+                status.head_slot,
+                status.finalized_slot,
+                err,
+            });
+            return;
+        };
+        self.local_status = status;
     }
 
     fn sweepTimedOutRequests(self: *Self) void {
@@ -1172,6 +1369,16 @@ pub const BeamNode = struct {
                     });
                     self.network.finalizePendingRequest(request_id);
                 },
+                .blocks_by_range => |range_ctx| {
+                    self.logger.warn("blocks-by-range RPC request_id={d} to peer {s}{f} timed out after {d}/{d} blocks, finalizing", .{
+                        request_id,
+                        range_ctx.peer_id,
+                        self.node_registry.getNodeNameFromPeerId(range_ctx.peer_id),
+                        range_ctx.received,
+                        range_ctx.count,
+                    });
+                    self.network.finalizePendingRequest(request_id);
+                },
             }
         }
     }
@@ -1219,6 +1426,7 @@ pub const BeamNode = struct {
 
         // 4. Followup with additional housekeeping tasks.
         self.chain.onBlockFollowup(true, &signed_block);
+        self.updateLocalStatus();
     }
 
     pub fn publishAttestation(self: *Self, signed_attestation: networks.AttestationGossip) !void {
@@ -1311,6 +1519,7 @@ pub const BeamNode = struct {
 
         const peer_handler = self.getPeerEventHandler();
         try self.network.backend.peers.subscribe(peer_handler);
+        self.updateLocalStatus();
 
         const req_handler = self.getOnReqRespRequestCbHandler();
         try self.network.backend.reqresp.subscribe(req_handler);
@@ -656,6 +656,28 @@ pub const BlockByRootRequest = struct {
     }
 };
 
+/// Request for the canonical blocks at `start_slot`, `start_slot + step`, ... (`count` slots).
+/// Empty slots are skipped, so fewer than `count` blocks may be returned.
+pub const BlocksByRangeRequest = struct {
+    start_slot: Slot,
+    count: u64,
+    step: u64,
+
+    pub fn toJson(self: *const BlocksByRangeRequest, allocator: Allocator) !json.Value {
+        var obj = json.ObjectMap.init(allocator);
+        try obj.put("start_slot", json.Value{ .integer = @as(i64, @intCast(self.start_slot)) });
+        try obj.put("count", json.Value{ .integer = @as(i64, @intCast(self.count)) });
+        try obj.put("step", json.Value{ .integer = @as(i64, @intCast(self.step)) });
+        return json.Value{ .object = obj };
+    }
+
+    pub fn toJsonString(self: *const BlocksByRangeRequest, allocator: Allocator) ![]const u8 {
+        var json_value = try self.toJson(allocator);
+        defer json_value.object.deinit();
+        return utils.jsonToString(allocator, json_value);
+    }
+};
+
 /// Canonical lightweight forkchoice proto block used across modules
 pub const ProtoBlock = struct {
     slot: Slot,
@@ -0,0 +1,255 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use discv5::enr::{CombinedKey, EnrPublicKey};
+use discv5::Enr;
+use libp2p::identity::{secp256k1, Keypair};
+use libp2p::multiaddr::Protocol;
+use libp2p::{Multiaddr, PeerId};
+
+/// ENR key of the QUIC port, following the consensus layer convention.
+pub const ENR_QUIC_KEY: &str = "quic";
+pub const ENR_QUIC6_KEY: &str = "quic6";
+
+/// Converts the libp2p identity of the node into the key used to sign its ENR.
+pub fn enr_key_from_keypair(keypair: &Keypair) -> Result<CombinedKey, String> {
+    let keypair = keypair
+        .clone()
+        .try_into_secp256k1()
+        .map_err(|_| "only secp256k1 keys are supported for discovery".to_string())?;
+    let mut secret = keypair.secret().to_bytes();
+    CombinedKey::secp256k1_from_bytes(&mut secret).map_err(|e| format!("invalid ENR key: {e}"))
+}
+
+/// Addresses and ports advertised in the local ENR. Unset fields are left out of the record.
+#[derive(Debug, Clone, Default)]
+pub struct LocalEnrConfig {
+    pub ip4: Option<Ipv4Addr>,
+    pub ip6: Option<Ipv6Addr>,
+    pub udp: Option<u16>,
+    pub tcp: Option<u16>,
+    pub quic: Option<u16>,
+}
+
+impl LocalEnrConfig {
+    /// Derives the advertised ip and transport ports from the swarm listen addresses. Wildcard
+    /// ips and port 0 are skipped, since they are not reachable by other nodes.
+    pub fn from_listen_addresses(listen_addresses: &[Multiaddr], udp: Option<u16>) -> Self {
+        let mut config = LocalEnrConfig {
+            udp,
+            ..Default::default()
+        };
+
+        for addr in listen_addresses {
+            let mut udp_port = None;
+            for protocol in addr.iter() {
+                match protocol {
+                    Protocol::Ip4(ip) if !ip.is_unspecified() => {
+                        config.ip4.get_or_insert(ip);
+                    }
+                    Protocol::Ip6(ip) if !ip.is_unspecified() => {
+                        config.ip6.get_or_insert(ip);
+                    }
+                    Protocol::Tcp(port) if port != 0 => {
+                        config.tcp.get_or_insert(port);
+                    }
+                    Protocol::Udp(port) if port != 0 => udp_port = Some(port),
+                    Protocol::QuicV1 => {
+                        if let Some(port) = udp_port {
+                            config.quic.get_or_insert(port);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        config
+    }
+}
+
+/// Builds and signs the local ENR.
+pub fn build_local_enr(key: &CombinedKey, config: &LocalEnrConfig) -> Result<Enr, String> {
+    let mut builder = Enr::builder();
+    if let Some(ip) = config.ip4 {
+        builder.ip4(ip);
+    }
+    if let Some(ip) = config.ip6 {
+        builder.ip6(ip);
+    }
+    if let Some(port) = config.udp {
+        builder.udp4(port);
+    }
+    if let Some(port) = config.tcp {
+        builder.tcp4(port);
+    }
+    if let Some(port) = config.quic {
+        builder.add_value(ENR_QUIC_KEY, &port);
+    }
+    builder
+        .build(key)
+        .map_err(|e| format!("failed to build ENR: {e:?}"))
+}
+
+/// Parses a comma-separated list of multiaddrs and `enr:` records. ENRs are expanded into the
+/// TCP and QUIC addresses they advertise; with `with_peer_id` each of them gets the `/p2p`
+/// suffix of the record's key so it can be dialed.
+pub fn parse_addresses(addresses: &str, with_peer_id: bool) -> Result<Vec<Multiaddr>, String> {
+    let mut multiaddrs = Vec::new();
+    for address in addresses
+        .split(",")
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+    {
+        if !address.starts_with("enr:") {
+            let multiaddr = address
+                .parse::<Multiaddr>()
+                .map_err(|e| format!("invalid multiaddr {address}: {e}"))?;
+            multiaddrs.push(multiaddr);
+            continue;
+        }
+
+        let enr = address
+            .parse::<Enr>()
+            .map_err(|e| format!("invalid ENR {address}: {e}"))?;
+        let enr_multiaddrs = enr.multiaddrs();
+        if enr_multiaddrs.is_empty() {
+            return Err(format!("ENR {address} has no tcp or quic address"));
+        }
+        if with_peer_id {
+            let peer_id = enr
+                .peer_id()
+                .ok_or_else(|| format!("ENR {address} has no secp256k1 key"))?;
+            multiaddrs.extend(
+                enr_multiaddrs
+                    .into_iter()
+                    .map(|addr| addr.with(Protocol::P2p(peer_id))),
+            );
+        } else {
+            multiaddrs.extend(enr_multiaddrs);
+        }
+    }
+    Ok(multiaddrs)
+}
+
+/// libp2p view of an ENR.
+pub trait EnrExt {
+    /// The libp2p peer id derived from the ENR's public key.
+    fn peer_id(&self) -> Option<PeerId>;
+
+    /// TCP and QUIC multiaddrs the node advertises, without the `/p2p` suffix.
+    fn multiaddrs(&self) -> Vec<Multiaddr>;
+
+    fn quic4(&self) -> Option<u16>;
+
+    fn quic6(&self) -> Option<u16>;
+}
+
+impl EnrExt for Enr {
+    fn peer_id(&self) -> Option<PeerId> {
+        let public_key = secp256k1::PublicKey::try_from_bytes(&self.public_key().encode()).ok()?;
+        Some(PeerId::from_public_key(&public_key.into()))
+    }
+
+    fn multiaddrs(&self) -> Vec<Multiaddr> {
+        let mut multiaddrs = Vec::new();
+        if let Some(ip) = self.ip4() {
+            if let Some(port) = self.tcp4() {
+                multiaddrs.push(Multiaddr::from(ip).with(Protocol::Tcp(port)));
+            }
+            if let Some(port) = self.quic4() {
+                multiaddrs.push(
+                    Multiaddr::from(ip)
+                        .with(Protocol::Udp(port))
+                        .with(Protocol::QuicV1),
+                );
+            }
+        }
+        if let Some(ip) = self.ip6() {
+            if let Some(port) = self.tcp6() {
+                multiaddrs.push(Multiaddr::from(ip).with(Protocol::Tcp(port)));
+            }
+            if let Some(port) = self.quic6() {
+                multiaddrs.push(
+                    Multiaddr::from(ip)
+                        .with(Protocol::Udp(port))
+                        .with(Protocol::QuicV1),
+                );
+            }
+        }
+        multiaddrs
+    }
+
+    fn quic4(&self) -> Option<u16> {
+        self.get_decodable(ENR_QUIC_KEY).and_then(Result::ok)
+    }
+
+    fn quic6(&self) -> Option<u16> {
+        self.get_decodable(ENR_QUIC6_KEY).and_then(Result::ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_enr_advertises_transport_addresses() {
+        let keypair = Keypair::from(secp256k1::Keypair::generate());
+        let key = enr_key_from_keypair(&keypair).unwrap();
+        let listen_addresses = [
+            "/ip4/127.0.0.1/tcp/9000".parse().unwrap(),
+            "/ip4/127.0.0.1/udp/9001/quic-v1".parse().unwrap(),
+        ];
+
+        let config = LocalEnrConfig::from_listen_addresses(&listen_addresses, Some(9002));
+        let enr = build_local_enr(&key, &config).unwrap();
+
+        assert_eq!(enr.peer_id(), Some(keypair.public().to_peer_id()));
+        assert_eq!(enr.udp4(), Some(9002));
+        assert_eq!(
+            enr.multiaddrs(),
+            vec![
+                "/ip4/127.0.0.1/tcp/9000".parse::<Multiaddr>().unwrap(),
+                "/ip4/127.0.0.1/udp/9001/quic-v1".parse().unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_addresses_expands_enrs() {
+        let keypair = Keypair::from(secp256k1::Keypair::generate());
+        let key = enr_key_from_keypair(&keypair).unwrap();
+        let config = LocalEnrConfig {
+            ip4: Some(Ipv4Addr::LOCALHOST),
+            tcp: Some(9000),
+            quic: Some(9001),
+            ..Default::default()
+        };
+        let enr = build_local_enr(&key, &config).unwrap().to_base64();
+        let peer_id = keypair.public().to_peer_id();
+
+        let addresses = parse_addresses(&format!("/ip4/10.0.0.1/tcp/9000, {enr}"), true).unwrap();
+        assert_eq!(
+            addresses,
+            vec![
+                "/ip4/10.0.0.1/tcp/9000".parse::<Multiaddr>().unwrap(),
+                format!("/ip4/127.0.0.1/tcp/9000/p2p/{peer_id}")
+                    .parse()
+                    .unwrap(),
+                format!("/ip4/127.0.0.1/udp/9001/quic-v1/p2p/{peer_id}")
+                    .parse()
+                    .unwrap(),
+            ]
+        );
+        assert_eq!(parse_addresses(&enr, false).unwrap().len(), 2);
+        assert!(parse_addresses("enr:-invalid", true).is_err());
+        assert!(parse_addresses("", true).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_wildcard_listen_addresses_are_not_advertised() {
+        let listen_addresses = ["/ip4/0.0.0.0/tcp/0".parse().unwrap()];
+        let config = LocalEnrConfig::from_listen_addresses(&listen_addresses, None);
+        assert!(config.ip4.is_none());
+        assert!(config.tcp.is_none());
+    }
+}
@@ -0,0 +1,221 @@
+pub mod enr;
+
+pub use self::enr::{
+    build_local_enr, enr_key_from_keypair, parse_addresses, EnrExt, LocalEnrConfig,
+};
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use discv5::enr::{CombinedKey, NodeId};
+use discv5::{ConfigBuilder, Discv5, Enr, ListenConfig, QueryError};
+use libp2p::{
+    core::{transport::PortUse, Endpoint},
+    multiaddr::Protocol,
+    swarm::{
+        dummy, ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour, NewListenAddr,
+        THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+    },
+    Multiaddr, PeerId,
+};
+use tracing::{debug, warn};
+
+type QueryFuture = Pin<Box<dyn Future<Output = Result<Vec<Enr>, QueryError>> + Send>>;
+
+/// Discovery settings passed in by Zig.
+#[derive(Debug, Clone)]
+pub struct DiscoveryConfig {
+    /// UDP port of the discv5 service, advertised in the local ENR.
+    pub udp_port: u16,
+    pub bootnodes: Vec<Enr>,
+}
+
+#[derive(Debug)]
+pub enum DiscoveryEvent {
+    /// Peers found by a random lookup, with the addresses advertised in their ENR.
+    DiscoveredPeers(Vec<(PeerId, Vec<Multiaddr>)>),
+}
+
+/// Discv5 discovery running next to the swarm.
+///
+/// Lookups are only started on request through `discover_peers`, so the network decides when
+/// more peers are needed. The local ENR follows the swarm's listen addresses, advertising the
+/// TCP and QUIC ports once they are known.
+pub struct Discovery {
+    discv5: Discv5,
+    local_peer_id: PeerId,
+    active_query: Option<QueryFuture>,
+    events: VecDeque<DiscoveryEvent>,
+}
+
+impl Discovery {
+    /// Starts the discv5 service on `udp_port` and seeds its routing table with `bootnodes`.
+    pub async fn new(
+        enr_key: CombinedKey,
+        local_enr: Enr,
+        udp_port: u16,
+        bootnodes: Vec<Enr>,
+    ) -> Result<Self, String> {
+        let local_peer_id = local_enr
+            .peer_id()
+            .ok_or_else(|| "local ENR has no secp256k1 key".to_string())?;
+        let listen_ip = local_enr
+            .ip4()
+            .map(IpAddr::V4)
+            .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        let config = ConfigBuilder::new(ListenConfig::from_ip(listen_ip, udp_port)).build();
+
+        let mut discv5: Discv5 = Discv5::new(local_enr, enr_key, config)?;
+        for bootnode in bootnodes {
+            if let Err(e) = discv5.add_enr(bootnode.clone()) {
+                warn!(
+                    "DISCOVERY: failed to add bootnode {}: {e}",
+                    bootnode.to_base64()
+                );
+            }
+        }
+        discv5
+            .start()
+            .await
+            .map_err(|e| format!("failed to start discv5: {e:?}"))?;
+
+        Ok(Self {
+            discv5,
+            local_peer_id,
+            active_query: None,
+            events: VecDeque::new(),
+        })
+    }
+
+    pub fn local_enr(&self) -> Enr {
+        self.discv5.local_enr()
+    }
+
+    /// Starts a random lookup unless one is already running.
+    pub fn discover_peers(&mut self) {
+        if self.active_query.is_some() {
+            return;
+        }
+        debug!("DISCOVERY: starting random lookup");
+        self.active_query = Some(Box::pin(self.discv5.find_node(NodeId::random())));
+    }
+
+    fn on_query_result(&mut self, result: Result<Vec<Enr>, QueryError>) {
+        let enrs = match result {
+            Ok(enrs) => enrs,
+            Err(e) => {
+                debug!("DISCOVERY: lookup failed: {e:?}");
+                return;
+            }
+        };
+
+        let peers = enrs
+            .iter()
+            .filter_map(|enr| {
+                let peer_id = enr.peer_id()?;
+                let addrs = enr.multiaddrs();
+                (peer_id != self.local_peer_id && !addrs.is_empty()).then_some((peer_id, addrs))
+            })
+            .collect::<Vec<_>>();
+        debug!(
+            "DISCOVERY: lookup returned {} ENRs, {} dialable",
+            enrs.len(),
+            peers.len()
+        );
+        if !peers.is_empty() {
+            self.events
+                .push_back(DiscoveryEvent::DiscoveredPeers(peers));
+        }
+    }
+
+    /// Advertises a new swarm listen address in the local ENR.
+    fn on_new_listen_addr(&mut self, addr: &Multiaddr) {
+        let mut udp_port = None;
+        for protocol in addr.iter() {
+            let result = match protocol {
+                Protocol::Tcp(port) => self.discv5.enr_insert("tcp", &port),
+                Protocol::Udp(port) => {
+                    udp_port = Some(port);
+                    continue;
+                }
+                Protocol::QuicV1 => match udp_port {
+                    Some(port) => self.discv5.enr_insert(enr::ENR_QUIC_KEY, &port),
+                    None => continue,
+                },
+                _ => continue,
+            };
+            if let Err(e) = result {
+                warn!("DISCOVERY: failed to advertise listen address {addr}: {e:?}");
+            }
+        }
+    }
+}
+
+impl Drop for Discovery {
+    fn drop(&mut self) {
+        self.discv5.shutdown();
+    }
+}
+
+impl NetworkBehaviour for Discovery {
+    type ConnectionHandler = dummy::ConnectionHandler;
+
+    type ToSwarm = DiscoveryEvent;
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        _connection_id: ConnectionId,
+        _peer: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        _connection_id: ConnectionId,
+        _peer: PeerId,
+        _addr: &Multiaddr,
+        _role_override: Endpoint,
+        _port_use: PortUse,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        if let FromSwarm::NewListenAddr(NewListenAddr { addr, .. }) = event {
+            self.on_new_listen_addr(addr);
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        _peer_id: PeerId,
+        _connection_id: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        match event {}
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        if let Some(query) = self.active_query.as_mut() {
+            if let Poll::Ready(result) = query.as_mut().poll(cx) {
+                self.active_query = None;
+                self.on_query_result(result);
+            }
+        }
+
+        if let Some(event) = self.events.pop_front() {
+            return Poll::Ready(ToSwarm::GenerateEvent(event));
+        }
+
+        Poll::Pending
+    }
+}
@@ -0,0 +1,160 @@
+use std::time::Duration;
+
+use libp2p::gossipsub;
+use serde::Deserialize;
+
+/// Gossipsub configuration supplied by Zig as JSON at network start.
+///
+/// Every field is optional; missing fields fall back to the defaults below. The duplicate cache
+/// time defaults to six slots, so only the slot duration of the chain preset has to be passed.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GossipsubConfig {
+    pub seconds_per_slot: u64,
+    pub mesh_n: usize,
+    pub mesh_n_low: usize,
+    pub mesh_n_high: usize,
+    pub gossip_lazy: usize,
+    pub heartbeat_interval_ms: u64,
+    /// Heartbeats a published message is kept in the message cache.
+    pub history_length: usize,
+    /// None selects `DUPLICATE_CACHE_SLOTS` slots.
+    pub duplicate_cache_time_ms: Option<u64>,
+    pub max_transmit_size: usize,
+    /// Received messages larger than this many bytes are announced to the mesh with IDONTWANT
+    /// (gossipsub v1.2), so mesh peers do not send them again.
+    pub idontwant_message_size_threshold: usize,
+}
+
+/// Slots a message id is remembered to drop duplicates, also how long a message waits for its
+/// validation result from Zig.
+const DUPLICATE_CACHE_SLOTS: u64 = 6;
+
+impl Default for GossipsubConfig {
+    fn default() -> Self {
+        Self {
+            seconds_per_slot: 4,
+            mesh_n: 8,
+            mesh_n_low: 6,
+            mesh_n_high: 12,
+            gossip_lazy: 6,
+            heartbeat_interval_ms: 700,
+            history_length: 6,
+            duplicate_cache_time_ms: None,
+            // 2 MiB for blocks and aggregated payloads
+            max_transmit_size: 2 * 1024 * 1024,
+            idontwant_message_size_threshold: 1000,
+        }
+    }
+}
+
+impl GossipsubConfig {
+    /// Parses the JSON config passed over FFI. An empty document selects the defaults.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        if json.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        let config: Self =
+            serde_json::from_str(json).map_err(|e| format!("invalid gossipsub config: {e}"))?;
+        config
+            .validate()
+            .map_err(|e| format!("invalid gossipsub config: {e}"))?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.seconds_per_slot == 0 {
+            return Err("seconds_per_slot must be positive".into());
+        }
+        if self.heartbeat_interval_ms == 0 {
+            return Err("heartbeat_interval_ms must be positive".into());
+        }
+        if self.duplicate_cache_time_ms == Some(0) {
+            return Err("duplicate_cache_time_ms must be positive".into());
+        }
+        // The builder checks the mesh degrees, history and transmit size against each other
+        self.builder()
+            .build()
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn heartbeat_interval(&self) -> Duration {
+        Duration::from_millis(self.heartbeat_interval_ms)
+    }
+
+    pub fn duplicate_cache_time(&self) -> Duration {
+        match self.duplicate_cache_time_ms {
+            Some(ms) => Duration::from_millis(ms),
+            None => Duration::from_secs(DUPLICATE_CACHE_SLOTS * self.seconds_per_slot),
+        }
+    }
+
+    fn builder(&self) -> gossipsub::ConfigBuilder {
+        let mut builder = gossipsub::ConfigBuilder::default();
+        builder
+            .mesh_n(self.mesh_n)
+            .mesh_n_low(self.mesh_n_low)
+            .mesh_n_high(self.mesh_n_high)
+            .gossip_lazy(self.gossip_lazy)
+            .heartbeat_interval(self.heartbeat_interval())
+            .validation_mode(gossipsub::ValidationMode::Anonymous)
+            .validate_messages() // forward only after Zig reports the validation result
+            .history_length(self.history_length)
+            .duplicate_cache_time(self.duplicate_cache_time())
+            .max_transmit_size(self.max_transmit_size)
+            .idontwant_message_size_threshold(self.idontwant_message_size_threshold);
+        builder
+    }
+
+    /// Builds the gossipsub config, content-addressing messages with `message_id_fn`.
+    pub fn to_gossipsub_config<F>(&self, message_id_fn: F) -> Result<gossipsub::Config, String>
+    where
+        F: Fn(&gossipsub::Message) -> gossipsub::MessageId + Send + Sync + 'static,
+    {
+        self.builder()
+            .message_id_fn(message_id_fn)
+            .build()
+            .map_err(|e| format!("invalid gossipsub config: {e}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_follow_the_slot_duration() {
+        let config = GossipsubConfig::from_json("").unwrap();
+        assert_eq!(config, GossipsubConfig::default());
+        assert_eq!(config.duplicate_cache_time(), Duration::from_secs(24));
+
+        let config = GossipsubConfig::from_json(r#"{"seconds_per_slot": 12}"#).unwrap();
+        assert_eq!(config.duplicate_cache_time(), Duration::from_secs(72));
+        assert_eq!(config.heartbeat_interval(), Duration::from_millis(700));
+
+        let config = GossipsubConfig::from_json(
+            r#"{"seconds_per_slot": 12, "duplicate_cache_time_ms": 5000}"#,
+        )
+        .unwrap();
+        assert_eq!(config.duplicate_cache_time(), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_invalid_combinations_are_errors() {
+        assert!(GossipsubConfig::from_json(r#"{"seconds_per_slot": 0}"#).is_err());
+        assert!(GossipsubConfig::from_json(r#"{"mesh_n": 4, "mesh_n_low": 6}"#).is_err());
+        assert!(GossipsubConfig::from_json(r#"{"mesh_n_high": 7}"#).is_err());
+        assert!(GossipsubConfig::from_json(r#"{"history_length": 1}"#).is_err());
+        assert!(GossipsubConfig::from_json(r#"{"mesh_d": 8}"#).is_err());
+
+        // Configs built in Rust skip from_json, building them reports the same errors
+        let config = GossipsubConfig {
+            mesh_n_low: 10,
+            ..GossipsubConfig::default()
+        };
+        assert!(config
+            .to_gossipsub_config(|message| gossipsub::MessageId::from(message.data.clone()))
+            .is_err());
+    }
+}
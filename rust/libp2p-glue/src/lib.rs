@@ -1,20 +1,31 @@
+pub mod discovery;
+pub mod gossipsub_config;
 pub mod logger;
+pub mod metrics;
+pub mod network_info;
+pub mod peer_manager;
+pub mod peer_score;
+pub mod peer_store;
+mod registry;
 pub mod req_resp;
+pub mod req_resp_config;
+pub mod status;
+pub mod transport;
 
-use futures::future::Either;
-use futures::Stream;
 use futures::StreamExt;
-use libp2p::core::{
-    multiaddr::Multiaddr, multiaddr::Protocol, muxing::StreamMuxerBox, transport::Boxed,
-};
+use libp2p::core::{multiaddr::Multiaddr, multiaddr::Protocol};
 
+use libp2p::core::transport::ListenerId;
 use libp2p::identity::{secp256k1, Keypair};
-use libp2p::swarm::{dial_opts::DialOpts, ConnectionId, NetworkBehaviour, SwarmEvent};
-use libp2p::{
-    core, gossipsub, identify, identity, noise, ping, yamux, PeerId, SwarmBuilder, Transport,
+use libp2p::swarm::{
+    behaviour::toggle::Toggle,
+    dial_opts::{DialOpts, PeerCondition},
+    ConnectionId, NetworkBehaviour, SwarmEvent,
 };
+use libp2p::{core, gossipsub, identify, identity, ping, PeerId, SwarmBuilder};
 use std::convert::TryFrom;
 use std::os::raw::c_char;
+use std::path::PathBuf;
 use std::time::Duration;
 use tokio::runtime::Builder;
 
@@ -23,109 +34,75 @@ use snap::raw::Decoder;
 use std::ffi::{CStr, CString};
 
 use delay_map::HashMapDelay;
-use futures::future::poll_fn;
-use std::collections::HashMap;
+use prometheus_client::registry::Registry;
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Mutex;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+use crate::discovery::{
+    build_local_enr, enr_key_from_keypair, parse_addresses, Discovery, DiscoveryConfig,
+    DiscoveryEvent, LocalEnrConfig,
+};
+use crate::gossipsub_config::GossipsubConfig;
+use crate::metrics::{GossipDirection, NetworkMetrics, RequestOutcome};
+use crate::network_info::{NetworkInfo, PeerMetadataStore};
+use crate::peer_manager::{
+    reqresp_error_action, PeerAction, PeerManager, PeerManagerConfig, PeerManagerEvent,
+    DEFAULT_BAN_DURATION,
+};
+use crate::peer_score::PeerScoreConfig;
+use crate::peer_store::{unix_now, PeerStore, PEER_STORE_MAX_AGE};
+use crate::registry::NETWORKS;
+use crate::req_resp_config::ReqRespConfig;
+use crate::status::{Status, StatusHandshakes};
+use crate::transport::{build_transport, TransportConfig};
 
 use crate::req_resp::{
     configurations::REQUEST_TIMEOUT,
     configurations::RESPONSE_CHANNEL_IDLE_TIMEOUT,
-    varint::{encode_varint, MAX_VARINT_BYTES},
-    LeanSupportedProtocol, ProtocolId, ReqResp, ReqRespMessage, ReqRespMessageError,
-    ReqRespMessageReceived, RequestMessage, ResponseMessage,
+    error::ReqRespError,
+    messages::GoodbyeReason,
+    outbound_queue::OutboundQueue,
+    protocol_id::GOODBYE_SSZ_LEN,
+    varint::{decode_snappy_payload, encode_error_response, encode_snappy_payload},
+    ForkContext, LeanProtocolFamily, LeanSupportedProtocol, ProtocolId, RateLimiterConfig, ReqResp,
+    ReqRespMessage, ReqRespMessageError, ReqRespMessageReceived, RequestMessage, ResponseCode,
+    ResponseMessage, MAX_CONCURRENT_REQUESTS,
 };
 
-type BoxedTransport = Boxed<(PeerId, StreamMuxerBox)>;
-
-// TODO: protect the access by mutex
-#[allow(static_mut_refs)]
-static mut SWARM_STATE: Option<libp2p::swarm::Swarm<Behaviour>> = None;
-// additional network slots for multi-node testing
-#[allow(static_mut_refs)]
-static mut SWARM_STATE1: Option<libp2p::swarm::Swarm<Behaviour>> = None;
-#[allow(static_mut_refs)]
-static mut SWARM_STATE2: Option<libp2p::swarm::Swarm<Behaviour>> = None;
-
-// Store Zig handler pointers per network id so free functions can forward logs
-#[allow(static_mut_refs)]
-static mut ZIG_HANDLER0: Option<u64> = None;
-#[allow(static_mut_refs)]
-static mut ZIG_HANDLER1: Option<u64> = None;
-#[allow(static_mut_refs)]
-static mut ZIG_HANDLER2: Option<u64> = None;
-
-/// Get a mutable reference to the swarm for the given network id.
-///
-/// # Safety
-/// The caller must ensure no other thread is concurrently writing to the same slot.
-#[allow(static_mut_refs)]
-unsafe fn get_swarm_mut(network_id: u32) -> Option<&'static mut libp2p::swarm::Swarm<Behaviour>> {
-    match network_id {
-        0 => SWARM_STATE.as_mut(),
-        1 => SWARM_STATE1.as_mut(),
-        2 => SWARM_STATE2.as_mut(),
-        _ => None,
-    }
-}
+static REQUEST_ID_COUNTER: AtomicU64 = AtomicU64::new(0);
+static RESPONSE_CHANNEL_COUNTER: AtomicU64 = AtomicU64::new(0);
 
-/// Store a swarm in the global slot for the given network id.
-///
-/// # Safety
-/// The caller must ensure no other thread is concurrently accessing the same slot.
-unsafe fn set_swarm(network_id: u32, swarm: libp2p::swarm::Swarm<Behaviour>) {
-    match network_id {
-        0 => SWARM_STATE = Some(swarm),
-        1 => SWARM_STATE1 = Some(swarm),
-        2 => SWARM_STATE2 = Some(swarm),
-        _ => panic!("unsupported network_id: {}", network_id),
-    }
-}
+const MAX_RECONNECT_ATTEMPTS: u32 = 5;
+const RECONNECT_DELAYS_SECS: [u64; 5] = [5, 10, 20, 40, 80];
 
-/// Store the Zig handler pointer for the given network id.
-///
-/// # Safety
-/// The caller must ensure no other thread is concurrently accessing the same slot.
-unsafe fn set_zig_handler(network_id: u32, handler: u64) {
-    match network_id {
-        0 => ZIG_HANDLER0 = Some(handler),
-        1 => ZIG_HANDLER1 = Some(handler),
-        2 => ZIG_HANDLER2 = Some(handler),
-        _ => {}
-    }
-}
+/// Upper bound on how long `stop_network` waits for in-flight req/resp streams to drain.
+const SHUTDOWN_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
 
-/// Get the Zig handler pointer for the given network id.
-///
-/// # Safety
-/// The caller must ensure no other thread is concurrently writing to the same slot.
-unsafe fn get_zig_handler(network_id: u32) -> Option<u64> {
-    match network_id {
-        0 => ZIG_HANDLER0,
-        1 => ZIG_HANDLER1,
-        2 => ZIG_HANDLER2,
-        _ => None,
-    }
-}
+/// How often a discovery lookup is started while the node is below its target peer count.
+const DISCOVERY_QUERY_INTERVAL: Duration = Duration::from_secs(3);
 
-lazy_static::lazy_static! {
-    static ref REQUEST_ID_MAP: Mutex<HashMapDelay<u64, ()>> = Mutex::new(HashMapDelay::new(REQUEST_TIMEOUT));
-    static ref REQUEST_PROTOCOL_MAP: Mutex<HashMap<u64, ProtocolId>> = Mutex::new(HashMap::new());
-    static ref RESPONSE_CHANNEL_MAP: Mutex<HashMapDelay<u64, PendingResponse>> = Mutex::new(HashMapDelay::new(RESPONSE_CHANNEL_IDLE_TIMEOUT));
-    static ref NETWORK_READY_SIGNALS: std::sync::Mutex<(bool, bool, bool)> = std::sync::Mutex::new((false, false, false));
-    static ref NETWORK_READY_CONDVAR: std::sync::Condvar = std::sync::Condvar::new();
-    static ref RECONNECT_QUEUE: Mutex<HashMapDelay<(u32, PeerId), (Multiaddr, u32)>> =
-        Mutex::new(HashMapDelay::new(Duration::from_secs(5))); // default delay, will be overridden
-    static ref RECONNECT_ATTEMPTS: Mutex<HashMap<(u32, PeerId), (Multiaddr, u32)>> = Mutex::new(HashMap::new());
-    // Track connection directions for disconnect events (network_id, peer_id, connection_id) -> direction
-    static ref CONNECTION_DIRECTIONS: Mutex<HashMap<(u32, PeerId, ConnectionId), u32>> = Mutex::new(HashMap::new());
-}
+/// How long a synchronous FFI query waits for the event loop to answer.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
 
-static REQUEST_ID_COUNTER: AtomicU64 = AtomicU64::new(0);
-static RESPONSE_CHANNEL_COUNTER: AtomicU64 = AtomicU64::new(0);
+/// How often the peer store is written to its file while the network runs.
+const PEER_STORE_SAVE_INTERVAL: Duration = Duration::from_secs(60);
 
-const MAX_RECONNECT_ATTEMPTS: u32 = 5;
-const RECONNECT_DELAYS_SECS: [u64; 5] = [5, 10, 20, 40, 80];
+/// How long a peer gets to take a Goodbye request before it is disconnected anyway.
+const GOODBYE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Name gossipsub gives peers that negotiated gossipsub v1.2, the version with IDONTWANT.
+const GOSSIPSUB_V1_2_PEER_KIND: &str = "Gossipsub v1.2";
+
+/// Error code reported to Zig for outbound requests that timed out. Like `LOCAL_FAILURE_CODE`
+/// it is outside the spec's response codes, which are only used for error chunks of the peer.
+const REQUEST_TIMEOUT_CODE: u32 = 408;
+
+/// Error code reported to Zig for outbound requests that failed without an error chunk of the
+/// peer, e.g. the stream could not be opened or the response could not be decoded.
+const LOCAL_FAILURE_CODE: u32 = 500;
+
+type NetworkQuery = Box<dyn FnOnce(&mut Network, &mut libp2p::swarm::Swarm<Behaviour>) + Send>;
 
 #[derive(Clone)]
 struct PendingResponse {
@@ -135,49 +112,104 @@ struct PendingResponse {
     protocol: ProtocolId,
 }
 
+/// An outbound request with the payload of every version of the message it offers.
+pub(crate) struct OutboundRequest {
+    peer_id: PeerId,
+    request_id: u64,
+    protocol: LeanSupportedProtocol,
+    payload: Vec<u8>,
+    /// Older versions of the message with their own payloads, in preference order.
+    fallbacks: Vec<(LeanSupportedProtocol, Vec<u8>)>,
+}
+
+/// Work handed from the FFI entry points to the event loop of a network.
+///
+/// The swarm is only ever touched by the thread running the event loop, so every FFI call that
+/// needs it is turned into a command and queued through the network registry.
+pub(crate) enum NetworkCommand {
+    Publish {
+        topic: gossipsub::IdentTopic,
+        data: Vec<u8>,
+    },
+    // Boxed, the versions and their payloads would make every command as large
+    SendRequest(Box<OutboundRequest>),
+    SendResponseChunk {
+        channel_id: u64,
+        payload: Vec<u8>,
+    },
+    EndOfStream {
+        channel_id: u64,
+    },
+    SendErrorResponse {
+        channel_id: u64,
+        code: ResponseCode,
+        message: String,
+    },
+    ReportGossipValidation {
+        message_id: gossipsub::MessageId,
+        acceptance: gossipsub::MessageAcceptance,
+    },
+    Subscribe {
+        topic: gossipsub::IdentTopic,
+    },
+    Unsubscribe {
+        topic: gossipsub::IdentTopic,
+    },
+    BanPeer {
+        peer_id: PeerId,
+        duration: Duration,
+    },
+    UnbanPeer {
+        peer_id: PeerId,
+    },
+    DisconnectPeer {
+        peer_id: PeerId,
+        reason: u64,
+    },
+    CancelRequest {
+        request_id: u64,
+    },
+    SetLocalStatus {
+        status: Status,
+    },
+    /// Runs a closure against the network state on the event loop, see `query_network`.
+    Query(NetworkQuery),
+    Shutdown,
+}
+
 /// Wait for a network to be fully initialized and ready to accept messages.
-/// Returns true if the network is ready, false on timeout.
+/// Returns a `Readiness` code: 0 if the network is ready, 1 on timeout and 2 if it failed to
+/// start, e.g. because of an invalid config. The reason of a failed start is logged through the
+/// network's Zig handler.
 ///
 /// # Safety
 ///
 /// This function is thread-safe and can be called from any thread.
 #[no_mangle]
-pub unsafe fn wait_for_network_ready(network_id: u32, timeout_ms: u64) -> bool {
-    let timeout = Duration::from_millis(timeout_ms);
-    let deadline = std::time::Instant::now() + timeout;
-
-    let mut ready = NETWORK_READY_SIGNALS.lock().unwrap();
-    loop {
-        if match network_id {
-            0 => ready.0,
-            1 => ready.1,
-            2 => ready.2,
-            _ => false,
-        } {
-            return true;
-        }
-
-        let now = std::time::Instant::now();
-        if now >= deadline {
-            return false;
-        }
-
-        let remaining = deadline - now;
-        let (guard, timeout_result) = NETWORK_READY_CONDVAR
-            .wait_timeout(ready, remaining)
-            .unwrap();
-        ready = guard;
-
-        if timeout_result.timed_out() {
-            return false;
-        }
-    }
+pub unsafe fn wait_for_network_ready(network_id: u32, timeout_ms: u64) -> u32 {
+    NETWORKS.wait_until_ready(network_id, Duration::from_millis(timeout_ms)) as u32
 }
 
 /// # Safety
 ///
 /// The caller must ensure that `listen_addresses` and `connect_addresses` point to valid null-terminated C strings.
+/// `peer_score_params` must be null or point to a null-terminated JSON document (see
+/// `peer_score::PeerScoreConfig`); null or an empty string selects the default scoring.
+/// `transport_config` likewise selects the transports, muxers, security and dial timeout, see
+/// `transport::TransportConfig`, `gossipsub_config` the mesh, heartbeat and cache settings of
+/// gossipsub, see `gossipsub_config::GossipsubConfig`, and `req_resp_config` the fork digests
+/// used as context bytes and the outbound request concurrency, see
+/// `req_resp_config::ReqRespConfig`.
+/// `target_peers` and `max_peers` configure the peer manager, 0 selects the default.
+/// `discovery_port` is the UDP port of discv5, 0 disables discovery. `bootnodes` must be null or
+/// point to a null-terminated, comma-separated list of ENRs. `peer_store_path` must be null or
+/// point to the null-terminated path of the file known peers are persisted to and seeded from,
+/// see `peer_store::PeerStore`; null or an empty string keeps them in memory only. With
+/// `decompress_rpc_payloads` the req/resp payloads exchanged with Zig are plain SSZ, see
+/// `NetworkConfig`.
+/// Invalid parameters are logged and make `wait_for_network_ready` report a failed start.
 #[no_mangle]
+#[allow(clippy::too_many_arguments)]
 pub unsafe fn create_and_run_network(
     network_id: u32,
     zig_handler: u64,
@@ -185,19 +217,25 @@ pub unsafe fn create_and_run_network(
     listen_addresses: *const c_char,
     connect_addresses: *const c_char,
     topics_str: *const c_char,
+    peer_score_params: *const c_char,
+    transport_config: *const c_char,
+    gossipsub_config: *const c_char,
+    req_resp_config: *const c_char,
+    target_peers: u32,
+    max_peers: u32,
+    discovery_port: u16,
+    bootnodes: *const c_char,
+    peer_store_path: *const c_char,
+    decompress_rpc_payloads: bool,
 ) {
-    let listen_multiaddrs = CStr::from_ptr(listen_addresses)
-        .to_string_lossy()
-        .split(",")
-        .map(|addr| addr.parse::<Multiaddr>().expect("Invalid multiaddress"))
-        .collect::<Vec<_>>();
+    let listen_multiaddrs =
+        parse_addresses(&CStr::from_ptr(listen_addresses).to_string_lossy(), false)
+            .map_err(|e| format!("invalid listen address: {e}"));
 
-    let connect_multiaddrs = CStr::from_ptr(connect_addresses)
-        .to_string_lossy()
-        .split(",")
-        .filter(|s| !s.trim().is_empty()) // filter out empty strings because connect_addresses can be empty
-        .map(|addr| addr.parse::<Multiaddr>().expect("Invalid multiaddress"))
-        .collect::<Vec<_>>();
+    // connect_addresses can be empty, ENRs are expanded to multiaddrs with their peer id
+    let connect_multiaddrs =
+        parse_addresses(&CStr::from_ptr(connect_addresses).to_string_lossy(), true)
+            .map_err(|e| format!("invalid connect address: {e}"));
 
     let topics = CStr::from_ptr(topics_str)
         .to_string_lossy()
@@ -206,24 +244,61 @@ pub unsafe fn create_and_run_network(
         .filter(|s| !s.is_empty())
         .collect::<Vec<_>>();
 
-    let local_private_key_hex = CStr::from_ptr(local_private_key)
-        .to_string_lossy()
-        .into_owned();
+    let peer_score = if peer_score_params.is_null() {
+        Ok(PeerScoreConfig::default())
+    } else {
+        PeerScoreConfig::from_json(&CStr::from_ptr(peer_score_params).to_string_lossy())
+    };
+    let transport = if transport_config.is_null() {
+        Ok(TransportConfig::default())
+    } else {
+        TransportConfig::from_json(&CStr::from_ptr(transport_config).to_string_lossy())
+    };
+    let gossipsub = if gossipsub_config.is_null() {
+        Ok(GossipsubConfig::default())
+    } else {
+        GossipsubConfig::from_json(&CStr::from_ptr(gossipsub_config).to_string_lossy())
+    };
+    let req_resp = if req_resp_config.is_null() {
+        Ok(ReqRespConfig::default())
+    } else {
+        ReqRespConfig::from_json(&CStr::from_ptr(req_resp_config).to_string_lossy())
+    };
 
-    let private_key_hex = local_private_key_hex
-        .strip_prefix("0x")
-        .unwrap_or(&local_private_key_hex);
+    let bootnode_enrs = if bootnodes.is_null() {
+        Vec::new()
+    } else {
+        CStr::from_ptr(bootnodes)
+            .to_string_lossy()
+            .split(",")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .filter_map(|enr| match enr.parse::<discv5::Enr>() {
+                Ok(enr) => Some(enr),
+                Err(e) => {
+                    forward_log_with_handler(
+                        zig_handler,
+                        3,
+                        &format!("Ignoring invalid bootnode ENR {}: {}", enr, e),
+                    );
+                    None
+                }
+            })
+            .collect::<Vec<_>>()
+    };
 
-    let mut private_key_bytes =
-        hex::decode(private_key_hex).expect("Invalid hex string for private key");
+    let peer_store_file = if peer_store_path.is_null() {
+        None
+    } else {
+        let path = CStr::from_ptr(peer_store_path).to_string_lossy();
+        (!path.trim().is_empty()).then(|| PathBuf::from(path.as_ref()))
+    };
 
-    let local_key_pair = Keypair::from(secp256k1::Keypair::from(
-        secp256k1::SecretKey::try_from_bytes(&mut private_key_bytes)
-            .expect("Invalid private key bytes"),
-    ));
+    let local_key_pair = keypair_from_hex(&CStr::from_ptr(local_private_key).to_string_lossy())
+        .map_err(|e| format!("invalid private key: {e}"));
 
-    // Store zig_handler for this network id for use by free functions
-    set_zig_handler(network_id, zig_handler);
+    // Register the network so free functions can forward logs through its zig_handler
+    let registered = NETWORKS.register(network_id, zig_handler);
 
     releaseStartNetworkParams(
         zig_handler,
@@ -231,22 +306,227 @@ pub unsafe fn create_and_run_network(
         listen_addresses,
         connect_addresses,
         topics_str,
+        peer_score_params,
+        transport_config,
+        gossipsub_config,
+        req_resp_config,
+        bootnodes,
+        peer_store_path,
     );
 
-    let rt = Builder::new_current_thread().enable_all().build().unwrap();
+    if !registered {
+        forward_log_with_handler(
+            zig_handler,
+            3,
+            &format!("network_id {} is already in use", network_id),
+        );
+        return;
+    }
+
+    // Invalid parameters fail the start, `wait_for_network_ready` reports it to Zig
+    let start = (|| -> Result<(Keypair, NetworkConfig), String> {
+        let req_resp = req_resp?;
+        let config = NetworkConfig {
+            listen_addresses: listen_multiaddrs?,
+            connect_addresses: connect_multiaddrs?,
+            topics,
+            peer_score: peer_score?,
+            transport: transport?,
+            gossipsub: gossipsub?,
+            peer_manager: PeerManagerConfig::from_limits(target_peers, max_peers),
+            discovery: (discovery_port != 0).then(|| DiscoveryConfig {
+                udp_port: discovery_port,
+                bootnodes: bootnode_enrs,
+            }),
+            fork_context: req_resp.fork_context()?,
+            rate_limiter: req_resp.rate_limits,
+            max_concurrent_requests: req_resp.max_concurrent_requests,
+            peer_store_path: peer_store_file,
+            decompress_rpc_payloads,
+        };
+        Ok((local_key_pair?, config))
+    })();
+    let rt = start.and_then(|start| {
+        Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map(|rt| (rt, start))
+            .map_err(|e| format!("failed to build the tokio runtime: {e}"))
+    });
+    let (rt, (local_key_pair, config)) = match rt {
+        Ok(rt) => rt,
+        Err(e) => {
+            forward_log_with_handler(zig_handler, 3, &e);
+            NETWORKS.mark_failed(network_id);
+            NETWORKS.remove(network_id);
+            return;
+        }
+    };
 
     rt.block_on(async move {
         let mut p2p_net = Network::new(network_id, zig_handler);
-        p2p_net
-            .start_network(
-                local_key_pair,
-                listen_multiaddrs,
-                connect_multiaddrs,
-                topics,
-            )
-            .await;
-        p2p_net.run_eventloop().await;
+        if p2p_net.start_network(local_key_pair, config).await {
+            p2p_net.run_eventloop().await;
+        } else {
+            NETWORKS.mark_failed(network_id);
+        }
     });
+
+    // Only release the network id once the runtime is gone so `stop_network` callers can rely
+    // on nothing of this network running anymore.
+    drop(rt);
+    NETWORKS.remove(network_id);
+}
+
+/// Decodes a hex secp256k1 secret key, with or without `0x` prefix, into the node identity.
+fn keypair_from_hex(private_key_hex: &str) -> Result<Keypair, String> {
+    let private_key_hex = private_key_hex
+        .strip_prefix("0x")
+        .unwrap_or(private_key_hex);
+
+    let mut private_key_bytes =
+        hex::decode(private_key_hex).map_err(|e| format!("invalid hex string: {}", e))?;
+    let secret_key = secp256k1::SecretKey::try_from_bytes(&mut private_key_bytes)
+        .map_err(|e| format!("invalid private key bytes: {}", e))?;
+    Ok(Keypair::from(secp256k1::Keypair::from(secret_key)))
+}
+
+/// Builds and signs the ENR of the local node and writes its `enr:` text form into `buf`.
+///
+/// The record is signed with `local_private_key` (hex secp256k1 secret) and advertises the ip,
+/// `tcp` and `quic` ports of `listen_addresses` as well as `udp_port` for discovery, 0 leaves
+/// the udp port out. `ip` overrides the advertised ip, which is needed when listening on a
+/// wildcard address; null or an empty string uses the listen addresses.
+///
+/// Returns the length of the ENR. If it is larger than `buf_len` nothing is written and the
+/// caller should retry with a larger buffer. Returns 0 if the inputs are invalid.
+///
+/// # Safety
+///
+/// `local_private_key` and `listen_addresses` must point to valid null-terminated C strings,
+/// `ip` must be null or point to one. `buf` must point to writable memory of `buf_len` bytes.
+#[no_mangle]
+pub unsafe fn generate_local_enr(
+    zig_handler: u64,
+    local_private_key: *const c_char,
+    listen_addresses: *const c_char,
+    ip: *const c_char,
+    udp_port: u16,
+    buf: *mut u8,
+    buf_len: usize,
+) -> usize {
+    let build = || -> Result<String, String> {
+        if local_private_key.is_null() || listen_addresses.is_null() {
+            return Err("null pointer passed to generate_local_enr".to_string());
+        }
+        let key_pair = keypair_from_hex(&CStr::from_ptr(local_private_key).to_string_lossy())?;
+        let listen_multiaddrs =
+            parse_addresses(&CStr::from_ptr(listen_addresses).to_string_lossy(), false)?;
+
+        let mut config = LocalEnrConfig::from_listen_addresses(
+            &listen_multiaddrs,
+            (udp_port != 0).then_some(udp_port),
+        );
+        let ip = if ip.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(ip).to_string_lossy().trim().to_string()
+        };
+        if !ip.is_empty() {
+            match ip
+                .parse::<std::net::IpAddr>()
+                .map_err(|e| format!("invalid ip {}: {}", ip, e))?
+            {
+                std::net::IpAddr::V4(ip4) => config.ip4 = Some(ip4),
+                std::net::IpAddr::V6(ip6) => config.ip6 = Some(ip6),
+            }
+        }
+
+        let enr = build_local_enr(&enr_key_from_keypair(&key_pair)?, &config)?;
+        Ok(enr.to_base64())
+    };
+
+    match build() {
+        Ok(enr) => write_text_to_buffer(&enr, buf, buf_len),
+        Err(e) => {
+            forward_log_with_handler(
+                zig_handler,
+                3,
+                &format!("Failed to generate local ENR: {}", e),
+            );
+            0
+        }
+    }
+}
+
+/// Stops a running network: closes its listeners, drains in-flight req/resp streams and drops
+/// the swarm together with all of its request, response and reconnect state.
+///
+/// Returns true once the network's tokio runtime has exited, or false if no running network is
+/// registered under `network_id`.
+///
+/// # Safety
+///
+/// This function blocks until the event loop of the network has exited, so it must not be
+/// called from within a callback invoked by that same network.
+#[no_mangle]
+pub unsafe fn stop_network(network_id: u32) -> bool {
+    if !NETWORKS.request_stop(network_id, NetworkCommand::Shutdown) {
+        logger::rustLogger.error(
+            network_id,
+            "stop_network called for a network that is not running",
+        );
+        return false;
+    }
+
+    logger::rustLogger.info(network_id, "stopping network");
+    NETWORKS.wait_until_removed(network_id);
+    true
+}
+
+/// Runs `query` on the event loop of `network_id` and waits for its result.
+///
+/// Returns `None` if the network is not running or does not answer within `QUERY_TIMEOUT`.
+/// Must not be called from a bridge callback, since those run on the event loop itself.
+fn query_network<T, F>(network_id: u32, context: &str, query: F) -> Option<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut Network, &mut libp2p::swarm::Swarm<Behaviour>) -> T + Send + 'static,
+{
+    let (result_tx, result_rx) = std::sync::mpsc::sync_channel(1);
+    let command = NetworkCommand::Query(Box::new(move |network, swarm| {
+        // The caller may have given up waiting already.
+        let _ = result_tx.send(query(network, swarm));
+    }));
+    if !dispatch_command(network_id, command, context) {
+        return None;
+    }
+
+    match result_rx.recv_timeout(QUERY_TIMEOUT) {
+        Ok(result) => Some(result),
+        Err(_) => {
+            logger::rustLogger.error(
+                network_id,
+                &format!("{} timed out waiting for the event loop", context),
+            );
+            None
+        }
+    }
+}
+
+/// Queue a command for the event loop of `network_id`, logging `context` if the network
+/// is not running.
+fn dispatch_command(network_id: u32, command: NetworkCommand, context: &str) -> bool {
+    match NETWORKS.send(network_id, command) {
+        Ok(()) => true,
+        Err(_) => {
+            logger::rustLogger.error(
+                network_id,
+                &format!("{} called before network initialized", context),
+            );
+            false
+        }
+    }
 }
 
 /// # Safety
@@ -283,205 +563,570 @@ pub unsafe fn publish_msg_to_rust_bridge(
     let topic = CStr::from_ptr(topic).to_string_lossy().to_string();
     let topic = gossipsub::IdentTopic::new(topic);
 
-    let swarm = match unsafe { get_swarm_mut(network_id) } {
-        Some(s) => s,
-        None => {
-            logger::rustLogger.error(
-                network_id,
-                "publish_msg_to_rust_bridge called before network initialized",
-            );
-            return;
-        }
-    };
-    if let Err(e) = swarm
-        .behaviour_mut()
-        .gossipsub
-        .publish(topic.clone(), message_data)
-    {
-        logger::rustLogger.error(network_id, &format!("Publish error: {e:?}"));
-    }
+    dispatch_command(
+        network_id,
+        NetworkCommand::Publish {
+            topic,
+            data: message_data,
+        },
+        "publish_msg_to_rust_bridge",
+    );
 }
 
+/// Reports the outcome of Zig's validation of a gossip message delivered through
+/// `handleMsgFromRustBridge`. `acceptance` is 0=accept, 1=reject, 2=ignore: accepted messages
+/// are forwarded to the mesh, rejected ones penalize the peer that propagated them and ignored
+/// ones are dropped without penalty.
+///
 /// # Safety
 ///
-/// The caller must ensure that `peer_id` points to a valid null-terminated C string.
-/// The caller must ensure that `request_data` points to valid memory of `request_len` bytes.
+/// The caller must ensure that `message_id` points to a valid null-terminated C string.
 #[no_mangle]
-pub unsafe fn send_rpc_request(
+pub unsafe fn report_gossip_validation(
     network_id: u32,
-    peer_id: *const c_char,
-    protocol_tag: u32,
-    request_data: *const u8,
-    request_len: usize,
-) -> u64 {
-    let peer_id_str = CStr::from_ptr(peer_id).to_string_lossy().to_string();
-    let peer_id: PeerId = match peer_id_str.parse() {
-        Ok(id) => id,
-        Err(e) => {
-            logger::rustLogger.error(network_id, &format!("Invalid peer ID: {}", e));
-            return 0;
-        }
-    };
-
-    let request_slice = std::slice::from_raw_parts(request_data, request_len);
-    let request_bytes = request_slice.to_vec();
+    message_id: *const c_char,
+    acceptance: u32,
+) {
+    if message_id.is_null() {
+        logger::rustLogger.error(
+            network_id,
+            "null pointer passed for `message_id` in report_gossip_validation",
+        );
+        return;
+    }
 
-    let protocol = match LeanSupportedProtocol::try_from(protocol_tag) {
-        Ok(protocol) => protocol,
-        Err(_) => {
+    let message_id_hex = CStr::from_ptr(message_id).to_string_lossy();
+    let message_id = match hex::decode(message_id_hex.as_ref()) {
+        Ok(bytes) => gossipsub::MessageId::new(&bytes),
+        Err(e) => {
             logger::rustLogger.error(
                 network_id,
-                &format!(
-                    "Invalid protocol tag {} provided for RPC request to {}",
-                    protocol_tag, peer_id_str
-                ),
+                &format!("Invalid gossip message id {}: {}", message_id_hex, e),
             );
-            return 0;
+            return;
         }
     };
 
-    let protocol_id: ProtocolId = protocol.into();
-
-    let swarm = match get_swarm_mut(network_id) {
-        Some(s) => s,
-        None => {
+    let acceptance = match acceptance {
+        0 => gossipsub::MessageAcceptance::Accept,
+        1 => gossipsub::MessageAcceptance::Reject,
+        2 => gossipsub::MessageAcceptance::Ignore,
+        _ => {
             logger::rustLogger.error(
                 network_id,
-                "send_rpc_request called before network initialized",
+                &format!(
+                    "Invalid gossip validation result {} for message {}",
+                    acceptance, message_id_hex
+                ),
             );
-            return 0;
+            return;
         }
     };
 
-    let request_id = REQUEST_ID_COUNTER.fetch_add(1, Ordering::Relaxed) + 1;
-
-    let request_message = RequestMessage::new(protocol_id.clone(), request_bytes);
-
-    swarm
-        .behaviour_mut()
-        .reqresp
-        .send_request(peer_id, request_id, request_message);
-
-    REQUEST_ID_MAP.lock().unwrap().insert(request_id, ());
-    REQUEST_PROTOCOL_MAP
-        .lock()
-        .unwrap()
-        .insert(request_id, protocol_id.clone());
-
-    logger::rustLogger.info(
+    dispatch_command(
         network_id,
-        &format!(
-            "[reqresp] Sent {:?} request to {} (id: {})",
-            protocol, peer_id, request_id
-        ),
+        NetworkCommand::ReportGossipValidation {
+            message_id,
+            acceptance,
+        },
+        "report_gossip_validation",
     );
-
-    request_id
 }
 
+/// Subscribes to a gossipsub topic while the network is running. Peers joining or leaving the
+/// topic mesh are reported through `handleTopicMeshEventFromRustBridge`.
+///
+/// Returns false if the network is not running.
+///
 /// # Safety
-/// The caller must ensure that `response_data` points to valid memory of `response_len` bytes.
+///
+/// The caller must ensure that `topic` points to a valid null-terminated C string.
 #[no_mangle]
-pub unsafe fn send_rpc_response_chunk(
-    network_id: u32,
-    channel_id: u64,
-    response_data: *const u8,
-    response_len: usize,
-) {
-    let response_slice = std::slice::from_raw_parts(response_data, response_len);
-    let response_bytes = response_slice.to_vec();
-
-    let channel = {
-        let mut response_map = RESPONSE_CHANNEL_MAP.lock().unwrap();
-        let channel = response_map.get(&channel_id).cloned();
-        if channel.is_some() {
-            _ = response_map.update_timeout(&channel_id, RESPONSE_CHANNEL_IDLE_TIMEOUT);
-        }
-        channel
-    };
-
-    if let Some(channel) = channel {
-        let swarm = match get_swarm_mut(network_id) {
-            Some(s) => s,
-            None => {
-                logger::rustLogger.error(
-                    network_id,
-                    "send_rpc_response_chunk called before network initialized",
-                );
-                return;
-            }
-        };
-
-        let response_message = ResponseMessage::new(channel.protocol.clone(), response_bytes);
-
-        swarm.behaviour_mut().reqresp.send_response(
-            channel.peer_id,
-            channel.connection_id,
-            channel.stream_id,
-            response_message,
-        );
-        logger::rustLogger.info(
-            network_id,
-            &format!(
-                "[reqresp] Sent response payload on channel {} (peer: {})",
-                channel_id, channel.peer_id
-            ),
-        );
-    } else {
+pub unsafe fn subscribe_topic(network_id: u32, topic: *const c_char) -> bool {
+    if topic.is_null() {
         logger::rustLogger.error(
             network_id,
-            &format!("No response channel found for id {}", channel_id),
+            "null pointer passed for `topic` in subscribe_topic",
         );
+        return false;
     }
+
+    let topic = gossipsub::IdentTopic::new(CStr::from_ptr(topic).to_string_lossy().to_string());
+    dispatch_command(
+        network_id,
+        NetworkCommand::Subscribe { topic },
+        "subscribe_topic",
+    )
 }
 
+/// Unsubscribes from a gossipsub topic while the network is running.
+///
+/// Returns false if the network is not running.
+///
 /// # Safety
-/// The caller must ensure the channel id is valid for a pending response.
+///
+/// The caller must ensure that `topic` points to a valid null-terminated C string.
 #[no_mangle]
-pub unsafe fn send_rpc_end_of_stream(network_id: u32, channel_id: u64) {
-    let channel = {
-        let mut response_map = RESPONSE_CHANNEL_MAP.lock().unwrap();
-        response_map.remove(&channel_id)
-    };
+pub unsafe fn unsubscribe_topic(network_id: u32, topic: *const c_char) -> bool {
+    if topic.is_null() {
+        logger::rustLogger.error(
+            network_id,
+            "null pointer passed for `topic` in unsubscribe_topic",
+        );
+        return false;
+    }
 
-    if let Some(channel) = channel {
-        let swarm = match get_swarm_mut(network_id) {
-            Some(s) => s,
-            None => {
-                logger::rustLogger.error(
+    let topic = gossipsub::IdentTopic::new(CStr::from_ptr(topic).to_string_lossy().to_string());
+    dispatch_command(
+        network_id,
+        NetworkCommand::Unsubscribe { topic },
+        "unsubscribe_topic",
+    )
+}
+
+/// Bans a peer for `duration_secs` seconds (0 selects the default ban duration) and disconnects
+/// it. Connections to and from a banned peer are denied until the ban expires.
+///
+/// Returns false if the peer id is invalid or the network is not running.
+///
+/// # Safety
+///
+/// The caller must ensure that `peer_id` points to a valid null-terminated C string.
+#[no_mangle]
+pub unsafe fn ban_peer(network_id: u32, peer_id: *const c_char, duration_secs: u64) -> bool {
+    let Some(peer_id) = parse_peer_id(network_id, peer_id, "ban_peer") else {
+        return false;
+    };
+    let duration = match duration_secs {
+        0 => DEFAULT_BAN_DURATION,
+        secs => Duration::from_secs(secs),
+    };
+
+    dispatch_command(
+        network_id,
+        NetworkCommand::BanPeer { peer_id, duration },
+        "ban_peer",
+    )
+}
+
+/// Lifts the ban of a peer and resets its reputation.
+///
+/// Returns false if the peer id is invalid or the network is not running.
+///
+/// # Safety
+///
+/// The caller must ensure that `peer_id` points to a valid null-terminated C string.
+#[no_mangle]
+pub unsafe fn unban_peer(network_id: u32, peer_id: *const c_char) -> bool {
+    let Some(peer_id) = parse_peer_id(network_id, peer_id, "unban_peer") else {
+        return false;
+    };
+
+    dispatch_command(
+        network_id,
+        NetworkCommand::UnbanPeer { peer_id },
+        "unban_peer",
+    )
+}
+
+/// Sends a Goodbye request with `reason` to a peer and disconnects it once the peer took the
+/// request, failed it or `GOODBYE_TIMEOUT` elapsed. The peer is not redialed afterwards. See
+/// `GoodbyeReason` for the known reason codes, any other value is sent as is.
+///
+/// Returns false if the peer id is invalid or the network is not running.
+///
+/// # Safety
+///
+/// The caller must ensure that `peer_id` points to a valid null-terminated C string.
+#[no_mangle]
+pub unsafe fn disconnect_peer(network_id: u32, peer_id: *const c_char, reason: u64) -> bool {
+    let Some(peer_id) = parse_peer_id(network_id, peer_id, "disconnect_peer") else {
+        return false;
+    };
+
+    dispatch_command(
+        network_id,
+        NetworkCommand::DisconnectPeer { peer_id, reason },
+        "disconnect_peer",
+    )
+}
+
+/// Sets the chain status the glue sends in the Status handshake with every newly connected peer
+/// and answers Status requests with. Until it is first set, no handshakes are made and Status
+/// requests are passed to Zig; the peers connected by then get their handshake once it is set.
+/// The status of every peer that answers or sends a Status request is reported through
+/// `handlePeerStatusFromRustBridge`, peers failing the handshake are disconnected.
+///
+/// Returns false if the network is not running.
+///
+/// # Safety
+///
+/// The caller must ensure that `finalized_root` and `head_root` point to 32 bytes each.
+#[no_mangle]
+pub unsafe fn set_local_status(
+    network_id: u32,
+    finalized_root: *const u8,
+    finalized_slot: u64,
+    head_root: *const u8,
+    head_slot: u64,
+) -> bool {
+    if finalized_root.is_null() || head_root.is_null() {
+        logger::rustLogger.error(network_id, "null root passed to set_local_status");
+        return false;
+    }
+
+    let status = Status {
+        finalized_root: *(finalized_root as *const [u8; 32]),
+        finalized_slot,
+        head_root: *(head_root as *const [u8; 32]),
+        head_slot,
+    };
+    dispatch_command(
+        network_id,
+        NetworkCommand::SetLocalStatus { status },
+        "set_local_status",
+    )
+}
+
+/// Writes a JSON array describing every peer known to the peer manager into `buf`: peer id,
+/// reputation, number of connections, direction, whether it is trusted and the remaining ban
+/// time in seconds.
+///
+/// Returns the length of the JSON document, or 0 if the network is not running. If it is larger
+/// than `buf_len` nothing is written. It can grow between two calls, so callers must retry with
+/// a buffer of the returned length until the returned length fits.
+///
+/// # Safety
+///
+/// The caller must ensure that `buf` points to writable memory of `buf_len` bytes. Must not be
+/// called from a bridge callback.
+#[no_mangle]
+pub unsafe fn list_peers(network_id: u32, buf: *mut u8, buf_len: usize) -> usize {
+    let Some(peers) = query_network(network_id, "list_peers", |_, swarm| {
+        swarm.behaviour().peer_manager.peer_summaries()
+    }) else {
+        return 0;
+    };
+
+    match serde_json::to_string(&peers) {
+        Ok(json) => write_text_to_buffer(&json, buf, buf_len),
+        Err(e) => {
+            logger::rustLogger.error(network_id, &format!("Failed to encode peer list: {}", e));
+            0
+        }
+    }
+}
+
+/// Renders the metrics of the network in the OpenMetrics text format into `buf`: libp2p swarm,
+/// identify, ping and gossipsub metrics plus req/resp request counts and latencies, reconnect
+/// attempts and gossip bytes per topic.
+///
+/// Returns the length of the text, or 0 if the network is not running. If it is larger
+/// than `buf_len` nothing is written. It can grow between two calls, so callers must retry with
+/// a buffer of the returned length until the returned length fits.
+///
+/// # Safety
+///
+/// The caller must ensure that `buf` points to writable memory of `buf_len` bytes. Must not be
+/// called from a bridge callback.
+#[no_mangle]
+pub unsafe fn get_metrics(network_id: u32, buf: *mut u8, buf_len: usize) -> usize {
+    let Some(metrics) = query_network(network_id, "get_metrics", |network, _| {
+        network.metrics.encode()
+    }) else {
+        return 0;
+    };
+
+    match metrics {
+        Ok(text) => write_text_to_buffer(&text, buf, buf_len),
+        Err(e) => {
+            logger::rustLogger.error(network_id, &format!("Failed to encode metrics: {}", e));
+            0
+        }
+    }
+}
+
+/// Writes a JSON object describing the network into `buf`: the local peer id, listen addresses,
+/// the mesh peers of every subscribed topic and every connected peer with its direction,
+/// addresses, identify agent version and protocols and last ping RTT in milliseconds.
+///
+/// Returns the length of the JSON document, or 0 if the network is not running. If it is larger
+/// than `buf_len` nothing is written. It can grow between two calls, so callers must retry with
+/// a buffer of the returned length until the returned length fits.
+///
+/// # Safety
+///
+/// The caller must ensure that `buf` points to writable memory of `buf_len` bytes. Must not be
+/// called from a bridge callback.
+#[no_mangle]
+pub unsafe fn get_network_info(network_id: u32, buf: *mut u8, buf_len: usize) -> usize {
+    let Some(info) = query_network(network_id, "get_network_info", |network, swarm| {
+        network.network_info(swarm)
+    }) else {
+        return 0;
+    };
+
+    match serde_json::to_string(&info) {
+        Ok(json) => write_text_to_buffer(&json, buf, buf_len),
+        Err(e) => {
+            logger::rustLogger.error(network_id, &format!("Failed to encode network info: {}", e));
+            0
+        }
+    }
+}
+
+/// Copies `text` into a caller provided buffer if it fits and returns its length either way.
+unsafe fn write_text_to_buffer(text: &str, buf: *mut u8, buf_len: usize) -> usize {
+    if !buf.is_null() && text.len() <= buf_len {
+        std::ptr::copy_nonoverlapping(text.as_ptr(), buf, text.len());
+    }
+    text.len()
+}
+
+unsafe fn parse_peer_id(network_id: u32, peer_id: *const c_char, context: &str) -> Option<PeerId> {
+    if peer_id.is_null() {
+        logger::rustLogger.error(
+            network_id,
+            &format!("null pointer passed for `peer_id` in {}", context),
+        );
+        return None;
+    }
+
+    let peer_id_str = CStr::from_ptr(peer_id).to_string_lossy();
+    match peer_id_str.parse() {
+        Ok(peer_id) => Some(peer_id),
+        Err(e) => {
+            logger::rustLogger.error(
+                network_id,
+                &format!("Invalid peer ID {} in {}: {}", peer_id_str, context, e),
+            );
+            None
+        }
+    }
+}
+
+/// Writes the current gossipsub score of `peer_id` to `score_out`.
+///
+/// Returns false if the network is not running, peer scoring is disabled or the peer is not
+/// connected.
+///
+/// # Safety
+///
+/// The caller must ensure that `peer_id` points to a valid null-terminated C string and that
+/// `score_out` points to writable memory for an `f64`. Must not be called from a bridge callback.
+#[no_mangle]
+pub unsafe fn get_peer_score(network_id: u32, peer_id: *const c_char, score_out: *mut f64) -> bool {
+    if peer_id.is_null() || score_out.is_null() {
+        logger::rustLogger.error(network_id, "null pointer passed to get_peer_score");
+        return false;
+    }
+
+    let peer_id_str = CStr::from_ptr(peer_id).to_string_lossy();
+    let peer_id: PeerId = match peer_id_str.parse() {
+        Ok(id) => id,
+        Err(e) => {
+            logger::rustLogger.error(network_id, &format!("Invalid peer ID: {}", e));
+            return false;
+        }
+    };
+
+    match query_network(network_id, "get_peer_score", move |_, swarm| {
+        // Gossipsub scores peers it never saw as 0.0
+        if !swarm.is_connected(&peer_id) {
+            return None;
+        }
+        swarm.behaviour().gossipsub.peer_score(&peer_id)
+    }) {
+        Some(Some(score)) => {
+            *score_out = score;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// # Safety
+///
+/// The caller must ensure that `peer_id` points to a valid null-terminated C string.
+/// The caller must ensure that `request_data` points to valid memory of `request_len` bytes.
+#[no_mangle]
+pub unsafe fn send_rpc_request(
+    network_id: u32,
+    peer_id: *const c_char,
+    protocol_tag: u32,
+    request_data: *const u8,
+    request_len: usize,
+) -> u64 {
+    let request_bytes = std::slice::from_raw_parts(request_data, request_len).to_vec();
+    send_request_versions(
+        network_id,
+        &CStr::from_ptr(peer_id).to_string_lossy(),
+        vec![(protocol_tag, request_bytes)],
+    )
+}
+
+/// Sends a request offering several versions of one message during multistream-select, in
+/// preference order. Every version comes with the payload encoded for it. Zig learns the
+/// negotiated version from the protocol id passed with each response chunk.
+///
+/// # Safety
+///
+/// The caller must ensure that `peer_id` points to a valid null-terminated C string.
+/// The caller must ensure that `protocol_tags`, `request_data` and `request_lens` point to
+/// `count` elements each, and that every `request_data[i]` points to `request_lens[i]` bytes.
+#[no_mangle]
+pub unsafe fn send_rpc_request_versions(
+    network_id: u32,
+    peer_id: *const c_char,
+    protocol_tags: *const u32,
+    request_data: *const *const u8,
+    request_lens: *const usize,
+    count: usize,
+) -> u64 {
+    if count == 0 {
+        logger::rustLogger.error(network_id, "RPC request without any protocol version");
+        return 0;
+    }
+
+    let protocol_tags = std::slice::from_raw_parts(protocol_tags, count);
+    let request_data = std::slice::from_raw_parts(request_data, count);
+    let request_lens = std::slice::from_raw_parts(request_lens, count);
+    let versions = protocol_tags
+        .iter()
+        .zip(request_data.iter().zip(request_lens))
+        .map(|(tag, (data, len))| (*tag, std::slice::from_raw_parts(*data, *len).to_vec()))
+        .collect();
+
+    send_request_versions(
+        network_id,
+        &CStr::from_ptr(peer_id).to_string_lossy(),
+        versions,
+    )
+}
+
+fn send_request_versions(network_id: u32, peer_id_str: &str, versions: Vec<(u32, Vec<u8>)>) -> u64 {
+    let peer_id: PeerId = match peer_id_str.parse() {
+        Ok(id) => id,
+        Err(e) => {
+            logger::rustLogger.error(network_id, &format!("Invalid peer ID: {}", e));
+            return 0;
+        }
+    };
+
+    let mut protocols: Vec<(LeanSupportedProtocol, Vec<u8>)> = Vec::with_capacity(versions.len());
+    for (protocol_tag, payload) in versions {
+        let protocol = match LeanSupportedProtocol::try_from(protocol_tag) {
+            Ok(protocol) => protocol,
+            Err(_) => {
+                logger::rustLogger.error(
                     network_id,
-                    "send_rpc_end_of_stream called before network initialized",
+                    &format!(
+                        "Invalid protocol tag {} provided for RPC request to {}",
+                        protocol_tag, peer_id_str
+                    ),
                 );
-                return;
+                return 0;
             }
         };
+        // Only versions of the same message can stand in for each other
+        if protocols
+            .iter()
+            .any(|(offered, _)| *offered == protocol || offered.family() != protocol.family())
+        {
+            logger::rustLogger.error(
+                network_id,
+                &format!(
+                    "Protocol {} cannot be offered next to {:?} for RPC request to {}",
+                    protocol.protocol_id(),
+                    protocols
+                        .iter()
+                        .map(|(offered, _)| offered.protocol_id())
+                        .collect::<Vec<_>>(),
+                    peer_id_str
+                ),
+            );
+            return 0;
+        }
+        protocols.push((protocol, payload));
+    }
+    let (protocol, payload) = protocols.remove(0);
 
-        swarm.behaviour_mut().reqresp.finish_response_stream(
-            channel.peer_id,
-            channel.connection_id,
-            channel.stream_id,
-        );
-        logger::rustLogger.info(
-            network_id,
-            &format!(
-                "[reqresp] Sent end-of-stream on channel {} (peer: {})",
-                channel_id, channel.peer_id
-            ),
-        );
-    } else {
-        logger::rustLogger.error(
-            network_id,
-            &format!("No response channel found for id {}", channel_id),
-        );
+    let request_id = REQUEST_ID_COUNTER.fetch_add(1, Ordering::Relaxed) + 1;
+
+    if !dispatch_command(
+        network_id,
+        NetworkCommand::SendRequest(Box::new(OutboundRequest {
+            peer_id,
+            request_id,
+            protocol,
+            payload,
+            fallbacks: protocols,
+        })),
+        "send_rpc_request",
+    ) {
+        return 0;
     }
+
+    request_id
+}
+
+/// Cancels an outbound request sent with `send_rpc_request` or `send_rpc_request_versions`. A
+/// queued request is dropped, a sent one has its substream reset. Once the event loop takes the
+/// cancellation, no response, end-of-stream or error callback fires for the request anymore.
+///
+/// Returns false if the network is not running.
+#[no_mangle]
+pub fn cancel_rpc_request(network_id: u32, request_id: u64) -> bool {
+    dispatch_command(
+        network_id,
+        NetworkCommand::CancelRequest { request_id },
+        "cancel_rpc_request",
+    )
+}
+
+/// # Safety
+/// The caller must ensure that `response_data` points to valid memory of `response_len` bytes.
+#[no_mangle]
+pub unsafe fn send_rpc_response_chunk(
+    network_id: u32,
+    channel_id: u64,
+    response_data: *const u8,
+    response_len: usize,
+) {
+    let response_slice = std::slice::from_raw_parts(response_data, response_len);
+    let response_bytes = response_slice.to_vec();
+
+    dispatch_command(
+        network_id,
+        NetworkCommand::SendResponseChunk {
+            channel_id,
+            payload: response_bytes,
+        },
+        "send_rpc_response_chunk",
+    );
+}
+
+/// # Safety
+/// The caller must ensure the channel id is valid for a pending response.
+#[no_mangle]
+pub unsafe fn send_rpc_end_of_stream(network_id: u32, channel_id: u64) {
+    dispatch_command(
+        network_id,
+        NetworkCommand::EndOfStream { channel_id },
+        "send_rpc_end_of_stream",
+    );
 }
 
+/// Sends an error chunk with the given result code (1 = InvalidRequest, 2 = ServerError,
+/// 3 = ResourceUnavailable) and ends the response stream.
+///
 /// # Safety
 /// The caller must ensure `message_ptr` points to a valid null-terminated C string.
 #[no_mangle]
 pub unsafe fn send_rpc_error_response(
     network_id: u32,
     channel_id: u64,
+    code: u32,
     message_ptr: *const c_char,
 ) {
     if message_ptr.is_null() {
@@ -496,9 +1141,22 @@ pub unsafe fn send_rpc_error_response(
     }
 
     let message = CStr::from_ptr(message_ptr).to_string_lossy().to_string();
-    let message_bytes = message.as_bytes();
 
-    if message_bytes.len() > crate::req_resp::configurations::max_message_size() {
+    let code = match u8::try_from(code).ok().and_then(ResponseCode::from_u8) {
+        Some(code) if code != ResponseCode::Success => code,
+        _ => {
+            logger::rustLogger.warn(
+                network_id,
+                &format!(
+                    "Invalid RPC error code {} on channel {}, sending ServerError",
+                    code, channel_id
+                ),
+            );
+            ResponseCode::ServerError
+        }
+    };
+
+    if message.len() > crate::req_resp::configurations::max_message_size() {
         logger::rustLogger.error(
             network_id,
             &format!(
@@ -509,78 +1167,40 @@ pub unsafe fn send_rpc_error_response(
         return;
     }
 
-    let channel = {
-        let mut response_map = RESPONSE_CHANNEL_MAP.lock().unwrap();
-        response_map.remove(&channel_id)
-    };
+    dispatch_command(
+        network_id,
+        NetworkCommand::SendErrorResponse {
+            channel_id,
+            code,
+            message,
+        },
+        "send_rpc_error_response",
+    );
+}
 
-    if let Some(channel) = channel {
-        let swarm = match get_swarm_mut(network_id) {
-            Some(s) => s,
-            None => {
-                logger::rustLogger.error(
-                    network_id,
-                    "send_rpc_error_response called before network initialized",
-                );
-                return;
-            }
-        };
+extern "C" {
+    fn handleMsgFromRustBridge(
+        zig_handler: u64,
+        topic: *const c_char,
+        message_ptr: *const u8,
+        message_len: usize,
+        sender_peer_id: *const c_char,
+        message_id: *const c_char,
+    );
+}
 
-        let mut payload = Vec::with_capacity(1 + MAX_VARINT_BYTES + message_bytes.len());
-        payload.push(2);
-        encode_varint(message_bytes.len(), &mut payload);
-        payload.extend_from_slice(message_bytes);
-
-        let response_message = ResponseMessage::new(channel.protocol.clone(), payload);
-
-        let peer_id = channel.peer_id;
-
-        swarm.behaviour_mut().reqresp.send_response(
-            peer_id,
-            channel.connection_id,
-            channel.stream_id,
-            response_message,
-        );
-        swarm.behaviour_mut().reqresp.finish_response_stream(
-            peer_id,
-            channel.connection_id,
-            channel.stream_id,
-        );
-        logger::rustLogger.info(
-            network_id,
-            &format!(
-                "[reqresp] Sent error response on channel {} (peer: {}): {}",
-                channel_id, peer_id, message
-            ),
-        );
-    } else {
-        logger::rustLogger.error(
-            network_id,
-            &format!("No response channel found for id {}", channel_id),
-        );
-    }
-}
-
-extern "C" {
-    fn handleMsgFromRustBridge(
-        zig_handler: u64,
-        topic: *const c_char,
-        message_ptr: *const u8,
-        message_len: usize,
-        sender_peer_id: *const c_char,
-    );
-}
-
-extern "C" {
-    fn handleRPCRequestFromRustBridge(
-        zig_handler: u64,
-        channel_id: u64,
-        peer_id: *const c_char,
-        protocol_id: *const c_char,
-        request_ptr: *const u8,
-        request_len: usize,
-    );
+extern "C" {
+    fn handleRPCRequestFromRustBridge(
+        zig_handler: u64,
+        channel_id: u64,
+        peer_id: *const c_char,
+        protocol_id: *const c_char,
+        request_ptr: *const u8,
+        request_len: usize,
+    );
 
+    /// `context_bytes` points to the 4-byte fork digest of the chunk, or is null for protocols
+    /// without context bytes.
     fn handleRPCResponseFromRustBridge(
         zig_handler: u64,
         request_id: u64,
@@ -588,6 +1208,7 @@ extern "C" {
         protocol_id: *const c_char,
         response_ptr: *const u8,
         response_len: usize,
+        context_bytes: *const u8,
     );
 
     fn handleRPCEndOfStreamFromRustBridge(
@@ -597,6 +1218,8 @@ extern "C" {
         protocol_id: *const c_char,
     );
 
+    /// `code` is the response code of the peer's error chunk, `REQUEST_TIMEOUT_CODE` for
+    /// timeouts or `LOCAL_FAILURE_CODE` for other failures without an error chunk.
     fn handleRPCErrorFromRustBridge(
         zig_handler: u64,
         request_id: u64,
@@ -612,6 +1235,20 @@ extern "C" {
         peer_id: *const c_char,
         direction: u32, // 0=inbound, 1=outbound, 2=unknown
     );
+
+    /// Status of a peer learned from the Status handshake or a Status request of the peer.
+    /// The roots point to 32 bytes each.
+    fn handlePeerStatusFromRustBridge(
+        zig_handler: u64,
+        peer_id: *const c_char,
+        finalized_root: *const u8,
+        finalized_slot: u64,
+        head_root: *const u8,
+        head_slot: u64,
+    );
+
+    /// Reason of a Goodbye request received from a peer, which is about to disconnect.
+    fn handlePeerGoodbyeFromRustBridge(zig_handler: u64, peer_id: *const c_char, reason: u64);
 }
 
 extern "C" {
@@ -632,6 +1269,15 @@ extern "C" {
     );
 }
 
+extern "C" {
+    fn handleTopicMeshEventFromRustBridge(
+        zig_handler: u64,
+        topic: *const c_char,
+        peer_id: *const c_char,
+        event: u32, // 0=joined_mesh, 1=left_mesh
+    );
+}
+
 extern "C" {
     fn releaseStartNetworkParams(
         zig_handler: u64,
@@ -639,6 +1285,12 @@ extern "C" {
         listen_addresses: *const c_char,
         connect_addresses: *const c_char,
         topics: *const c_char,
+        peer_score_params: *const c_char,
+        transport_config: *const c_char,
+        gossipsub_config: *const c_char,
+        req_resp_config: *const c_char,
+        bootnodes: *const c_char,
+        peer_store_path: *const c_char,
     );
 }
 
@@ -658,7 +1310,7 @@ fn forward_log_with_handler(zig_handler: u64, level: u32, message: &str) {
 }
 
 pub(crate) fn forward_log_by_network(network_id: u32, level: u32, message: &str) {
-    let handler_opt = unsafe { get_zig_handler(network_id) };
+    let handler_opt = NETWORKS.zig_handler(network_id);
     if let Some(handler) = handler_opt {
         forward_log_with_handler(handler, level, message);
     }
@@ -666,10 +1318,75 @@ pub(crate) fn forward_log_by_network(network_id: u32, level: u32, message: &str)
 
 // Legacy rb_log_* helpers removed in favor of logger::rustLogger.*
 
+/// Everything `start_network` needs besides the node identity, parsed from the FFI arguments.
+pub struct NetworkConfig {
+    pub listen_addresses: Vec<Multiaddr>,
+    pub connect_addresses: Vec<Multiaddr>,
+    pub topics: Vec<String>,
+    pub peer_score: PeerScoreConfig,
+    pub transport: TransportConfig,
+    pub gossipsub: GossipsubConfig,
+    pub peer_manager: PeerManagerConfig,
+    /// None disables discovery.
+    pub discovery: Option<DiscoveryConfig>,
+    /// Quotas of inbound req/resp requests per peer and protocol.
+    pub rate_limiter: RateLimiterConfig,
+    /// Outbound requests in flight per peer and protocol, further ones are queued.
+    pub max_concurrent_requests: usize,
+    /// Fork digests used as context bytes by the req/resp protocols that carry them.
+    pub fork_context: ForkContext,
+    /// File the peers this node dialed are persisted to and seeded from at the next start, None
+    /// keeps them in memory only.
+    pub peer_store_path: Option<PathBuf>,
+    /// Exchange plain SSZ req/resp payloads with Zig. The glue then decompresses and checks the
+    /// snappy frames of inbound requests and responses and compresses the outbound ones, instead
+    /// of passing the `varint + snappy frame` payloads through.
+    pub decompress_rpc_payloads: bool,
+}
+
 pub struct Network {
     network_id: u32,
     zig_handler: u64,
     peer_addr_map: HashMap<PeerId, Multiaddr>,
+    swarm: Option<libp2p::swarm::Swarm<Behaviour>>,
+    listeners: Vec<ListenerId>,
+    shutdown_deadline: Option<tokio::time::Instant>,
+    commands: Option<UnboundedReceiver<NetworkCommand>>,
+    request_timeouts: HashMapDelay<u64, ()>,
+    request_protocols: HashMap<u64, ProtocolId>,
+    outbound_queue: OutboundQueue,
+    response_channels: HashMapDelay<u64, PendingResponse>,
+    reconnect_queue: HashMapDelay<PeerId, (Multiaddr, u32)>,
+    reconnect_attempts: HashMap<PeerId, (Multiaddr, u32)>,
+    // Track connection directions for disconnect events (peer_id, connection_id) -> direction
+    connection_directions: HashMap<(PeerId, ConnectionId), u32>,
+    // Gossip messages awaiting Zig's validation result, keyed by message id -> propagation source.
+    // Entries expire after the gossipsub duplicate cache time.
+    pending_validations: HashMapDelay<gossipsub::MessageId, PeerId>,
+    // How often the meshes are checked for peers joining or leaving, the gossipsub heartbeat
+    // which is when mesh maintenance happens
+    mesh_poll_interval: Duration,
+    // Received gossip messages larger than this are announced to the mesh with IDONTWANT
+    idontwant_threshold: usize,
+    // Scoring config applied to topics subscribed at runtime, None if scoring is disabled
+    peer_score: Option<PeerScoreConfig>,
+    // Last seen mesh of every subscribed topic, used to report mesh joins/leaves to Zig
+    mesh_peers: HashMap<gossipsub::TopicHash, HashSet<PeerId>>,
+    // Addresses, identify info and ping RTT of connected peers, reported by `get_network_info`
+    peer_metadata: PeerMetadataStore,
+    // Successfully dialed peers, kept across restarts
+    peer_store: PeerStore,
+    // Prometheus registry exported through `get_metrics`
+    metrics: NetworkMetrics,
+    // Whether req/resp payloads are (de)compressed here instead of in Zig
+    decompress_rpc_payloads: bool,
+    // Status sent in handshakes, None until Zig sets it
+    local_status: Option<Status>,
+    status_handshakes: StatusHandshakes,
+    // Goodbye requests in flight -> peer to disconnect once they end
+    pending_goodbyes: HashMap<u64, PeerId>,
+    // Peers that said or got a goodbye, not redialed when their connections close
+    goodbye_peers: HashSet<PeerId>,
 }
 
 impl Network {
@@ -678,6 +1395,32 @@ impl Network {
             network_id,
             zig_handler,
             peer_addr_map: HashMap::new(),
+            swarm: None,
+            listeners: Vec::new(),
+            shutdown_deadline: None,
+            commands: None,
+            request_timeouts: HashMapDelay::new(REQUEST_TIMEOUT),
+            request_protocols: HashMap::new(),
+            outbound_queue: OutboundQueue::new(MAX_CONCURRENT_REQUESTS),
+            response_channels: HashMapDelay::new(RESPONSE_CHANNEL_IDLE_TIMEOUT),
+            reconnect_queue: HashMapDelay::new(Duration::from_secs(5)), // default delay, will be overridden
+            reconnect_attempts: HashMap::new(),
+            connection_directions: HashMap::new(),
+            pending_validations: HashMapDelay::new(
+                GossipsubConfig::default().duplicate_cache_time(),
+            ),
+            mesh_poll_interval: GossipsubConfig::default().heartbeat_interval(),
+            idontwant_threshold: GossipsubConfig::default().idontwant_message_size_threshold,
+            peer_score: None,
+            peer_metadata: PeerMetadataStore::default(),
+            peer_store: PeerStore::in_memory(),
+            metrics: NetworkMetrics::new(),
+            mesh_peers: HashMap::new(),
+            decompress_rpc_payloads: false,
+            local_status: None,
+            status_handshakes: StatusHandshakes::default(),
+            pending_goodbyes: HashMap::new(),
+            goodbye_peers: HashSet::new(),
         }
     }
 
@@ -692,161 +1435,1351 @@ impl Network {
         if attempt > MAX_RECONNECT_ATTEMPTS {
             logger::rustLogger.warn(
                 self.network_id,
-                &format!(
-                    "Max reconnection attempts ({}) reached for peer {}, giving up",
-                    MAX_RECONNECT_ATTEMPTS, addr
-                ),
+                &format!(
+                    "Max reconnection attempts ({}) reached for peer {}, giving up",
+                    MAX_RECONNECT_ATTEMPTS, addr
+                ),
+            );
+            self.peer_addr_map.remove(&peer_id);
+            self.reconnect_attempts.remove(&peer_id);
+            return;
+        }
+
+        let delay_secs = RECONNECT_DELAYS_SECS
+            .get((attempt - 1) as usize)
+            .copied()
+            .unwrap_or(80);
+
+        logger::rustLogger.info(
+            self.network_id,
+            &format!(
+                "Scheduling reconnection to peer {} (attempt {}/{}) in {}s",
+                addr, attempt, MAX_RECONNECT_ATTEMPTS, delay_secs
+            ),
+        );
+
+        self.reconnect_queue
+            .insert_at(peer_id, (addr, attempt), Duration::from_secs(delay_secs));
+    }
+
+    /// Builds the swarm, starts the listeners and dials the static peers. On success the
+    /// network is marked ready in the registry and `true` is returned.
+    pub async fn start_network(&mut self, key_pair: Keypair, config: NetworkConfig) -> bool {
+        let NetworkConfig {
+            listen_addresses,
+            connect_addresses,
+            topics,
+            peer_score,
+            transport,
+            gossipsub,
+            peer_manager: peer_manager_config,
+            discovery,
+            rate_limiter,
+            max_concurrent_requests,
+            fork_context,
+            peer_store_path,
+            decompress_rpc_payloads,
+        } = config;
+        self.outbound_queue = OutboundQueue::new(max_concurrent_requests);
+        self.decompress_rpc_payloads = decompress_rpc_payloads;
+        self.pending_validations = HashMapDelay::new(gossipsub.duplicate_cache_time());
+        self.mesh_poll_interval = gossipsub.heartbeat_interval();
+        self.idontwant_threshold = gossipsub.idontwant_message_size_threshold;
+        self.peer_store = PeerStore::new(peer_store_path, PEER_STORE_MAX_AGE);
+        match self.peer_store.load(unix_now()) {
+            Ok(loaded) if loaded > 0 => logger::rustLogger.info(
+                self.network_id,
+                &format!("Loaded {} peers from the peer store", loaded),
+            ),
+            Ok(_) => {}
+            Err(e) => logger::rustLogger.warn(
+                self.network_id,
+                &format!("Starting with an empty peer store: {}", e),
+            ),
+        }
+
+        let discovery = match discovery {
+            Some(discovery_config) => {
+                match self
+                    .start_discovery(&key_pair, &listen_addresses, discovery_config)
+                    .await
+                {
+                    Ok(discovery) => Some(discovery),
+                    Err(e) => {
+                        logger::rustLogger.error(
+                            self.network_id,
+                            &format!("Failed to start discovery: {}", e),
+                        );
+                        return false;
+                    }
+                }
+            }
+            None => None,
+        };
+
+        let score_params = if peer_score.enabled {
+            match peer_score.to_params(&topics) {
+                Ok(score_params) => Some(score_params),
+                Err(e) => {
+                    logger::rustLogger.error(
+                        self.network_id,
+                        &format!("Invalid peer score parameters: {}", e),
+                    );
+                    return false;
+                }
+            }
+        } else {
+            None
+        };
+
+        let mut swarm = match new_swarm(
+            key_pair,
+            &transport,
+            &gossipsub,
+            topics,
+            peer_manager_config,
+            discovery,
+            rate_limiter,
+            fork_context,
+            self.metrics.registry_mut(),
+            self.network_id,
+        ) {
+            Ok(swarm) => swarm,
+            Err(e) => {
+                logger::rustLogger.error(
+                    self.network_id,
+                    &format!("Failed to build the swarm: {}", e),
+                );
+                return false;
+            }
+        };
+        if let Some((params, thresholds)) = score_params {
+            if let Err(e) = swarm
+                .behaviour_mut()
+                .gossipsub
+                .with_peer_score(params, thresholds)
+            {
+                logger::rustLogger.error(
+                    self.network_id,
+                    &format!("Failed to enable peer scoring: {}", e),
+                );
+                return false;
+            }
+            self.peer_score = Some(peer_score);
+        }
+        logger::rustLogger.info(self.network_id, "starting listener");
+
+        let mut listen_success = false;
+        for mut addr in listen_addresses {
+            strip_peer_id(&mut addr);
+            match swarm.listen_on(addr.clone()) {
+                Ok(listener_id) => {
+                    self.listeners.push(listener_id);
+                    logger::rustLogger.info(
+                        self.network_id,
+                        &format!("Successfully started listener on {}", addr),
+                    );
+                    listen_success = true;
+                }
+                Err(e) => {
+                    logger::rustLogger.error(
+                        self.network_id,
+                        &format!("Failed to listen on {}: {:?}", addr, e),
+                    );
+                }
+            }
+        }
+
+        if !listen_success {
+            logger::rustLogger.error(
+                self.network_id,
+                "Failed to start listener on any address - network initialization failed",
+            );
+            // Signal failure by NOT marking the network ready
+            return false;
+        }
+
+        logger::rustLogger.debug(self.network_id, "going for loop match");
+
+        if !connect_addresses.is_empty() {
+            // static peers are exempt from the peer limits
+            for peer_id in connect_addresses.iter().filter_map(Self::extract_peer_id) {
+                swarm.behaviour_mut().peer_manager.add_trusted_peer(peer_id);
+            }
+
+            // helper closure for dialing peers
+            let mut dial = |mut multiaddr: Multiaddr| {
+                // strip the p2p protocol if it exists
+                strip_peer_id(&mut multiaddr);
+                match swarm.dial(multiaddr.clone()) {
+                    Ok(()) => logger::rustLogger.debug(
+                        self.network_id,
+                        &format!("dialing libp2p peer address: {}", multiaddr),
+                    ),
+                    Err(err) => {
+                        logger::rustLogger.error(
+                            self.network_id,
+                            &format!(
+                                "could not connect to peer address: {} error: {:?}",
+                                multiaddr, err
+                            ),
+                        );
+                    }
+                };
+            };
+
+            for addr in connect_addresses {
+                if let Some(peer_id) = Self::extract_peer_id(&addr) {
+                    self.peer_addr_map
+                        .entry(peer_id)
+                        .or_insert_with(|| addr.clone());
+                } else {
+                    logger::rustLogger.warn(
+                        self.network_id,
+                        &format!("Connect address missing peer id: {}", addr),
+                    );
+                }
+                dial(addr);
+            }
+        } else {
+            logger::rustLogger.debug(self.network_id, "no connect addresses");
+        }
+
+        // Peers known from earlier runs fill up the slots the static peers leave
+        let stored_peers = self
+            .peer_store
+            .dial_candidates()
+            .into_iter()
+            .filter(|(peer_id, _)| !self.peer_addr_map.contains_key(peer_id))
+            .collect::<Vec<_>>();
+        self.dial_peers(&mut swarm, stored_peers);
+
+        let (command_tx, command_rx) = unbounded_channel();
+        self.swarm = Some(swarm);
+        self.commands = Some(command_rx);
+
+        // Signal that this network is now ready
+        NETWORKS.mark_ready(self.network_id, command_tx);
+
+        logger::rustLogger.info(self.network_id, "network initialization complete and ready");
+        true
+    }
+
+    async fn start_discovery(
+        &self,
+        key_pair: &Keypair,
+        listen_addresses: &[Multiaddr],
+        config: DiscoveryConfig,
+    ) -> Result<Discovery, String> {
+        let enr_key = enr_key_from_keypair(key_pair)?;
+        let local_enr = build_local_enr(
+            &enr_key,
+            &LocalEnrConfig::from_listen_addresses(listen_addresses, Some(config.udp_port)),
+        )?;
+        logger::rustLogger.info(
+            self.network_id,
+            &format!(
+                "starting discovery on udp port {} with {} bootnodes, local ENR: {}",
+                config.udp_port,
+                config.bootnodes.len(),
+                local_enr.to_base64()
+            ),
+        );
+
+        Discovery::new(enr_key, local_enr, config.udp_port, config.bootnodes).await
+    }
+
+    /// Dials peers found by discovery or kept in the peer store until the peer manager's target
+    /// is reached.
+    fn dial_peers(
+        &self,
+        swarm: &mut libp2p::swarm::Swarm<Behaviour>,
+        peers: Vec<(PeerId, Vec<Multiaddr>)>,
+    ) {
+        let mut wanted = swarm.behaviour().peer_manager.peers_wanted();
+        for (peer_id, addrs) in peers {
+            if wanted == 0 {
+                break;
+            }
+            if swarm.is_connected(&peer_id) || swarm.behaviour().peer_manager.is_banned(&peer_id) {
+                continue;
+            }
+
+            let dial_opts = DialOpts::peer_id(peer_id)
+                .condition(PeerCondition::DisconnectedAndNotDialing)
+                .addresses(addrs)
+                .build();
+            match swarm.dial(dial_opts) {
+                Ok(()) => {
+                    wanted -= 1;
+                    logger::rustLogger.debug(self.network_id, &format!("Dialing peer {}", peer_id));
+                }
+                Err(e) => logger::rustLogger.debug(
+                    self.network_id,
+                    &format!("Not dialing peer {}: {}", peer_id, e),
+                ),
+            }
+        }
+    }
+
+    fn handle_command(
+        &mut self,
+        swarm: &mut libp2p::swarm::Swarm<Behaviour>,
+        command: NetworkCommand,
+    ) {
+        match command {
+            NetworkCommand::Publish { topic, data } => {
+                let topic_hash = topic.hash();
+                let len = data.len();
+                match swarm.behaviour_mut().gossipsub.publish(topic, data) {
+                    Ok(_) => self.metrics.gossip_bytes(
+                        topic_hash.as_str(),
+                        GossipDirection::Published,
+                        len,
+                    ),
+                    Err(e) => {
+                        logger::rustLogger.error(self.network_id, &format!("Publish error: {e:?}"))
+                    }
+                }
+            }
+            NetworkCommand::SendRequest(request) => {
+                let OutboundRequest {
+                    peer_id,
+                    request_id,
+                    protocol,
+                    payload,
+                    fallbacks,
+                } = *request;
+                let protocol_id: ProtocolId = protocol.into();
+                let decompress_rpc_payloads = self.decompress_rpc_payloads;
+                let encode = |payload: Vec<u8>| {
+                    if decompress_rpc_payloads {
+                        encode_snappy_payload(&payload)
+                    } else {
+                        payload
+                    }
+                };
+                let request_message = fallbacks.into_iter().fold(
+                    RequestMessage::new(protocol_id.clone(), encode(payload)),
+                    |request, (protocol, payload)| {
+                        request.with_fallback(RequestMessage::new(protocol.into(), encode(payload)))
+                    },
+                );
+
+                // A queued request times out like a sent one if no slot frees up in time
+                self.request_timeouts.insert(request_id, ());
+                self.request_protocols.insert(request_id, protocol_id);
+
+                let Some(request_message) =
+                    self.outbound_queue
+                        .push(peer_id, request_id, request_message)
+                else {
+                    logger::rustLogger.debug(
+                        self.network_id,
+                        &format!(
+                            "[reqresp] Queued {:?} request to {} (id: {})",
+                            protocol, peer_id, request_id
+                        ),
+                    );
+                    return;
+                };
+                self.dispatch_request(swarm, peer_id, request_id, request_message);
+            }
+            NetworkCommand::SendResponseChunk {
+                channel_id,
+                payload,
+            } => {
+                let Some(channel) = self.response_channels.get(&channel_id).cloned() else {
+                    logger::rustLogger.error(
+                        self.network_id,
+                        &format!("No response channel found for id {}", channel_id),
+                    );
+                    return;
+                };
+                _ = self
+                    .response_channels
+                    .update_timeout(&channel_id, RESPONSE_CHANNEL_IDLE_TIMEOUT);
+
+                let payload = if self.decompress_rpc_payloads {
+                    let mut chunk = vec![ResponseCode::Success as u8];
+                    chunk.extend(encode_snappy_payload(&payload));
+                    chunk
+                } else {
+                    payload
+                };
+                let response_message = ResponseMessage::new(channel.protocol.clone(), payload);
+
+                swarm.behaviour_mut().reqresp.send_response(
+                    channel.peer_id,
+                    channel.connection_id,
+                    channel.stream_id,
+                    response_message,
+                );
+                logger::rustLogger.info(
+                    self.network_id,
+                    &format!(
+                        "[reqresp] Sent response payload on channel {} (peer: {})",
+                        channel_id, channel.peer_id
+                    ),
+                );
+            }
+            NetworkCommand::EndOfStream { channel_id } => {
+                let Some(channel) = self.response_channels.remove(&channel_id) else {
+                    logger::rustLogger.error(
+                        self.network_id,
+                        &format!("No response channel found for id {}", channel_id),
+                    );
+                    return;
+                };
+
+                swarm.behaviour_mut().reqresp.finish_response_stream(
+                    channel.peer_id,
+                    channel.connection_id,
+                    channel.stream_id,
+                );
+                self.metrics
+                    .inbound_request_finished(channel.protocol.as_str(), RequestOutcome::Success);
+                logger::rustLogger.info(
+                    self.network_id,
+                    &format!(
+                        "[reqresp] Sent end-of-stream on channel {} (peer: {})",
+                        channel_id, channel.peer_id
+                    ),
+                );
+            }
+            NetworkCommand::SendErrorResponse {
+                channel_id,
+                code,
+                message,
+            } => {
+                let Some(channel) = self.response_channels.remove(&channel_id) else {
+                    logger::rustLogger.error(
+                        self.network_id,
+                        &format!("No response channel found for id {}", channel_id),
+                    );
+                    return;
+                };
+
+                self.metrics
+                    .inbound_request_finished(channel.protocol.as_str(), RequestOutcome::Error);
+
+                let payload = encode_error_response(code, &message);
+                let response_message = ResponseMessage::new(channel.protocol.clone(), payload);
+
+                let peer_id = channel.peer_id;
+
+                swarm.behaviour_mut().reqresp.send_response(
+                    peer_id,
+                    channel.connection_id,
+                    channel.stream_id,
+                    response_message,
+                );
+                swarm.behaviour_mut().reqresp.finish_response_stream(
+                    peer_id,
+                    channel.connection_id,
+                    channel.stream_id,
+                );
+                logger::rustLogger.info(
+                    self.network_id,
+                    &format!(
+                        "[reqresp] Sent error response on channel {} (peer: {}): {}",
+                        channel_id, peer_id, message
+                    ),
+                );
+            }
+            NetworkCommand::ReportGossipValidation {
+                message_id,
+                acceptance,
+            } => {
+                let Some(propagation_source) = self.pending_validations.remove(&message_id) else {
+                    logger::rustLogger.warn(
+                        self.network_id,
+                        &format!(
+                            "No pending validation for gossip message {}",
+                            hex::encode(&message_id.0)
+                        ),
+                    );
+                    return;
+                };
+
+                if matches!(acceptance, gossipsub::MessageAcceptance::Reject) {
+                    swarm
+                        .behaviour_mut()
+                        .peer_manager
+                        .report_peer(propagation_source, PeerAction::LowTolerance);
+                }
+
+                // MessageAcceptance is neither Copy nor Clone, name it before handing it over
+                let outcome = match acceptance {
+                    gossipsub::MessageAcceptance::Accept => "accept",
+                    gossipsub::MessageAcceptance::Reject => "reject",
+                    gossipsub::MessageAcceptance::Ignore => "ignore",
+                };
+                let in_cache = swarm
+                    .behaviour_mut()
+                    .gossipsub
+                    .report_message_validation_result(&message_id, &propagation_source, acceptance);
+                logger::rustLogger.debug(
+                    self.network_id,
+                    &format!(
+                        "Reported {} for gossip message {} from {} (in cache: {:?})",
+                        outcome,
+                        hex::encode(&message_id.0),
+                        propagation_source,
+                        in_cache
+                    ),
+                );
+            }
+            NetworkCommand::Subscribe { topic } => {
+                if let Some(params) = self
+                    .peer_score
+                    .as_ref()
+                    .and_then(|peer_score| peer_score.topic_params(&topic.to_string()))
+                {
+                    if let Err(e) = swarm
+                        .behaviour_mut()
+                        .gossipsub
+                        .set_topic_params(topic.clone(), params)
+                    {
+                        logger::rustLogger.error(
+                            self.network_id,
+                            &format!("Failed to set score parameters for topic {}: {}", topic, e),
+                        );
+                    }
+                }
+
+                match swarm.behaviour_mut().gossipsub.subscribe(&topic) {
+                    Ok(true) => logger::rustLogger
+                        .info(self.network_id, &format!("Subscribed to topic {}", topic)),
+                    Ok(false) => logger::rustLogger.debug(
+                        self.network_id,
+                        &format!("Already subscribed to topic {}", topic),
+                    ),
+                    Err(e) => logger::rustLogger.error(
+                        self.network_id,
+                        &format!("Failed to subscribe to topic {}: {:?}", topic, e),
+                    ),
+                }
+            }
+            NetworkCommand::Unsubscribe { topic } => {
+                if swarm.behaviour_mut().gossipsub.unsubscribe(&topic) {
+                    logger::rustLogger.info(
+                        self.network_id,
+                        &format!("Unsubscribed from topic {}", topic),
+                    );
+                } else {
+                    logger::rustLogger.debug(
+                        self.network_id,
+                        &format!("Not subscribed to topic {}", topic),
+                    );
+                }
+            }
+            NetworkCommand::BanPeer { peer_id, duration } => {
+                swarm
+                    .behaviour_mut()
+                    .peer_manager
+                    .ban_peer(peer_id, duration);
+            }
+            NetworkCommand::UnbanPeer { peer_id } => {
+                if !swarm.behaviour_mut().peer_manager.unban_peer(&peer_id) {
+                    logger::rustLogger.warn(
+                        self.network_id,
+                        &format!("unban_peer: peer {} is not banned", peer_id),
+                    );
+                }
+            }
+            NetworkCommand::DisconnectPeer { peer_id, reason } => {
+                self.say_goodbye(swarm, peer_id, reason)
+            }
+            NetworkCommand::CancelRequest { request_id } => self.cancel_request(swarm, request_id),
+            NetworkCommand::SetLocalStatus { status } => self.set_local_status(swarm, status),
+            NetworkCommand::Query(query) => query(self, swarm),
+            NetworkCommand::Shutdown => self.begin_shutdown(swarm),
+        }
+    }
+
+    fn handle_peer_manager_event(
+        &mut self,
+        swarm: &mut libp2p::swarm::Swarm<Behaviour>,
+        event: PeerManagerEvent,
+    ) {
+        match event {
+            PeerManagerEvent::Banned { peer_id, duration } => {
+                logger::rustLogger.warn(
+                    self.network_id,
+                    &format!("Banned peer {} for {:?}", peer_id, duration),
+                );
+                self.reconnect_queue.remove(&peer_id);
+                self.reconnect_attempts.remove(&peer_id);
+                self.peer_store.remove(&peer_id);
+                self.say_goodbye(swarm, peer_id, GoodbyeReason::Banned as u64);
+            }
+            PeerManagerEvent::Unbanned { peer_id } => {
+                logger::rustLogger.info(self.network_id, &format!("Unbanned peer {}", peer_id));
+            }
+            PeerManagerEvent::Pruned { peer_id } => {
+                logger::rustLogger.info(
+                    self.network_id,
+                    &format!(
+                        "Disconnecting peer {} to stay within the target peer count",
+                        peer_id
+                    ),
+                );
+                self.say_goodbye(swarm, peer_id, GoodbyeReason::TooManyPeers as u64);
+            }
+        }
+    }
+
+    /// Records a swarm event and the behaviour event it carries with the libp2p metrics.
+    fn record_metrics(&self, event: &SwarmEvent<BehaviourEvent>) {
+        self.metrics.record(event);
+        match event {
+            SwarmEvent::Behaviour(BehaviourEvent::Gossipsub(event)) => self.metrics.record(event),
+            SwarmEvent::Behaviour(BehaviourEvent::Identify(event)) => self.metrics.record(event),
+            SwarmEvent::Behaviour(BehaviourEvent::Ping(event)) => self.metrics.record(event),
+            _ => {}
+        }
+    }
+
+    fn network_info(&self, swarm: &libp2p::swarm::Swarm<Behaviour>) -> NetworkInfo {
+        let behaviour = swarm.behaviour();
+        let mesh_peers = behaviour
+            .gossipsub
+            .topics()
+            .map(|topic| {
+                let mut peers = behaviour
+                    .gossipsub
+                    .mesh_peers(topic)
+                    .map(|peer_id| peer_id.to_string())
+                    .collect::<Vec<_>>();
+                peers.sort();
+                (topic.to_string(), peers)
+            })
+            .collect();
+
+        NetworkInfo {
+            local_peer_id: swarm.local_peer_id().to_string(),
+            listen_addresses: swarm.listeners().map(|addr| addr.to_string()).collect(),
+            peers: self
+                .peer_metadata
+                .connected_peers(|peer_id| behaviour.peer_manager.direction(peer_id)),
+            mesh_peers,
+        }
+    }
+
+    /// Compares the current gossipsub meshes with the last snapshot and reports every peer that
+    /// joined or left a topic mesh to Zig.
+    fn report_mesh_changes(&mut self, swarm: &libp2p::swarm::Swarm<Behaviour>) {
+        let gossipsub = &swarm.behaviour().gossipsub;
+        let current = gossipsub
+            .topics()
+            .map(|topic| {
+                let peers = gossipsub.mesh_peers(topic).copied().collect::<HashSet<_>>();
+                (topic.clone(), peers)
+            })
+            .collect::<HashMap<_, _>>();
+        let previous = std::mem::replace(&mut self.mesh_peers, current);
+
+        let empty = HashSet::new();
+        for (topic, peers) in &self.mesh_peers {
+            let before = previous.get(topic).unwrap_or(&empty);
+            for peer_id in peers.difference(before) {
+                self.notify_mesh_event(topic, peer_id, 0);
+            }
+        }
+        for (topic, peers) in &previous {
+            let now = self.mesh_peers.get(topic).unwrap_or(&empty);
+            for peer_id in peers.difference(now) {
+                self.notify_mesh_event(topic, peer_id, 1);
+            }
+        }
+    }
+
+    /// Writes the peer store to its file, the connected peers count as seen now.
+    fn save_peer_store(&mut self, swarm: &libp2p::swarm::Swarm<Behaviour>) {
+        let now = unix_now();
+        for peer_id in swarm.connected_peers() {
+            self.peer_store.seen(peer_id, now);
+        }
+        if let Err(e) = self.peer_store.save(now) {
+            logger::rustLogger.warn(
+                self.network_id,
+                &format!("Failed to save the peer store: {}", e),
+            );
+        }
+    }
+
+    /// Records the IDONTWANT gossipsub sent for a large received message: every gossipsub v1.2
+    /// peer in the topic mesh but the one that propagated it is told not to send it again.
+    fn record_idontwant(
+        &self,
+        swarm: &libp2p::swarm::Swarm<Behaviour>,
+        topic: &gossipsub::TopicHash,
+        propagation_source: &PeerId,
+        message_len: usize,
+    ) {
+        let gossipsub = &swarm.behaviour().gossipsub;
+        // PeerKind is not exported by gossipsub, its name identifies the negotiated version
+        let v1_2_peers = gossipsub
+            .peer_protocol()
+            .filter(|(_, kind)| kind.as_static_ref() == GOSSIPSUB_V1_2_PEER_KIND)
+            .map(|(peer_id, _)| peer_id)
+            .collect::<HashSet<_>>();
+        let peers = gossipsub
+            .mesh_peers(topic)
+            .filter(|peer_id| *peer_id != propagation_source && v1_2_peers.contains(peer_id))
+            .count();
+        if peers > 0 {
+            self.metrics
+                .idontwant_sent(topic.as_str(), peers, message_len);
+        }
+    }
+
+    fn notify_mesh_event(&self, topic: &gossipsub::TopicHash, peer_id: &PeerId, event: u32) {
+        logger::rustLogger.debug(
+            self.network_id,
+            &format!(
+                "Peer {} {} mesh of topic {}",
+                peer_id,
+                if event == 0 { "joined" } else { "left" },
+                topic
+            ),
+        );
+
+        let (Ok(topic_cstring), Ok(peer_id_cstring)) = (
+            CString::new(topic.as_str()),
+            CString::new(peer_id.to_string()),
+        ) else {
+            logger::rustLogger.error(self.network_id, &format!("invalid_topic_string={}", topic));
+            return;
+        };
+        unsafe {
+            handleTopicMeshEventFromRustBridge(
+                self.zig_handler,
+                topic_cstring.as_ptr(),
+                peer_id_cstring.as_ptr(),
+                event,
+            )
+        };
+    }
+
+    fn dispatch_request(
+        &mut self,
+        swarm: &mut libp2p::swarm::Swarm<Behaviour>,
+        peer_id: PeerId,
+        request_id: u64,
+        request_message: RequestMessage,
+    ) {
+        logger::rustLogger.info(
+            self.network_id,
+            &format!(
+                "[reqresp] Sent {} request to {} (id: {})",
+                request_message.protocol.as_str(),
+                peer_id,
+                request_id
+            ),
+        );
+        swarm
+            .behaviour_mut()
+            .reqresp
+            .send_request(peer_id, request_id, request_message);
+        self.metrics.outbound_request_sent(request_id);
+    }
+
+    /// Frees the outbound slot of a finished request and sends the next queued request of the
+    /// same peer and protocol, which gets the full request timeout from now on.
+    fn finish_request(&mut self, swarm: &mut libp2p::swarm::Swarm<Behaviour>, request_id: u64) {
+        if let Some((peer_id, next_id, request_message)) = self.outbound_queue.finish(request_id) {
+            if !self
+                .request_timeouts
+                .update_timeout(&next_id, REQUEST_TIMEOUT)
+            {
+                self.request_timeouts.insert(next_id, ());
+            }
+            self.dispatch_request(swarm, peer_id, next_id, request_message);
+        }
+    }
+
+    /// Frees the slot of an outbound request that will not complete. A sent request also has its
+    /// substream reset, so the peer stops responding to it.
+    fn abort_request(&mut self, swarm: &mut libp2p::swarm::Swarm<Behaviour>, request_id: u64) {
+        let sent = !self.outbound_queue.is_queued(request_id);
+        let peer_id = self.outbound_queue.peer_of(request_id);
+        self.finish_request(swarm, request_id);
+        if let (true, Some(peer_id)) = (sent, peer_id) {
+            for (peer, connection_id) in self.connection_directions.keys() {
+                if *peer == peer_id {
+                    swarm.behaviour_mut().reqresp.cancel_request(
+                        peer_id,
+                        *connection_id,
+                        request_id,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Cancels an outbound request of Zig. Events of the request that are already on their way
+    /// are dropped since its protocol mapping is gone.
+    fn cancel_request(&mut self, swarm: &mut libp2p::swarm::Swarm<Behaviour>, request_id: u64) {
+        // Requests made by the glue are not Zig's to cancel
+        if self.status_handshakes.contains(request_id)
+            || self.pending_goodbyes.contains_key(&request_id)
+        {
+            return;
+        }
+        let Some(protocol_id) = self.request_protocols.remove(&request_id) else {
+            logger::rustLogger.debug(
+                self.network_id,
+                &format!("[reqresp] Request {} to cancel already ended", request_id),
+            );
+            return;
+        };
+
+        self.request_timeouts.remove(&request_id);
+        self.abort_request(swarm, request_id);
+        self.metrics.outbound_request_finished(
+            request_id,
+            protocol_id.as_str(),
+            RequestOutcome::Cancelled,
+        );
+        logger::rustLogger.info(
+            self.network_id,
+            &format!(
+                "[reqresp] Cancelled {} request {}",
+                protocol_id.as_str(),
+                request_id
+            ),
+        );
+    }
+
+    /// Ends a failed outbound request: the peer is reported if the failure is its fault and Zig
+    /// gets the error with the response code of the peer's error chunk, if any.
+    fn fail_request(
+        &mut self,
+        swarm: &mut libp2p::swarm::Swarm<Behaviour>,
+        peer_id: PeerId,
+        request_id: u64,
+        err: ReqRespError,
+    ) {
+        self.request_timeouts.remove(&request_id);
+        self.finish_request(swarm, request_id);
+        if let Some(action) = reqresp_error_action(&err) {
+            swarm
+                .behaviour_mut()
+                .peer_manager
+                .report_peer(peer_id, action);
+        }
+        if self.end_goodbye(swarm, request_id, RequestOutcome::Error) {
+            return;
+        }
+        let protocol = self.request_protocols.remove(&request_id);
+
+        if let Some((peer_id, _)) = self.status_handshakes.finish(request_id) {
+            if let Some(protocol_id) = protocol {
+                self.metrics.outbound_request_finished(
+                    request_id,
+                    protocol_id.as_str(),
+                    RequestOutcome::Error,
+                );
+            }
+            self.end_failed_handshake(swarm, peer_id, &format!("{:?}", err));
+            return;
+        }
+
+        if let Some(protocol_id) = protocol {
+            self.metrics.outbound_request_finished(
+                request_id,
+                protocol_id.as_str(),
+                RequestOutcome::Error,
+            );
+            // Error chunks of the peer keep their code and text, local failures get codes
+            // outside the spec's range
+            let (code, message) = match &err {
+                ReqRespError::ErrorResponse { code, message } => {
+                    (*code as u32, message.replace('\0', ""))
+                }
+                ReqRespError::InvalidRequest(_) => {
+                    (ResponseCode::InvalidRequest as u32, format!("{:?}", err))
+                }
+                ReqRespError::StreamTimedOut => (REQUEST_TIMEOUT_CODE, format!("{:?}", err)),
+                _ => (LOCAL_FAILURE_CODE, format!("{:?}", err)),
+            };
+            if let (Ok(protocol_cstring), Ok(message_cstring)) =
+                (CString::new(protocol_id.as_str()), CString::new(message))
+            {
+                unsafe {
+                    handleRPCErrorFromRustBridge(
+                        self.zig_handler,
+                        request_id,
+                        protocol_cstring.as_ptr(),
+                        code,
+                        message_cstring.as_ptr(),
+                    );
+                }
+            }
+        }
+        logger::rustLogger.error(
+            self.network_id,
+            &format!(
+                "[reqresp] Outbound error for request {} with {}: {:?}",
+                request_id, peer_id, err
+            ),
+        );
+    }
+
+    /// Updates the status exchanged in the Status handshake. Peers that connected before the
+    /// first status was set get their handshake now.
+    fn set_local_status(&mut self, swarm: &mut libp2p::swarm::Swarm<Behaviour>, status: Status) {
+        let first_status = self.local_status.replace(status).is_none();
+        if !first_status {
+            return;
+        }
+        let connected_peers = swarm.connected_peers().copied().collect::<Vec<_>>();
+        for peer_id in connected_peers {
+            self.start_status_handshake(swarm, peer_id);
+        }
+    }
+
+    /// Sends the local status to a newly connected peer. Its answer is reported to Zig, a peer
+    /// that fails to answer is disconnected.
+    fn start_status_handshake(
+        &mut self,
+        swarm: &mut libp2p::swarm::Swarm<Behaviour>,
+        peer_id: PeerId,
+    ) {
+        let Some(status) = self.local_status else {
+            return;
+        };
+        if self.status_handshakes.is_pending_for(&peer_id) {
+            return;
+        }
+
+        let request_id = self.send_glue_request(
+            swarm,
+            peer_id,
+            LeanSupportedProtocol::StatusV1,
+            status.to_ssz_bytes(),
+        );
+        self.status_handshakes.start(request_id, peer_id);
+    }
+
+    /// Sends a request made by the glue itself rather than Zig and returns its request id. Its
+    /// events must be intercepted before they reach the Zig callbacks.
+    fn send_glue_request(
+        &mut self,
+        swarm: &mut libp2p::swarm::Swarm<Behaviour>,
+        peer_id: PeerId,
+        protocol: LeanSupportedProtocol,
+        ssz_bytes: Vec<u8>,
+    ) -> u64 {
+        // Payloads given to the command are in the format Zig would use
+        let payload = if self.decompress_rpc_payloads {
+            ssz_bytes
+        } else {
+            encode_snappy_payload(&ssz_bytes)
+        };
+        let request_id = REQUEST_ID_COUNTER.fetch_add(1, Ordering::Relaxed) + 1;
+        self.handle_command(
+            swarm,
+            NetworkCommand::SendRequest(Box::new(OutboundRequest {
+                peer_id,
+                request_id,
+                protocol,
+                payload,
+                fallbacks: Vec::new(),
+            })),
+        );
+        request_id
+    }
+
+    /// Handles the response chunk of a handshake. A Status response has exactly one chunk.
+    fn on_status_response(
+        &mut self,
+        swarm: &mut libp2p::swarm::Swarm<Behaviour>,
+        peer_id: PeerId,
+        request_id: u64,
+        payload: &[u8],
+    ) {
+        let status = decode_snappy_payload(&payload[1..]).and_then(|ssz_bytes| {
+            Status::from_ssz_bytes(&ssz_bytes).map_err(ReqRespError::InvalidData)
+        });
+        match status {
+            Ok(status) if self.status_handshakes.received(request_id) => {
+                self.report_peer_status(peer_id, &status);
+            }
+            Ok(_) => self.fail_request(
+                swarm,
+                peer_id,
+                request_id,
+                ReqRespError::InvalidData("More than one Status response chunk".into()),
+            ),
+            Err(err) => self.fail_request(swarm, peer_id, request_id, err),
+        }
+    }
+
+    /// Answers a Status request with the local status and reports the status of the peer.
+    fn answer_status_request(
+        &mut self,
+        swarm: &mut libp2p::swarm::Swarm<Behaviour>,
+        peer_id: PeerId,
+        channel_id: u64,
+        payload: &[u8],
+    ) {
+        let (Some(local_status), Some(channel)) = (
+            self.local_status,
+            self.response_channels.get(&channel_id).cloned(),
+        ) else {
+            return;
+        };
+
+        let status = decode_snappy_payload(payload).and_then(|ssz_bytes| {
+            Status::from_ssz_bytes(&ssz_bytes).map_err(ReqRespError::InvalidData)
+        });
+        let status = match status {
+            Ok(status) => status,
+            Err(err) => {
+                if let Some(action) = reqresp_error_action(&err) {
+                    swarm
+                        .behaviour_mut()
+                        .peer_manager
+                        .report_peer(peer_id, action);
+                }
+                self.handle_command(
+                    swarm,
+                    NetworkCommand::SendErrorResponse {
+                        channel_id,
+                        code: ResponseCode::InvalidRequest,
+                        message: "Invalid Status request".to_string(),
+                    },
+                );
+                return;
+            }
+        };
+        self.report_peer_status(peer_id, &status);
+
+        let mut chunk = vec![ResponseCode::Success as u8];
+        chunk.extend(encode_snappy_payload(&local_status.to_ssz_bytes()));
+        swarm.behaviour_mut().reqresp.send_response(
+            channel.peer_id,
+            channel.connection_id,
+            channel.stream_id,
+            ResponseMessage::new(channel.protocol, chunk),
+        );
+        self.handle_command(swarm, NetworkCommand::EndOfStream { channel_id });
+    }
+
+    /// Ends a handshake whose response stream is complete.
+    fn end_status_handshake(
+        &mut self,
+        swarm: &mut libp2p::swarm::Swarm<Behaviour>,
+        request_id: u64,
+    ) {
+        self.request_timeouts.remove(&request_id);
+        self.finish_request(swarm, request_id);
+        let Some((peer_id, received)) = self.status_handshakes.finish(request_id) else {
+            return;
+        };
+        let outcome = if received {
+            RequestOutcome::Success
+        } else {
+            RequestOutcome::Error
+        };
+        if let Some(protocol_id) = self.request_protocols.remove(&request_id) {
+            self.metrics
+                .outbound_request_finished(request_id, protocol_id.as_str(), outcome);
+        }
+        if !received {
+            self.end_failed_handshake(swarm, peer_id, "no status in the response");
+        }
+    }
+
+    fn end_failed_handshake(
+        &mut self,
+        swarm: &mut libp2p::swarm::Swarm<Behaviour>,
+        peer_id: PeerId,
+        reason: &str,
+    ) {
+        logger::rustLogger.warn(
+            self.network_id,
+            &format!(
+                "Status handshake with peer {} failed, disconnecting: {}",
+                peer_id, reason
+            ),
+        );
+        let _ = swarm.disconnect_peer_id(peer_id);
+    }
+
+    /// Sends a Goodbye request to a connected peer, which is disconnected once the request ends.
+    fn say_goodbye(
+        &mut self,
+        swarm: &mut libp2p::swarm::Swarm<Behaviour>,
+        peer_id: PeerId,
+        reason: u64,
+    ) {
+        if !swarm.is_connected(&peer_id) {
+            logger::rustLogger.debug(
+                self.network_id,
+                &format!("Not saying goodbye to peer {}: not connected", peer_id),
             );
-            self.peer_addr_map.remove(&peer_id);
-            RECONNECT_ATTEMPTS
-                .lock()
-                .unwrap()
-                .remove(&(self.network_id, peer_id));
             return;
         }
-
-        let delay_secs = RECONNECT_DELAYS_SECS
-            .get((attempt - 1) as usize)
-            .copied()
-            .unwrap_or(80);
+        self.goodbye_peers.insert(peer_id);
+        if self.pending_goodbyes.values().any(|peer| *peer == peer_id) {
+            return;
+        }
 
         logger::rustLogger.info(
             self.network_id,
-            &format!(
-                "Scheduling reconnection to peer {} (attempt {}/{}) in {}s",
-                addr, attempt, MAX_RECONNECT_ATTEMPTS, delay_secs
-            ),
+            &format!("Saying goodbye to peer {} (reason: {})", peer_id, reason),
         );
-
-        let mut queue = RECONNECT_QUEUE.lock().unwrap();
-        queue.insert_at(
-            (self.network_id, peer_id),
-            (addr, attempt),
-            Duration::from_secs(delay_secs),
+        let request_id = self.send_glue_request(
+            swarm,
+            peer_id,
+            LeanSupportedProtocol::GoodbyeV1,
+            reason.to_le_bytes().to_vec(),
         );
+        self.pending_goodbyes.insert(request_id, peer_id);
+        if !self
+            .request_timeouts
+            .update_timeout(&request_id, GOODBYE_TIMEOUT)
+        {
+            self.request_timeouts.insert(request_id, ());
+        }
     }
 
-    pub async fn start_network(
+    /// Disconnects the peer of a finished, failed or timed out Goodbye request. Returns false if
+    /// the request is not a Goodbye.
+    fn end_goodbye(
         &mut self,
-        key_pair: Keypair,
-        listen_addresses: Vec<Multiaddr>,
-        connect_addresses: Vec<Multiaddr>,
-        topics: Vec<String>,
+        swarm: &mut libp2p::swarm::Swarm<Behaviour>,
+        request_id: u64,
+        outcome: RequestOutcome,
+    ) -> bool {
+        let Some(peer_id) = self.pending_goodbyes.remove(&request_id) else {
+            return false;
+        };
+        self.request_timeouts.remove(&request_id);
+        self.finish_request(swarm, request_id);
+        if let Some(protocol_id) = self.request_protocols.remove(&request_id) {
+            self.metrics
+                .outbound_request_finished(request_id, protocol_id.as_str(), outcome);
+        }
+        let _ = swarm.disconnect_peer_id(peer_id);
+        true
+    }
+
+    /// Takes the Goodbye request of a peer and reports its reason to Zig. The peer closes the
+    /// connection itself.
+    fn on_goodbye_request(
+        &mut self,
+        swarm: &mut libp2p::swarm::Swarm<Behaviour>,
+        peer_id: PeerId,
+        channel_id: u64,
+        payload: &[u8],
     ) {
-        let mut swarm = new_swarm(key_pair, topics, self.network_id);
-        logger::rustLogger.info(self.network_id, "starting listener");
+        self.handle_command(swarm, NetworkCommand::EndOfStream { channel_id });
+        self.goodbye_peers.insert(peer_id);
 
-        let mut listen_success = false;
-        for mut addr in listen_addresses {
-            strip_peer_id(&mut addr);
-            match swarm.listen_on(addr.clone()) {
-                Ok(_) => {
-                    logger::rustLogger.info(
-                        self.network_id,
-                        &format!("Successfully started listener on {}", addr),
-                    );
-                    listen_success = true;
-                }
-                Err(e) => {
-                    logger::rustLogger.error(
+        let reason = match decode_snappy_payload(payload) {
+            Ok(ssz_bytes) => match <[u8; GOODBYE_SSZ_LEN]>::try_from(ssz_bytes.as_slice()) {
+                Ok(bytes) => u64::from_le_bytes(bytes),
+                Err(_) => {
+                    logger::rustLogger.warn(
                         self.network_id,
-                        &format!("Failed to listen on {}: {:?}", addr, e),
+                        &format!("Goodbye of peer {} has {} bytes", peer_id, ssz_bytes.len()),
                     );
+                    return;
                 }
+            },
+            Err(err) => {
+                logger::rustLogger.warn(
+                    self.network_id,
+                    &format!("Invalid Goodbye from peer {}: {:?}", peer_id, err),
+                );
+                return;
             }
+        };
+        logger::rustLogger.info(
+            self.network_id,
+            &format!("Peer {} said goodbye (reason: {})", peer_id, reason),
+        );
+        let Ok(peer_id_cstring) = CString::new(peer_id.to_string()) else {
+            return;
+        };
+        unsafe {
+            handlePeerGoodbyeFromRustBridge(self.zig_handler, peer_id_cstring.as_ptr(), reason);
         }
+    }
 
-        if !listen_success {
-            logger::rustLogger.error(
-                self.network_id,
-                "Failed to start listener on any address - network initialization failed",
-            );
-            // Signal failure by NOT setting the ready flag
+    fn report_peer_status(&self, peer_id: PeerId, status: &Status) {
+        logger::rustLogger.debug(
+            self.network_id,
+            &format!(
+                "Status of peer {}: finalized_slot={} head_slot={}",
+                peer_id, status.finalized_slot, status.head_slot
+            ),
+        );
+        let Ok(peer_id_cstring) = CString::new(peer_id.to_string()) else {
             return;
+        };
+        unsafe {
+            handlePeerStatusFromRustBridge(
+                self.zig_handler,
+                peer_id_cstring.as_ptr(),
+                status.finalized_root.as_ptr(),
+                status.finalized_slot,
+                status.head_root.as_ptr(),
+                status.head_slot,
+            );
         }
+    }
 
-        logger::rustLogger.debug(self.network_id, "going for loop match");
-
-        if !connect_addresses.is_empty() {
-            // helper closure for dialing peers
-            let mut dial = |mut multiaddr: Multiaddr| {
-                // strip the p2p protocol if it exists
-                strip_peer_id(&mut multiaddr);
-                match swarm.dial(multiaddr.clone()) {
-                    Ok(()) => logger::rustLogger.debug(
-                        self.network_id,
-                        &format!("dialing libp2p peer address: {}", multiaddr),
-                    ),
-                    Err(err) => {
-                        logger::rustLogger.error(
-                            self.network_id,
-                            &format!(
-                                "could not connect to peer address: {} error: {:?}",
-                                multiaddr, err
-                            ),
-                        );
-                    }
-                };
-            };
+    /// Closes the listeners and asks every connection handler to drain its req/resp streams.
+    /// Connections close on their own once drained; the event loop exits when none are left or
+    /// `SHUTDOWN_DRAIN_TIMEOUT` elapses.
+    fn begin_shutdown(&mut self, swarm: &mut libp2p::swarm::Swarm<Behaviour>) {
+        logger::rustLogger.info(
+            self.network_id,
+            "shutting down: closing listeners and draining req/resp streams",
+        );
+        self.shutdown_deadline = Some(tokio::time::Instant::now() + SHUTDOWN_DRAIN_TIMEOUT);
 
-            for addr in connect_addresses {
-                if let Some(peer_id) = Self::extract_peer_id(&addr) {
-                    self.peer_addr_map
-                        .entry(peer_id)
-                        .or_insert_with(|| addr.clone());
-                } else {
-                    logger::rustLogger.warn(
-                        self.network_id,
-                        &format!("Connect address missing peer id: {}", addr),
-                    );
-                }
-                dial(addr);
-            }
-        } else {
-            logger::rustLogger.debug(self.network_id, "no connect addresses");
+        for listener_id in self.listeners.drain(..) {
+            swarm.remove_listener(listener_id);
         }
 
-        unsafe {
-            set_swarm(self.network_id, swarm);
+        // Response streams still owned by Zig would keep their inbound substream open, so finish
+        // them now to let the handlers drain.
+        let mut pending_responses = Vec::new();
+        self.response_channels.retain(|_, pending| {
+            pending_responses.push(pending.clone());
+            false
+        });
+        for pending in pending_responses {
+            swarm.behaviour_mut().reqresp.finish_response_stream(
+                pending.peer_id,
+                pending.connection_id,
+                pending.stream_id,
+            );
         }
 
-        // Signal that this network is now ready
-        {
-            let mut ready = NETWORK_READY_SIGNALS.lock().unwrap();
-            match self.network_id {
-                0 => ready.0 = true,
-                1 => ready.1 = true,
-                2 => ready.2 = true,
-                _ => {}
-            }
-            NETWORK_READY_CONDVAR.notify_all();
+        for (peer_id, connection_id) in self.connection_directions.keys() {
+            swarm
+                .behaviour_mut()
+                .reqresp
+                .shutdown(*peer_id, *connection_id);
         }
 
-        logger::rustLogger.info(self.network_id, "network initialization complete and ready");
+        // Nothing should be redialed while the network goes away.
+        self.peer_addr_map.clear();
+        self.reconnect_attempts.clear();
+        self.reconnect_queue.retain(|_, _| false);
+    }
+
+    /// Drops all per-network request bookkeeping once the event loop has exited.
+    fn clear_state(&mut self) {
+        self.request_timeouts.retain(|_, _| false);
+        self.request_protocols.clear();
+        self.outbound_queue.clear();
+        self.status_handshakes.clear();
+        self.pending_goodbyes.clear();
+        self.goodbye_peers.clear();
+        self.response_channels.retain(|_, _| false);
+        self.reconnect_queue.retain(|_, _| false);
+        self.reconnect_attempts.clear();
+        self.connection_directions.clear();
+        self.pending_validations.retain(|_, _| false);
+        self.mesh_peers.clear();
+        self.peer_metadata.clear();
+        self.metrics.clear();
+        self.peer_addr_map.clear();
+        self.listeners.clear();
     }
 
     pub async fn run_eventloop(&mut self) {
-        let swarm = unsafe { get_swarm_mut(self.network_id) }
-            .expect("run_eventloop called before start_network stored the swarm");
+        let (Some(mut swarm), Some(mut commands)) = (self.swarm.take(), self.commands.take())
+        else {
+            logger::rustLogger.error(
+                self.network_id,
+                "run_eventloop called before start_network created the swarm",
+            );
+            return;
+        };
+        let mut mesh_poll = tokio::time::interval(self.mesh_poll_interval);
+        mesh_poll.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut discovery_poll = tokio::time::interval(DISCOVERY_QUERY_INTERVAL);
+        discovery_poll.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut peer_store_poll = tokio::time::interval(PEER_STORE_SAVE_INTERVAL);
+        peer_store_poll.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
 
         loop {
+            if self.shutdown_deadline.is_some() && swarm.network_info().num_peers() == 0 {
+                logger::rustLogger.info(
+                    self.network_id,
+                    "all connections drained, leaving event loop",
+                );
+                break;
+            }
+
             tokio::select! {
 
-            Some(timeout_result) = poll_fn(|cx| {
-                let mut map = REQUEST_ID_MAP.lock().unwrap();
-                std::pin::Pin::new(&mut *map).poll_next(cx)
-            }) => {
+            command = commands.recv(), if self.shutdown_deadline.is_none() => {
+                match command {
+                    Some(command) => self.handle_command(&mut swarm, command),
+                    None => {
+                        logger::rustLogger.info(self.network_id, "command channel closed, leaving event loop");
+                        break;
+                    }
+                }
+            }
+
+            _ = tokio::time::sleep_until(self.shutdown_deadline.unwrap_or_else(tokio::time::Instant::now)), if self.shutdown_deadline.is_some() => {
+                logger::rustLogger.warn(
+                    self.network_id,
+                    &format!("connections did not drain within {:?}, leaving event loop", SHUTDOWN_DRAIN_TIMEOUT),
+                );
+                break;
+            }
+
+            _ = mesh_poll.tick(), if self.shutdown_deadline.is_none() => {
+                self.report_mesh_changes(&swarm);
+            }
+
+            _ = peer_store_poll.tick(), if self.shutdown_deadline.is_none() => {
+                self.save_peer_store(&swarm);
+            }
+
+            _ = discovery_poll.tick(), if self.shutdown_deadline.is_none() => {
+                let behaviour = swarm.behaviour_mut();
+                if behaviour.peer_manager.needs_peers() {
+                    if let Some(discovery) = behaviour.discovery.as_mut() {
+                        discovery.discover_peers();
+                    }
+                }
+            }
+
+            Some(timeout_result) = self.request_timeouts.next() => {
                 match timeout_result {
                     Ok((request_id, ())) => {
+                        let queued = if self.outbound_queue.is_queued(request_id) { " in the outbound queue" } else { "" };
                         logger::rustLogger.warn(
                             self.network_id,
-                            &format!("[reqresp] Request {} timed out after {:?}", request_id, REQUEST_TIMEOUT),
+                            &format!("[reqresp] Request {} timed out{} after {:?}", request_id, queued, REQUEST_TIMEOUT),
                         );
-                        if let Some(protocol_id) = REQUEST_PROTOCOL_MAP
-                            .lock()
-                            .unwrap()
-                            .remove(&request_id)
-                        {
+                        self.abort_request(&mut swarm, request_id);
+                        if self.end_goodbye(&mut swarm, request_id, RequestOutcome::Timeout) {
+                            continue;
+                        }
+                        if let Some((peer_id, _)) = self.status_handshakes.finish(request_id) {
+                            if let Some(protocol_id) = self.request_protocols.remove(&request_id) {
+                                self.metrics.outbound_request_finished(request_id, protocol_id.as_str(), RequestOutcome::Timeout);
+                            }
+                            self.end_failed_handshake(&mut swarm, peer_id, "timed out");
+                            continue;
+                        }
+                        if let Some(protocol_id) = self.request_protocols.remove(&request_id) {
+                            self.metrics.outbound_request_finished(request_id, protocol_id.as_str(), RequestOutcome::Timeout);
                             if let (Ok(protocol_cstring), Ok(message_cstring)) = (
                                 CString::new(protocol_id.as_str()),
                                 CString::new("request timed out"),
@@ -856,7 +2789,7 @@ impl Network {
                                         self.zig_handler,
                                         request_id,
                                         protocol_cstring.as_ptr(),
-                                        408,
+                                        REQUEST_TIMEOUT_CODE,
                                         message_cstring.as_ptr(),
                                     );
                                 }
@@ -869,59 +2802,49 @@ impl Network {
                 }
             }
 
-            Some(reconnect_result) = poll_fn(|cx| {
-                let mut queue = RECONNECT_QUEUE.lock().unwrap();
-                std::pin::Pin::new(&mut *queue).poll_next(cx)
-            }) => {
-                    match reconnect_result {
-                        Ok(((network_id, peer_id), (addr, attempt))) => {
-                            if network_id == self.network_id {
-                                if swarm.is_connected(&peer_id) {
-                                    logger::rustLogger.debug(
-                                        self.network_id,
-                                        &format!(
-                                            "Skipping reconnection attempt to peer {} because it is already connected",
-                                            peer_id
-                                        ),
-                                    );
-                                    continue;
-                                }
+            Some(reconnect_result) = self.reconnect_queue.next() => {
+                match reconnect_result {
+                    Ok((peer_id, (addr, attempt))) => {
+                        if swarm.is_connected(&peer_id) {
+                            logger::rustLogger.debug(
+                                self.network_id,
+                                &format!(
+                                    "Skipping reconnection attempt to peer {} because it is already connected",
+                                    peer_id
+                                ),
+                            );
+                            continue;
+                        }
 
-                                logger::rustLogger.info(
-                                    self.network_id,
-                                    &format!("Attempting reconnection to {} (attempt {}/{})", addr, attempt, MAX_RECONNECT_ATTEMPTS),
-                                );
+                        logger::rustLogger.info(
+                            self.network_id,
+                            &format!("Attempting reconnection to {} (attempt {}/{})", addr, attempt, MAX_RECONNECT_ATTEMPTS),
+                        );
 
-                            RECONNECT_ATTEMPTS
-                                .lock()
-                                .unwrap()
-                                .insert((self.network_id, peer_id), (addr.clone(), attempt));
+                        self.reconnect_attempts.insert(peer_id, (addr.clone(), attempt));
+                        self.metrics.reconnect_attempted();
 
-                            let mut dial_addr = addr.clone();
-                            strip_peer_id(&mut dial_addr);
+                        let mut dial_addr = addr.clone();
+                        strip_peer_id(&mut dial_addr);
 
-                            match swarm.dial(
-                                DialOpts::peer_id(peer_id)
-                                    .addresses(vec![dial_addr.clone()])
-                                    .build(),
-                            ) {
-                                Ok(()) => {
-                                    logger::rustLogger.info(
-                                        self.network_id,
-                                        &format!("Dialing peer {} at {} for reconnection", peer_id, dial_addr),
-                                    );
-                                }
-                                Err(e) => {
-                                    logger::rustLogger.error(
-                                        self.network_id,
-                                        &format!("Failed to dial peer {} at {}: {:?}", peer_id, dial_addr, e),
-                                    );
-                                    RECONNECT_ATTEMPTS
-                                        .lock()
-                                        .unwrap()
-                                        .remove(&(self.network_id, peer_id));
-                                    self.schedule_reconnection(peer_id, addr, attempt + 1);
-                                }
+                        match swarm.dial(
+                            DialOpts::peer_id(peer_id)
+                                .addresses(vec![dial_addr.clone()])
+                                .build(),
+                        ) {
+                            Ok(()) => {
+                                logger::rustLogger.info(
+                                    self.network_id,
+                                    &format!("Dialing peer {} at {} for reconnection", peer_id, dial_addr),
+                                );
+                            }
+                            Err(e) => {
+                                logger::rustLogger.error(
+                                    self.network_id,
+                                    &format!("Failed to dial peer {} at {}: {:?}", peer_id, dial_addr, e),
+                                );
+                                self.reconnect_attempts.remove(&peer_id);
+                                self.schedule_reconnection(peer_id, addr, attempt + 1);
                             }
                         }
                     }
@@ -931,10 +2854,20 @@ impl Network {
                 }
             }
 
-            Some(response_channel_timeout) = poll_fn(|cx| {
-                let mut map = RESPONSE_CHANNEL_MAP.lock().unwrap();
-                std::pin::Pin::new(&mut *map).poll_next(cx)
-            }) => {
+            Some(validation_timeout) = self.pending_validations.next() => {
+                if let Ok((message_id, propagation_source)) = validation_timeout {
+                    logger::rustLogger.debug(
+                        self.network_id,
+                        &format!(
+                            "Gossip message {} from {} was never validated",
+                            hex::encode(&message_id.0),
+                            propagation_source
+                        ),
+                    );
+                }
+            }
+
+            Some(response_channel_timeout) = self.response_channels.next() => {
                 match response_channel_timeout {
                     Ok((channel_id, channel)) => {
                         logger::rustLogger.warn(
@@ -947,6 +2880,7 @@ impl Network {
                                 channel.protocol.as_str(),
                             ),
                         );
+                        self.metrics.inbound_request_finished(channel.protocol.as_str(), RequestOutcome::Timeout);
 
                         // Best-effort: close the response stream so the remote does not hang.
                         swarm.behaviour_mut().reqresp.finish_response_stream(
@@ -965,11 +2899,12 @@ impl Network {
             }
 
                 event = swarm.select_next_some() => {
+                    self.record_metrics(&event);
                     match event {
                         SwarmEvent::NewListenAddr { address, .. } => {
                             logger::rustLogger.info(self.network_id, &format!("Listening on {}", address));
                         }
-                        SwarmEvent::ConnectionEstablished { peer_id, endpoint, connection_id, .. } => {
+                        SwarmEvent::ConnectionEstablished { peer_id, endpoint, connection_id, num_established, .. } => {
                             let peer_id_str = peer_id.to_string();
 
                             // Determine direction from endpoint: Dialer=outbound, Listener=inbound
@@ -982,6 +2917,7 @@ impl Network {
                                 self.peer_addr_map
                                     .entry(peer_id)
                                     .or_insert_with(|| address.clone());
+                                self.peer_store.on_dialed(peer_id, address.clone(), unix_now());
                             }
 
                             logger::rustLogger.info(
@@ -992,16 +2928,21 @@ impl Network {
                             );
 
                             // Store direction for later use on disconnect
-                            CONNECTION_DIRECTIONS.lock().unwrap().insert(
-                                (self.network_id, peer_id, connection_id),
-                                direction,
+                            self.connection_directions.insert((peer_id, connection_id), direction);
+                            self.peer_metadata.connection_established(
+                                peer_id,
+                                connection_id,
+                                endpoint.get_remote_address().clone(),
                             );
 
-                            RECONNECT_QUEUE.lock().unwrap().remove(&(self.network_id, peer_id));
-                            RECONNECT_ATTEMPTS
-                                .lock()
-                                .unwrap()
-                                .remove(&(self.network_id, peer_id));
+                            if self.shutdown_deadline.is_some() {
+                                // A dial that was already in flight completed while stopping.
+                                swarm.behaviour_mut().reqresp.shutdown(peer_id, connection_id);
+                                continue;
+                            }
+
+                            self.reconnect_queue.remove(&peer_id);
+                            self.reconnect_attempts.remove(&peer_id);
                             let peer_id_cstr = match CString::new(peer_id_str.as_str()) {
                                 Ok(cstr) => cstr,
                                 Err(_) => {
@@ -1012,6 +2953,9 @@ impl Network {
                             unsafe {
                                 handlePeerConnectedFromRustBridge(self.zig_handler, peer_id_cstr.as_ptr(), direction)
                             };
+                            if num_established.get() == 1 {
+                                self.start_status_handshake(&mut swarm, peer_id);
+                            }
                         }
                             SwarmEvent::ConnectionClosed {
                                 peer_id,
@@ -1022,11 +2966,12 @@ impl Network {
                                 let peer_id_string = peer_id.to_string();
 
                             // Retrieve and remove stored direction
-                            let direction = CONNECTION_DIRECTIONS
-                                .lock()
-                                .unwrap()
-                                .remove(&(self.network_id, peer_id, connection_id))
+                            let direction = self
+                                .connection_directions
+                                .remove(&(peer_id, connection_id))
                                 .unwrap_or(2); // 2 = unknown if not found
+                            self.peer_metadata.connection_closed(&peer_id, &connection_id);
+                            self.peer_store.seen(&peer_id, unix_now());
 
                             // Map cause to reason enum: 0=timeout, 1=remote_close, 2=local_close, 3=error
                             let reason: u32 = match &cause {
@@ -1058,7 +3003,7 @@ impl Network {
                                 // Drop any pending response channels tied to this connection.
                                 // We can't finish streams here (the connection is already gone), but we must
                                 // remove them from the map to avoid leaking entries until idle TTL.
-                                RESPONSE_CHANNEL_MAP.lock().unwrap().retain(|_, pending| {
+                                self.response_channels.retain(|_, pending| {
                                     !(pending.peer_id == peer_id && pending.connection_id == connection_id)
                                 });
 
@@ -1076,6 +3021,9 @@ impl Network {
                                     continue;
                                 }
 
+                                // Queued requests of the peer will not be sent anymore and time out
+                                self.outbound_queue.remove_peer(&peer_id);
+
                                 let peer_id_cstr = match CString::new(peer_id_string.as_str()) {
                                     Ok(cstr) => cstr,
                                     Err(_) => {
@@ -1092,6 +3040,10 @@ impl Network {
                                     )
                                 };
 
+                                let said_goodbye = self.goodbye_peers.remove(&peer_id);
+                                if said_goodbye || swarm.behaviour().peer_manager.is_banned(&peer_id) {
+                                    continue;
+                                }
                                 if let Some(peer_addr) = self.peer_addr_map.get(&peer_id).cloned() {
                                     self.schedule_reconnection(peer_id, peer_addr, 1);
                                 }
@@ -1134,18 +3086,15 @@ impl Network {
                                 };
 
                                 // Schedule reconnection if this was a tracked connection attempt
-                                if let Some((addr, attempt)) = RECONNECT_ATTEMPTS
-                                    .lock()
-                                    .unwrap()
-                                    .remove(&(self.network_id, pid))
-                                {
+                                if let Some((addr, attempt)) = self.reconnect_attempts.remove(&pid) {
                                     self.schedule_reconnection(pid, addr, attempt + 1);
                                 }
                             }
                         }
                         SwarmEvent::Behaviour(BehaviourEvent::Gossipsub(gossipsub::Event::Message {
+                            propagation_source,
+                            message_id,
                             message,
-                            ..
                         })) => {
                             let topic = message.topic.as_str();
                             let topic = match CString::new(topic) {
@@ -1159,6 +3108,10 @@ impl Network {
 
                             let message_ptr = message.data.as_ptr();
                             let message_len = message.data.len();
+                            self.metrics.gossip_bytes(message.topic.as_str(), GossipDirection::Received, message_len);
+                            if message_len > self.idontwant_threshold {
+                                self.record_idontwant(&swarm, &message.topic, &propagation_source, message_len);
+                            }
 
                             let sender_peer_id_string = message.source.map(|p| p.to_string()).unwrap_or_else(|| "unknown_peer".to_string());
                             let sender_peer_id_cstring = match CString::new(sender_peer_id_string.clone()) {
@@ -1172,8 +3125,26 @@ impl Network {
                                 }
                             };
 
+                            let message_id_cstring = match CString::new(hex::encode(&message_id.0)) {
+                                Ok(cstring) => cstring,
+                                Err(_) => {
+                                    logger::rustLogger.error(self.network_id, "Failed to create C string for gossip message id");
+                                    continue;
+                                }
+                            };
+
+                            // Gossipsub holds the message back until Zig reports the validation result.
+                            self.pending_validations.insert(message_id, propagation_source);
+
                             unsafe {
-                                handleMsgFromRustBridge(self.zig_handler, topic, message_ptr, message_len, sender_peer_id_cstring.as_ptr())
+                                handleMsgFromRustBridge(
+                                    self.zig_handler,
+                                    topic,
+                                    message_ptr,
+                                    message_len,
+                                    sender_peer_id_cstring.as_ptr(),
+                                    message_id_cstring.as_ptr(),
+                                )
                             };
                             logger::rustLogger.debug(self.network_id, "zig callback completed");
                         }
@@ -1185,7 +3156,7 @@ impl Network {
                             Ok(ReqRespMessageReceived::Request { stream_id, message }) => {
                                 let request_message = *message;
                                 let protocol = request_message.protocol.clone();
-                                let payload = request_message.payload;
+                                let mut payload = request_message.payload;
                                 logger::rustLogger.info(
                                     self.network_id,
                                     &format!("[reqresp] Received request from {} for protocol {} ({} bytes)", peer_id, protocol.as_str(), payload.len()),
@@ -1193,7 +3164,7 @@ impl Network {
 
                                 let channel_id =
                                     RESPONSE_CHANNEL_COUNTER.fetch_add(1, Ordering::Relaxed) + 1;
-                                RESPONSE_CHANNEL_MAP.lock().unwrap().insert(
+                                self.response_channels.insert(
                                     channel_id,
                                     PendingResponse {
                                         peer_id,
@@ -1203,6 +3174,41 @@ impl Network {
                                     },
                                 );
 
+                                if protocol.lean_protocol() == Some(LeanSupportedProtocol::GoodbyeV1) {
+                                    self.on_goodbye_request(&mut swarm, peer_id, channel_id, &payload);
+                                    continue;
+                                }
+                                if self.local_status.is_some()
+                                    && protocol.lean_protocol() == Some(LeanSupportedProtocol::StatusV1)
+                                {
+                                    self.answer_status_request(&mut swarm, peer_id, channel_id, &payload);
+                                    continue;
+                                }
+
+                                if self.decompress_rpc_payloads {
+                                    match decode_snappy_payload(&payload) {
+                                        Ok(ssz_bytes) => payload = ssz_bytes,
+                                        Err(err) => {
+                                            logger::rustLogger.error(
+                                                self.network_id,
+                                                &format!("[reqresp] Failed to decompress request from {} for protocol {}: {:?}", peer_id, protocol.as_str(), err),
+                                            );
+                                            if let Some(action) = reqresp_error_action(&err) {
+                                                swarm.behaviour_mut().peer_manager.report_peer(peer_id, action);
+                                            }
+                                            self.handle_command(
+                                                &mut swarm,
+                                                NetworkCommand::SendErrorResponse {
+                                                    channel_id,
+                                                    code: ResponseCode::InvalidRequest,
+                                                    message: "Failed to decompress request".to_string(),
+                                                },
+                                            );
+                                            continue;
+                                        }
+                                    }
+                                }
+
                                 let peer_id_string = peer_id.to_string();
                                 let peer_id_cstring = match CString::new(peer_id_string) {
                                     Ok(cstring) => cstring,
@@ -1238,13 +3244,33 @@ impl Network {
                                 }
                             }
                             Ok(ReqRespMessageReceived::Response { request_id, message }) => {
+                                if !self.request_protocols.contains_key(&request_id)
+                                    || self.pending_goodbyes.contains_key(&request_id)
                                 {
-                                    let mut map = REQUEST_ID_MAP.lock().unwrap();
-                                    if !map.update_timeout(&request_id, REQUEST_TIMEOUT) {
-                                        map.insert(request_id, ());
+                                    logger::rustLogger.debug(
+                                        self.network_id,
+                                        &format!("[reqresp] Dropping response from {} for finished request id {}", peer_id, request_id),
+                                    );
+                                    continue;
+                                }
+                                if !self.request_timeouts.update_timeout(&request_id, REQUEST_TIMEOUT) {
+                                    self.request_timeouts.insert(request_id, ());
+                                }
+                                let mut response_message = *message;
+                                if self.status_handshakes.contains(request_id) {
+                                    self.on_status_response(&mut swarm, peer_id, request_id, &response_message.payload);
+                                    continue;
+                                }
+                                if self.decompress_rpc_payloads {
+                                    // Error chunks never get here, so the code byte is always Success
+                                    match decode_snappy_payload(&response_message.payload[1..]) {
+                                        Ok(ssz_bytes) => response_message.payload = ssz_bytes,
+                                        Err(err) => {
+                                            self.fail_request(&mut swarm, peer_id, request_id, err);
+                                            continue;
+                                        }
                                     }
                                 }
-                                let response_message = *message;
                                 logger::rustLogger.info(
                                     self.network_id,
                                     &format!("[reqresp] Received response from {} for request id {} ({} bytes)", peer_id, request_id, response_message.payload.len()),
@@ -1281,17 +3307,27 @@ impl Network {
                                         protocol_cstring.as_ptr(),
                                         response_message.payload.as_ptr(),
                                         response_message.payload.len(),
+                                        response_message
+                                            .context_bytes
+                                            .as_ref()
+                                            .map_or(std::ptr::null(), |digest| digest.as_ptr()),
                                     );
                                 }
                             }
                             Ok(ReqRespMessageReceived::EndOfStream { request_id }) => {
-                                REQUEST_ID_MAP.lock().unwrap().remove(&request_id);
-                                let protocol = REQUEST_PROTOCOL_MAP
-                                    .lock()
-                                    .unwrap()
-                                    .remove(&request_id);
+                                if self.end_goodbye(&mut swarm, request_id, RequestOutcome::Success) {
+                                    continue;
+                                }
+                                if self.status_handshakes.contains(request_id) {
+                                    self.end_status_handshake(&mut swarm, request_id);
+                                    continue;
+                                }
+                                self.request_timeouts.remove(&request_id);
+                                self.finish_request(&mut swarm, request_id);
+                                let protocol = self.request_protocols.remove(&request_id);
 
                                 if let Some(protocol_id) = protocol {
+                                    self.metrics.outbound_request_finished(request_id, protocol_id.as_str(), RequestOutcome::Success);
                                     let peer_id_string = peer_id.to_string();
                                     let peer_id_cstring = match CString::new(peer_id_string) {
                                         Ok(cstring) => cstring,
@@ -1323,9 +3359,9 @@ impl Network {
                                         );
                                     }
                                 } else {
-                                    logger::rustLogger.warn(
+                                    logger::rustLogger.debug(
                                         self.network_id,
-                                        &format!("[reqresp] Received end-of-stream for request id {} without protocol mapping", request_id),
+                                        &format!("[reqresp] Received end-of-stream for finished or cancelled request id {}", request_id),
                                     );
                                 }
                             }
@@ -1334,56 +3370,66 @@ impl Network {
                                     self.network_id,
                                     &format!("[reqresp] Inbound error from {} on stream {}: {:?}", peer_id, stream_id, err),
                                 );
-                                RESPONSE_CHANNEL_MAP
-                                    .lock()
-                                    .unwrap()
+                                if let Some(action) = reqresp_error_action(&err) {
+                                    swarm.behaviour_mut().peer_manager.report_peer(peer_id, action);
+                                }
+                                let mut failed_protocols = Vec::new();
+                                self.response_channels
                                     .retain(|_, pending| {
-                                        !(
-                                            pending.peer_id == peer_id
-                                                && pending.connection_id == connection_id
-                                                && pending.stream_id == stream_id
-                                        )
+                                        let failed = pending.peer_id == peer_id
+                                            && pending.connection_id == connection_id
+                                            && pending.stream_id == stream_id;
+                                        if failed {
+                                            failed_protocols.push(pending.protocol.clone());
+                                        }
+                                        !failed
                                     });
+                                for protocol in failed_protocols {
+                                    self.metrics.inbound_request_finished(protocol.as_str(), RequestOutcome::Error);
+                                }
                             }
                             Err(ReqRespMessageError::Outbound { request_id, err }) => {
-                                REQUEST_ID_MAP.lock().unwrap().remove(&request_id);
-                                let protocol = REQUEST_PROTOCOL_MAP
-                                    .lock()
-                                    .unwrap()
-                                    .remove(&request_id);
-
-                                if let Some(protocol_id) = protocol {
-                                    if let (Ok(protocol_cstring), Ok(message_cstring)) = (
-                                        CString::new(protocol_id.as_str()),
-                                        CString::new(format!("{:?}", err)),
-                                    ) {
-                                        unsafe {
-                                            handleRPCErrorFromRustBridge(
-                                                self.zig_handler,
-                                                request_id,
-                                                protocol_cstring.as_ptr(),
-                                                3,
-                                                message_cstring.as_ptr(),
-                                            );
-                                        }
-                                    }
+                                if !self.request_protocols.contains_key(&request_id) {
+                                    logger::rustLogger.debug(
+                                        self.network_id,
+                                        &format!("[reqresp] Dropping error of finished or cancelled request id {}: {:?}", request_id, err),
+                                    );
+                                    continue;
                                 }
-                                logger::rustLogger.error(
-                                    self.network_id,
-                                    &format!("[reqresp] Outbound error for request {} with {}: {:?}", request_id, peer_id, err),
-                                );
+                                self.fail_request(&mut swarm, peer_id, request_id, err);
                             }
                         },
+                        SwarmEvent::Behaviour(BehaviourEvent::Identify(identify::Event::Received { peer_id, info, .. })) => {
+                            let protocols = info.protocols.iter().map(|protocol| protocol.to_string()).collect::<Vec<_>>();
+                            self.peer_store.on_identify(&peer_id, info.agent_version.clone(), protocols.clone());
+                            self.peer_metadata.on_identify(&peer_id, info.agent_version, protocols);
+                        }
+                        SwarmEvent::Behaviour(BehaviourEvent::Ping(ping::Event { peer, result: Ok(rtt), .. })) => {
+                            self.peer_metadata.on_ping(&peer, rtt);
+                        }
+                        SwarmEvent::Behaviour(BehaviourEvent::PeerManager(event)) => {
+                            self.handle_peer_manager_event(&mut swarm, event);
+                        }
+                        SwarmEvent::Behaviour(BehaviourEvent::Discovery(DiscoveryEvent::DiscoveredPeers(peers))) => {
+                            self.dial_peers(&mut swarm, peers);
+                        }
                         e => logger::rustLogger.debug(self.network_id, &format!("{:?}", e)),
                     }
                 }
             }
         }
+
+        self.save_peer_store(&swarm);
+        self.clear_state();
+        logger::rustLogger.info(self.network_id, "event loop stopped");
     }
 }
 
 #[derive(NetworkBehaviour)]
 struct Behaviour {
+    // Checked first so banned or excess connections are denied before other handlers are created
+    peer_manager: PeerManager,
+    discovery: Toggle<Discovery>,
     identify: identify::Behaviour,
     ping: ping::Behaviour,
     gossipsub: gossipsub::Behaviour,
@@ -1424,39 +3470,44 @@ impl Behaviour {
         gossipsub::MessageId::from(&digest[..20])
     }
 
-    fn new(key: identity::Keypair) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        key: identity::Keypair,
+        gossipsub_config: &GossipsubConfig,
+        peer_manager_config: PeerManagerConfig,
+        discovery: Option<Discovery>,
+        rate_limiter_config: RateLimiterConfig,
+        fork_context: ForkContext,
+        metrics_registry: &mut Registry,
+    ) -> Result<Self, String> {
         let local_public_key = key.public();
         // To content-address message, we can take the hash of message and use it as an ID.
+        // No two messages of the same content will be propagated.
         let message_id_fn = |message: &gossipsub::Message| Self::message_id_fn(message);
-
-        // Set a custom gossipsub configuration
-        let gossipsub_config = gossipsub::ConfigBuilder::default()
-            .mesh_n(8)
-            .mesh_n_low(6)
-            .mesh_n_high(12)
-            .gossip_lazy(6)
-            .heartbeat_interval(Duration::from_millis(700))
-            .validation_mode(gossipsub::ValidationMode::Anonymous)
-            .history_length(6)
-            .duplicate_cache_time(Duration::from_secs(3 * 4 * 2))
-            .max_transmit_size(2 * 1024 * 1024) // 2 MiB for blocks/ aggregated payloads
-            .message_id_fn(message_id_fn) // content-address messages. No two messages of the same content will be propagated.
-            .build()
-            .unwrap();
-        // .map_err(|msg| io::Error::new(io::ErrorKind::Other, msg))?; // Temporary hack because `build` does not return a proper `std::error::Error`.
+        let gossipsub_config = gossipsub_config.to_gossipsub_config(message_id_fn)?;
 
         // build a gossipsub network behaviour with Anonymous mode for multi-client compatibility
         // Anonymous mode ensures interoperability with other clients (ream, lanten, qlean)
-        let gossipsub =
-            gossipsub::Behaviour::new(gossipsub::MessageAuthenticity::Anonymous, gossipsub_config)
-                .unwrap();
-
-        let reqresp = ReqResp::new(vec![
-            LeanSupportedProtocol::StatusV1.into(),
-            LeanSupportedProtocol::BlocksByRootV1.into(),
-        ]);
+        let gossipsub = gossipsub::Behaviour::new_with_metrics(
+            gossipsub::MessageAuthenticity::Anonymous,
+            gossipsub_config,
+            metrics_registry.sub_registry_with_prefix("gossipsub"),
+            gossipsub::MetricsConfig::default(),
+        )
+        .map_err(|e| format!("failed to create gossipsub: {e}"))?;
+
+        let reqresp = ReqResp::new(
+            LeanProtocolFamily::all_versions()
+                .into_iter()
+                .map(ProtocolId::from)
+                .collect(),
+            rate_limiter_config,
+            fork_context,
+        );
 
-        Self {
+        Ok(Self {
+            peer_manager: PeerManager::new(peer_manager_config),
+            discovery: discovery.into(),
             identify: identify::Behaviour::new(identify::Config::new(
                 "/ipfs/0.1.0".into(),
                 local_public_key.clone(),
@@ -1464,26 +3515,45 @@ impl Behaviour {
             ping: ping::Behaviour::default(),
             gossipsub,
             reqresp,
-        }
+        })
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn new_swarm(
     local_keypair: Keypair,
+    transport_config: &TransportConfig,
+    gossipsub_config: &GossipsubConfig,
     topics: Vec<String>,
+    peer_manager_config: PeerManagerConfig,
+    discovery: Option<Discovery>,
+    rate_limiter_config: RateLimiterConfig,
+    fork_context: ForkContext,
+    metrics_registry: &mut Registry,
     network_id: u32,
-) -> libp2p::swarm::Swarm<Behaviour> {
-    let transport = build_transport(local_keypair.clone(), true).unwrap();
+) -> Result<libp2p::swarm::Swarm<Behaviour>, String> {
+    let transport = build_transport(&local_keypair, transport_config)
+        .map_err(|e| format!("failed to build the transport: {e}"))?;
     logger::rustLogger.debug(network_id, "build the transport");
 
+    let behaviour = Behaviour::new(
+        local_keypair.clone(),
+        gossipsub_config,
+        peer_manager_config,
+        discovery,
+        rate_limiter_config,
+        fork_context,
+        metrics_registry,
+    )?;
+
     let builder = SwarmBuilder::with_existing_identity(local_keypair)
         .with_tokio()
         .with_other_transport(|_key| transport)
-        .expect("infalible");
+        .map_err(|e| format!("failed to build the transport: {e}"))?;
 
     let mut swarm = builder
-        .with_behaviour(|key| Behaviour::new(key.clone()))
-        .unwrap()
+        .with_behaviour(|_key| behaviour)
+        .map_err(|e| format!("failed to build the behaviour: {e}"))?
         .with_swarm_config(|cfg| cfg.with_idle_connection_timeout(Duration::from_secs(u64::MAX)))
         .build();
 
@@ -1498,54 +3568,7 @@ fn new_swarm(
         }
     }
 
-    swarm
-}
-
-fn build_transport(
-    local_private_key: Keypair,
-    quic_support: bool,
-) -> std::io::Result<BoxedTransport> {
-    // mplex config
-    let mut mplex_config = libp2p_mplex::Config::new();
-    mplex_config.set_max_buffer_size(256);
-    mplex_config.set_max_buffer_behaviour(libp2p_mplex::MaxBufferBehaviour::Block);
-
-    // yamux config
-    let yamux_config = yamux::Config::default();
-    // Creates the TCP transport layer
-    let tcp = libp2p::tcp::tokio::Transport::new(libp2p::tcp::Config::default().nodelay(true))
-        .upgrade(core::upgrade::Version::V1)
-        .authenticate(generate_noise_config(&local_private_key))
-        .multiplex(core::upgrade::SelectUpgrade::new(
-            yamux_config,
-            mplex_config,
-        ))
-        .timeout(Duration::from_secs(10));
-    let transport = if quic_support {
-        // Enables Quic
-        // The default quic configuration suits us for now.
-        let quic_config = libp2p::quic::Config::new(&local_private_key);
-        let quic = libp2p::quic::tokio::Transport::new(quic_config);
-        let transport = tcp
-            .or_transport(quic)
-            .map(|either_output, _| match either_output {
-                Either::Left((peer_id, muxer)) => (peer_id, StreamMuxerBox::new(muxer)),
-                Either::Right((peer_id, muxer)) => (peer_id, StreamMuxerBox::new(muxer)),
-            });
-        transport.boxed()
-    } else {
-        tcp.boxed()
-    };
-
-    // Enables DNS over the transport.
-    let transport = libp2p::dns::tokio::Transport::system(transport)?.boxed();
-
-    Ok(transport)
-}
-
-/// Generate authenticated XX Noise config from identity keys
-fn generate_noise_config(identity_keypair: &Keypair) -> noise::Config {
-    noise::Config::new(identity_keypair).expect("signing can fail only once during starting a node")
+    Ok(swarm)
 }
 
 /// For a multiaddr that ends with a peer id, this strips this suffix. Rust-libp2p
@@ -1562,6 +3585,8 @@ fn strip_peer_id(addr: &mut Multiaddr) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::discovery::EnrExt;
+    use crate::registry::Readiness;
     use libp2p::gossipsub::IdentTopic;
     use libp2p::gossipsub::MessageId;
     use snap::raw::Encoder;
@@ -1577,6 +3602,243 @@ mod tests {
         // Mock: do nothing
     }
 
+    #[no_mangle]
+    extern "C" fn releaseStartNetworkParams(
+        _zig_handler: u64,
+        _local_private_key: *const c_char,
+        _listen_addresses: *const c_char,
+        _connect_addresses: *const c_char,
+        _topics: *const c_char,
+        _peer_score_params: *const c_char,
+        _transport_config: *const c_char,
+        _gossipsub_config: *const c_char,
+        _req_resp_config: *const c_char,
+        _bootnodes: *const c_char,
+        _peer_store_path: *const c_char,
+    ) {
+    }
+
+    #[no_mangle]
+    extern "C" fn handleMsgFromRustBridge(
+        zig_handler: u64,
+        _topic: *const c_char,
+        _message_ptr: *const u8,
+        _message_len: usize,
+        _sender_peer_id: *const c_char,
+        message_id: *const c_char,
+    ) {
+        // Accept every message so gossipsub forwards it, test handlers are their network ids.
+        // Like Zig, take a moment to validate: by then every receiver has the message from its
+        // publisher and has announced it with IDONTWANT.
+        let message_id = unsafe { CStr::from_ptr(message_id) }.to_owned();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(500));
+            unsafe { report_gossip_validation(zig_handler as u32, message_id.as_ptr(), 0) };
+        });
+    }
+
+    /// Test nodes that received a request, which they leave unanswered.
+    static RPC_REQUESTS: std::sync::Mutex<Vec<u64>> = std::sync::Mutex::new(Vec::new());
+
+    /// Response, end-of-stream and error callbacks of outbound requests, as (zig_handler,
+    /// request_id).
+    static RPC_CALLBACKS: std::sync::Mutex<Vec<(u64, u64)>> = std::sync::Mutex::new(Vec::new());
+
+    #[no_mangle]
+    extern "C" fn handleRPCRequestFromRustBridge(
+        zig_handler: u64,
+        _channel_id: u64,
+        _peer_id: *const c_char,
+        _protocol_id: *const c_char,
+        _request_ptr: *const u8,
+        _request_len: usize,
+    ) {
+        RPC_REQUESTS.lock().unwrap().push(zig_handler);
+    }
+
+    #[no_mangle]
+    extern "C" fn handleRPCResponseFromRustBridge(
+        zig_handler: u64,
+        request_id: u64,
+        _peer_id: *const c_char,
+        _protocol_id: *const c_char,
+        _response_ptr: *const u8,
+        _response_len: usize,
+        _context_bytes: *const u8,
+    ) {
+        RPC_CALLBACKS
+            .lock()
+            .unwrap()
+            .push((zig_handler, request_id));
+    }
+
+    #[no_mangle]
+    extern "C" fn handleRPCEndOfStreamFromRustBridge(
+        zig_handler: u64,
+        request_id: u64,
+        _peer_id: *const c_char,
+        _protocol_id: *const c_char,
+    ) {
+        RPC_CALLBACKS
+            .lock()
+            .unwrap()
+            .push((zig_handler, request_id));
+    }
+
+    #[no_mangle]
+    extern "C" fn handleRPCErrorFromRustBridge(
+        zig_handler: u64,
+        request_id: u64,
+        _protocol_id: *const c_char,
+        _code: u32,
+        _message: *const c_char,
+    ) {
+        RPC_CALLBACKS
+            .lock()
+            .unwrap()
+            .push((zig_handler, request_id));
+    }
+
+    #[no_mangle]
+    extern "C" fn handleTopicMeshEventFromRustBridge(
+        _zig_handler: u64,
+        _topic: *const c_char,
+        _peer_id: *const c_char,
+        _event: u32,
+    ) {
+    }
+
+    #[no_mangle]
+    extern "C" fn handlePeerConnectedFromRustBridge(
+        _zig_handler: u64,
+        _peer_id: *const c_char,
+        _direction: u32,
+    ) {
+    }
+
+    /// Peer statuses reported to the test nodes, as (zig_handler, head_slot).
+    static PEER_STATUSES: std::sync::Mutex<Vec<(u64, u64)>> = std::sync::Mutex::new(Vec::new());
+
+    #[no_mangle]
+    extern "C" fn handlePeerStatusFromRustBridge(
+        zig_handler: u64,
+        _peer_id: *const c_char,
+        _finalized_root: *const u8,
+        _finalized_slot: u64,
+        _head_root: *const u8,
+        head_slot: u64,
+    ) {
+        PEER_STATUSES.lock().unwrap().push((zig_handler, head_slot));
+    }
+
+    /// Goodbye reasons received by the test nodes, as (zig_handler, reason).
+    static GOODBYES: std::sync::Mutex<Vec<(u64, u64)>> = std::sync::Mutex::new(Vec::new());
+
+    #[no_mangle]
+    extern "C" fn handlePeerGoodbyeFromRustBridge(
+        zig_handler: u64,
+        _peer_id: *const c_char,
+        reason: u64,
+    ) {
+        GOODBYES.lock().unwrap().push((zig_handler, reason));
+    }
+
+    #[no_mangle]
+    extern "C" fn handlePeerDisconnectedFromRustBridge(
+        _zig_handler: u64,
+        _peer_id: *const c_char,
+        _direction: u32,
+        _reason: u32,
+    ) {
+    }
+
+    #[no_mangle]
+    extern "C" fn handlePeerConnectionFailedFromRustBridge(
+        _zig_handler: u64,
+        _peer_id: *const c_char,
+        _direction: u32,
+        _result: u32,
+    ) {
+    }
+
+    /// Runs `create_and_run_network` for a test node on its own thread, like the Zig side does.
+    fn spawn_test_network(
+        network_id: u32,
+        listen_addresses: &str,
+        connect_addresses: &str,
+        discovery_port: u16,
+        bootnodes: &str,
+    ) -> std::thread::JoinHandle<()> {
+        spawn_test_network_with(
+            network_id,
+            listen_addresses,
+            connect_addresses,
+            discovery_port,
+            bootnodes,
+            "",
+            "",
+        )
+    }
+
+    /// Like `spawn_test_network`, subscribed to `topics` and with a JSON gossipsub config.
+    fn spawn_test_network_with(
+        network_id: u32,
+        listen_addresses: &str,
+        connect_addresses: &str,
+        discovery_port: u16,
+        bootnodes: &str,
+        topics: &str,
+        gossipsub_config: &str,
+    ) -> std::thread::JoinHandle<()> {
+        let listen_addresses = CString::new(listen_addresses).unwrap();
+        let connect_addresses = CString::new(connect_addresses).unwrap();
+        let bootnodes = CString::new(bootnodes).unwrap();
+        let topics = CString::new(topics).unwrap();
+        let gossipsub_config = CString::new(gossipsub_config).unwrap();
+        // Every test network is on the same fork
+        let req_resp_config = CString::new(r#"{"fork_digest": "0x01020304"}"#).unwrap();
+        std::thread::spawn(move || {
+            let private_key = CString::new(format!("{:064x}", network_id + 1)).unwrap();
+            unsafe {
+                create_and_run_network(
+                    network_id,
+                    network_id as u64,
+                    private_key.as_ptr(),
+                    listen_addresses.as_ptr(),
+                    connect_addresses.as_ptr(),
+                    topics.as_ptr(),
+                    std::ptr::null(),
+                    std::ptr::null(),
+                    gossipsub_config.as_ptr(),
+                    req_resp_config.as_ptr(),
+                    0,
+                    0,
+                    discovery_port,
+                    bootnodes.as_ptr(),
+                    std::ptr::null(),
+                    false,
+                )
+            };
+        })
+    }
+
+    /// Polls `condition` until it holds or `timeout` passes and returns its last result.
+    fn wait_until(timeout: Duration, condition: impl Fn() -> bool) -> bool {
+        let deadline = std::time::Instant::now() + timeout;
+        while !condition() && std::time::Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        condition()
+    }
+
+    /// Peers a test node is connected to, from its network info.
+    fn connected_peer_count(network_id: u32) -> usize {
+        let mut buf = vec![0u8; 16 * 1024];
+        let len = unsafe { get_network_info(network_id, buf.as_mut_ptr(), buf.len()) };
+        let info = serde_json::from_slice::<serde_json::Value>(&buf[..len]).unwrap();
+        info["peers"].as_array().unwrap().len()
+    }
+
     #[test]
     fn test_message_id_computation_with_snappy() {
         let compressed_data = {
@@ -1616,7 +3878,11 @@ mod tests {
         // Test that wait_for_network_ready times out when network is not initialized
         // Use network_id 99 which we won't initialize
         let result = unsafe { wait_for_network_ready(99, 100) }; // 100ms timeout
-        assert!(!result, "Should timeout when network is not initialized");
+        assert_eq!(
+            result,
+            Readiness::TimedOut as u32,
+            "Should timeout when network is not initialized"
+        );
     }
 
     #[test]
@@ -1641,4 +3907,591 @@ mod tests {
             "Should return 0 when network is not initialized"
         );
     }
+
+    #[test]
+    fn test_cancel_rpc_request_needs_running_network() {
+        assert!(!cancel_rpc_request(98, 1));
+    }
+
+    #[test]
+    fn test_invalid_start_params_fail_the_start() {
+        let network_id = 204;
+        for (listen_addresses, gossipsub_config) in [
+            ("not-a-multiaddr", ""),
+            ("/ip4/127.0.0.1/tcp/0", r#"{"mesh_n": 4, "mesh_n_low": 6}"#),
+        ] {
+            let handle = spawn_test_network_with(
+                network_id,
+                listen_addresses,
+                "",
+                0,
+                "",
+                "",
+                gossipsub_config,
+            );
+            assert_eq!(
+                unsafe { wait_for_network_ready(network_id, 5000) },
+                Readiness::Failed as u32
+            );
+            handle.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_stop_network_allows_restart() {
+        let network_id = 200;
+        for _ in 0..2 {
+            let handle = spawn_test_network(network_id, "/ip4/127.0.0.1/tcp/0", "", 0, "");
+            assert_eq!(
+                unsafe { wait_for_network_ready(network_id, 5000) },
+                Readiness::Ready as u32
+            );
+
+            assert!(unsafe { stop_network(network_id) });
+            handle.join().unwrap();
+            assert_eq!(
+                unsafe { wait_for_network_ready(network_id, 0) },
+                Readiness::TimedOut as u32
+            );
+        }
+        assert!(!unsafe { stop_network(network_id) });
+    }
+
+    #[test]
+    fn test_get_peer_score_for_unknown_peer() {
+        let network_id = 201;
+        let peer_id = CString::new(PeerId::random().to_string()).unwrap();
+        let mut score = 0.0;
+        assert!(!unsafe { get_peer_score(network_id, peer_id.as_ptr(), &mut score) });
+
+        let handle = spawn_test_network(network_id, "/ip4/127.0.0.1/tcp/0", "", 0, "");
+        assert_eq!(
+            unsafe { wait_for_network_ready(network_id, 5000) },
+            Readiness::Ready as u32
+        );
+        assert!(!unsafe { get_peer_score(network_id, peer_id.as_ptr(), &mut score) });
+
+        assert!(unsafe { stop_network(network_id) });
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_get_metrics_renders_openmetrics_text() {
+        let network_id = 203;
+        let mut buf = vec![0u8; 64 * 1024];
+        assert_eq!(
+            unsafe { get_metrics(network_id, buf.as_mut_ptr(), buf.len()) },
+            0
+        );
+
+        let handle = spawn_test_network(network_id, "/ip4/127.0.0.1/tcp/0", "", 0, "");
+        assert_eq!(
+            unsafe { wait_for_network_ready(network_id, 5000) },
+            Readiness::Ready as u32
+        );
+        let len = unsafe { get_metrics(network_id, buf.as_mut_ptr(), buf.len()) };
+        assert!(len > 0 && len <= buf.len());
+        let text = std::str::from_utf8(&buf[..len]).unwrap();
+        assert!(text.contains("libp2p_swarm_"));
+        assert!(text.contains("zeam_network_reconnect_attempts_total 0"));
+        assert!(text.ends_with("# EOF\n"));
+
+        assert!(unsafe { stop_network(network_id) });
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_ban_and_unban_peer() {
+        let network_id = 202;
+        let handle = spawn_test_network(network_id, "/ip4/127.0.0.1/tcp/0", "", 0, "");
+        assert_eq!(
+            unsafe { wait_for_network_ready(network_id, 5000) },
+            Readiness::Ready as u32
+        );
+
+        let peer_id = PeerId::random();
+        let peer_id_cstr = CString::new(peer_id.to_string()).unwrap();
+        let list = || {
+            let mut buf = vec![0u8; 4096];
+            let len = unsafe { list_peers(network_id, buf.as_mut_ptr(), buf.len()) };
+            assert!(len > 0 && len <= buf.len());
+            serde_json::from_slice::<serde_json::Value>(&buf[..len]).unwrap()
+        };
+
+        assert!(unsafe { ban_peer(network_id, peer_id_cstr.as_ptr(), 60) });
+        let peers = list();
+        assert_eq!(peers[0]["peer_id"], peer_id.to_string());
+        assert!(peers[0]["ban_remaining_secs"].as_u64().unwrap() > 0);
+
+        assert!(unsafe { unban_peer(network_id, peer_id_cstr.as_ptr()) });
+        assert!(list()
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|peer| peer["ban_remaining_secs"].is_null()));
+
+        assert!(unsafe { stop_network(network_id) });
+        handle.join().unwrap();
+    }
+
+    fn test_keypair(network_id: u32) -> Keypair {
+        keypair_from_hex(&format!("{:064x}", network_id + 1)).unwrap()
+    }
+
+    #[test]
+    fn test_generate_local_enr() {
+        let private_key = CString::new(format!("0x{:064x}", 7)).unwrap();
+        let listen_addresses =
+            CString::new("/ip4/0.0.0.0/tcp/9000,/ip4/0.0.0.0/udp/9001/quic-v1").unwrap();
+        let ip = CString::new("192.168.1.10").unwrap();
+
+        let enr_len = |buf: &mut [u8]| unsafe {
+            generate_local_enr(
+                0,
+                private_key.as_ptr(),
+                listen_addresses.as_ptr(),
+                ip.as_ptr(),
+                9002,
+                buf.as_mut_ptr(),
+                buf.len(),
+            )
+        };
+        let len = enr_len(&mut []);
+        assert!(len > 0);
+        let mut buf = vec![0u8; len];
+        assert_eq!(enr_len(&mut buf), len);
+
+        let enr = std::str::from_utf8(&buf)
+            .unwrap()
+            .parse::<discv5::Enr>()
+            .unwrap();
+        assert_eq!(
+            enr.peer_id(),
+            Some(
+                keypair_from_hex(&format!("{:064x}", 7))
+                    .unwrap()
+                    .public()
+                    .to_peer_id()
+            )
+        );
+        assert_eq!(enr.ip4(), Some("192.168.1.10".parse().unwrap()));
+        assert_eq!(enr.tcp4(), Some(9000));
+        assert_eq!(enr.quic4(), Some(9001));
+        assert_eq!(enr.udp4(), Some(9002));
+    }
+
+    #[test]
+    fn test_discovery_connects_peers_through_bootnode() {
+        let (bootnode_id, node_b_id, node_c_id) = (210, 211, 212);
+        let bootnode_key = test_keypair(bootnode_id);
+        let bootnode_enr = build_local_enr(
+            &enr_key_from_keypair(&bootnode_key).unwrap(),
+            &LocalEnrConfig {
+                ip4: Some("127.0.0.1".parse().unwrap()),
+                udp: Some(19310),
+                tcp: Some(19210),
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .to_base64();
+
+        let handles = [
+            spawn_test_network(bootnode_id, "/ip4/127.0.0.1/tcp/19210", "", 19310, ""),
+            spawn_test_network(
+                node_b_id,
+                "/ip4/127.0.0.1/tcp/19211",
+                "",
+                19311,
+                &bootnode_enr,
+            ),
+            spawn_test_network(
+                node_c_id,
+                "/ip4/127.0.0.1/tcp/19212",
+                "",
+                19312,
+                &bootnode_enr,
+            ),
+        ];
+        for network_id in [bootnode_id, node_b_id, node_c_id] {
+            assert_eq!(
+                unsafe { wait_for_network_ready(network_id, 5000) },
+                Readiness::Ready as u32
+            );
+        }
+
+        // B and C only know the bootnode, so they can only find each other through discovery.
+        let node_c_peer_id = test_keypair(node_c_id).public().to_peer_id().to_string();
+        let node_b_sees_c = || {
+            let mut buf = vec![0u8; 4096];
+            let len = unsafe { list_peers(node_b_id, buf.as_mut_ptr(), buf.len()) };
+            let peers = serde_json::from_slice::<serde_json::Value>(&buf[..len]).unwrap();
+            peers.as_array().unwrap().iter().any(|peer| {
+                peer["peer_id"] == node_c_peer_id && peer["connections"].as_u64() > Some(0)
+            })
+        };
+        let deadline = std::time::Instant::now() + Duration::from_secs(60);
+        while !node_b_sees_c() && std::time::Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(500));
+        }
+        let connected = node_b_sees_c();
+
+        for network_id in [bootnode_id, node_b_id, node_c_id] {
+            assert!(unsafe { stop_network(network_id) });
+        }
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert!(connected, "node B did not discover node C");
+    }
+
+    #[test]
+    fn test_get_network_info_reports_connected_peer() {
+        let (node_a_id, node_b_id) = (220, 221);
+        let node_a_peer_id = test_keypair(node_a_id).public().to_peer_id();
+        // Node B dials node A once, so node A has to listen first
+        let handle_a = spawn_test_network(node_a_id, "/ip4/127.0.0.1/tcp/19220", "", 0, "");
+        assert_eq!(
+            unsafe { wait_for_network_ready(node_a_id, 5000) },
+            Readiness::Ready as u32
+        );
+        let handle_b = spawn_test_network(
+            node_b_id,
+            "/ip4/127.0.0.1/tcp/19221",
+            &format!("/ip4/127.0.0.1/tcp/19220/p2p/{}", node_a_peer_id),
+            0,
+            "",
+        );
+        assert_eq!(
+            unsafe { wait_for_network_ready(node_b_id, 5000) },
+            Readiness::Ready as u32
+        );
+        let handles = [handle_a, handle_b];
+
+        let network_info = || {
+            // Identify and ping results can grow the document between calls
+            let mut buf = Vec::new();
+            loop {
+                let len = unsafe { get_network_info(node_b_id, buf.as_mut_ptr(), buf.len()) };
+                assert!(len > 0);
+                if len <= buf.len() {
+                    buf.truncate(len);
+                    return serde_json::from_slice::<serde_json::Value>(&buf).unwrap();
+                }
+                buf.resize(len, 0);
+            }
+        };
+        // Identify and ping run right after the connection is established.
+        let deadline = std::time::Instant::now() + Duration::from_secs(20);
+        let mut info = network_info();
+        while std::time::Instant::now() < deadline
+            && (info["peers"][0]["agent_version"].is_null()
+                || info["peers"][0]["ping_rtt_ms"].is_null())
+        {
+            std::thread::sleep(Duration::from_millis(200));
+            info = network_info();
+        }
+
+        for network_id in [node_a_id, node_b_id] {
+            assert!(unsafe { stop_network(network_id) });
+        }
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(
+            info["local_peer_id"],
+            test_keypair(node_b_id).public().to_peer_id().to_string()
+        );
+        assert_eq!(info["listen_addresses"][0], "/ip4/127.0.0.1/tcp/19221");
+        let peer = &info["peers"][0];
+        assert_eq!(peer["peer_id"], node_a_peer_id.to_string());
+        assert_eq!(peer["direction"], "outbound");
+        assert!(peer["addresses"][0]
+            .as_str()
+            .unwrap()
+            .starts_with("/ip4/127.0.0.1/tcp/19220"));
+        assert!(peer["agent_version"].is_string());
+        assert!(peer["ping_rtt_ms"].is_number());
+        assert!(peer["protocols"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|protocol| protocol == "/ipfs/id/1.0.0"));
+        assert!(info["mesh_peers"].is_object());
+    }
+
+    #[test]
+    fn test_disconnect_peer_says_goodbye() {
+        let (node_a_id, node_b_id) = (230, 231);
+        let node_a_peer_id = test_keypair(node_a_id).public().to_peer_id();
+        let handles = [
+            spawn_test_network(node_a_id, "/ip4/127.0.0.1/tcp/19230", "", 0, ""),
+            spawn_test_network(
+                node_b_id,
+                "/ip4/127.0.0.1/tcp/19231",
+                &format!("/ip4/127.0.0.1/tcp/19230/p2p/{}", node_a_peer_id),
+                0,
+                "",
+            ),
+        ];
+        for network_id in [node_a_id, node_b_id] {
+            assert_eq!(
+                unsafe { wait_for_network_ready(network_id, 5000) },
+                Readiness::Ready as u32
+            );
+        }
+
+        let timeout = Duration::from_secs(10);
+        assert!(wait_until(timeout, || connected_peer_count(node_b_id) == 1));
+
+        let peer_id_cstr = CString::new(node_a_peer_id.to_string()).unwrap();
+        let reason = GoodbyeReason::TooManyPeers as u64;
+        assert!(unsafe { disconnect_peer(node_b_id, peer_id_cstr.as_ptr(), reason) });
+        let received = wait_until(timeout, || {
+            GOODBYES
+                .lock()
+                .unwrap()
+                .contains(&(node_a_id as u64, reason))
+        });
+        // Node A is not redialed after the goodbye
+        let disconnected = wait_until(timeout, || connected_peer_count(node_b_id) == 0);
+        std::thread::sleep(Duration::from_secs(1));
+        let still_disconnected = connected_peer_count(node_b_id) == 0;
+
+        for network_id in [node_a_id, node_b_id] {
+            assert!(unsafe { stop_network(network_id) });
+        }
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert!(received, "node A did not get the goodbye");
+        assert!(disconnected && still_disconnected);
+    }
+
+    #[test]
+    fn test_cancelled_request_gets_no_callbacks() {
+        let (node_a_id, node_b_id) = (260, 261);
+        let node_a_peer_id = test_keypair(node_a_id).public().to_peer_id();
+        // Node A has to listen before node B dials it
+        let node_a = spawn_test_network(node_a_id, "/ip4/127.0.0.1/tcp/19260", "", 0, "");
+        assert_eq!(
+            unsafe { wait_for_network_ready(node_a_id, 5000) },
+            Readiness::Ready as u32
+        );
+        let node_b = spawn_test_network(
+            node_b_id,
+            "/ip4/127.0.0.1/tcp/19261",
+            &format!("/ip4/127.0.0.1/tcp/19260/p2p/{}", node_a_peer_id),
+            0,
+            "",
+        );
+        assert_eq!(
+            unsafe { wait_for_network_ready(node_b_id, 5000) },
+            Readiness::Ready as u32
+        );
+
+        let timeout = Duration::from_secs(10);
+        let connected = wait_until(timeout, || connected_peer_count(node_b_id) == 1);
+
+        // Node A never answers, the request stays in flight until it is cancelled
+        let peer_id_cstr = CString::new(node_a_peer_id.to_string()).unwrap();
+        // A blocks_by_root request container: the offset of its roots list and one root
+        let mut roots_request = 4u32.to_le_bytes().to_vec();
+        roots_request.extend_from_slice(&[0u8; 32]);
+        let request = encode_snappy_payload(&roots_request);
+        let request_id = unsafe {
+            send_rpc_request(
+                node_b_id,
+                peer_id_cstr.as_ptr(),
+                0, // blocks_by_root v1
+                request.as_ptr(),
+                request.len(),
+            )
+        };
+        let received = wait_until(timeout, || {
+            RPC_REQUESTS.lock().unwrap().contains(&(node_a_id as u64))
+        });
+        let cancelled = cancel_rpc_request(node_b_id, request_id);
+        // Past the time the request would have timed out with a 408 error
+        std::thread::sleep(REQUEST_TIMEOUT + Duration::from_secs(1));
+        let callbacks = RPC_CALLBACKS
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|callback| **callback == (node_b_id as u64, request_id))
+            .count();
+
+        for network_id in [node_a_id, node_b_id] {
+            assert!(unsafe { stop_network(network_id) });
+        }
+        node_a.join().unwrap();
+        node_b.join().unwrap();
+        assert!(connected && request_id != 0);
+        assert!(received, "node A did not get the request");
+        assert!(cancelled);
+        assert_eq!(callbacks, 0);
+    }
+
+    #[test]
+    fn test_status_handshake_starts_when_the_status_is_set() {
+        let (node_a_id, node_b_id) = (270, 271);
+        let node_a_peer_id = test_keypair(node_a_id).public().to_peer_id();
+        // Node A has to listen before node B dials it
+        let node_a = spawn_test_network(node_a_id, "/ip4/127.0.0.1/tcp/19270", "", 0, "");
+        assert_eq!(
+            unsafe { wait_for_network_ready(node_a_id, 5000) },
+            Readiness::Ready as u32
+        );
+        let node_b = spawn_test_network(
+            node_b_id,
+            "/ip4/127.0.0.1/tcp/19271",
+            &format!("/ip4/127.0.0.1/tcp/19270/p2p/{}", node_a_peer_id),
+            0,
+            "",
+        );
+        assert_eq!(
+            unsafe { wait_for_network_ready(node_b_id, 5000) },
+            Readiness::Ready as u32
+        );
+
+        let timeout = Duration::from_secs(10);
+        let connected = wait_until(timeout, || connected_peer_count(node_b_id) == 1);
+
+        // The nodes connected without a status, setting it starts the handshake
+        let root = [0u8; 32];
+        for (network_id, head_slot) in [(node_a_id, 10), (node_b_id, 11)] {
+            assert!(unsafe {
+                set_local_status(network_id, root.as_ptr(), 0, root.as_ptr(), head_slot)
+            });
+        }
+        let statuses_exchanged = wait_until(timeout, || {
+            let statuses = PEER_STATUSES.lock().unwrap();
+            statuses.contains(&(node_a_id as u64, 11)) && statuses.contains(&(node_b_id as u64, 10))
+        });
+
+        for network_id in [node_a_id, node_b_id] {
+            assert!(unsafe { stop_network(network_id) });
+        }
+        node_a.join().unwrap();
+        node_b.join().unwrap();
+        assert!(connected);
+        assert!(statuses_exchanged);
+    }
+
+    /// Sums the values of every series of a metric in the OpenMetrics text of a network.
+    fn metric_sum(network_id: u32, series_prefix: &str) -> u64 {
+        let mut buf = vec![0u8; 256 * 1024];
+        let len = unsafe { get_metrics(network_id, buf.as_mut_ptr(), buf.len()) };
+        std::str::from_utf8(&buf[..len])
+            .unwrap()
+            .lines()
+            .filter(|line| line.starts_with(series_prefix))
+            .filter_map(|line| line.rsplit(' ').next()?.parse::<u64>().ok())
+            .sum()
+    }
+
+    /// Publishes a 256 KiB message from the first of four fully connected nodes and returns the
+    /// duplicates of it received by all nodes and the bytes announced with IDONTWANT.
+    fn gossip_duplicates(first_id: u32, first_port: u16, idontwant_threshold: usize) -> (u64, u64) {
+        let topic = "idontwant_test";
+        let gossipsub_config =
+            format!(r#"{{"idontwant_message_size_threshold": {idontwant_threshold}}}"#);
+        let node_ids = (first_id..first_id + 4).collect::<Vec<_>>();
+        let address = |i: usize| {
+            format!(
+                "/ip4/127.0.0.1/tcp/{}/p2p/{}",
+                first_port + i as u16,
+                test_keypair(node_ids[i]).public().to_peer_id()
+            )
+        };
+        // Every node dials the ones before it once, so they have to listen first
+        let handles = node_ids
+            .iter()
+            .enumerate()
+            .map(|(i, &network_id)| {
+                let connect = (0..i).map(address).collect::<Vec<_>>().join(",");
+                let handle = spawn_test_network_with(
+                    network_id,
+                    &format!("/ip4/127.0.0.1/tcp/{}", first_port + i as u16),
+                    &connect,
+                    0,
+                    "",
+                    topic,
+                    &gossipsub_config,
+                );
+                assert_eq!(
+                    unsafe { wait_for_network_ready(network_id, 5000) },
+                    Readiness::Ready as u32
+                );
+                handle
+            })
+            .collect::<Vec<_>>();
+
+        let timeout = Duration::from_secs(20);
+        let mesh_size = |network_id: u32| {
+            let mut buf = vec![0u8; 16 * 1024];
+            let len = unsafe { get_network_info(network_id, buf.as_mut_ptr(), buf.len()) };
+            let info = serde_json::from_slice::<serde_json::Value>(&buf[..len]).unwrap();
+            info["mesh_peers"][topic].as_array().map_or(0, Vec::len)
+        };
+        let meshes_formed = wait_until(timeout, || node_ids.iter().all(|&id| mesh_size(id) == 3));
+
+        let topic_cstr = CString::new(topic).unwrap();
+        let message = (0..256 * 1024).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        unsafe {
+            publish_msg_to_rust_bridge(
+                node_ids[0],
+                topic_cstr.as_ptr(),
+                message.as_ptr(),
+                message.len(),
+            )
+        };
+        let received = wait_until(timeout, || {
+            node_ids[1..]
+                .iter()
+                .all(|&id| metric_sum(id, "gossipsub_topic_msg_recv_counts_total{") == 1)
+        });
+        // Give the receivers time to forward the message to each other
+        std::thread::sleep(Duration::from_secs(2));
+
+        let mut duplicates = 0;
+        let mut announced_bytes = 0;
+        for &network_id in &node_ids {
+            duplicates += metric_sum(
+                network_id,
+                "gossipsub_topic_msg_recv_counts_unfiltered_total{",
+            ) - metric_sum(network_id, "gossipsub_topic_msg_recv_counts_total{");
+            announced_bytes += metric_sum(
+                network_id,
+                "zeam_network_gossip_idontwant_announced_bytes_total{",
+            );
+        }
+
+        for &network_id in &node_ids {
+            assert!(unsafe { stop_network(network_id) });
+        }
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert!(meshes_formed, "the topic meshes did not form");
+        assert!(received, "the message did not reach every node");
+        (duplicates, announced_bytes)
+    }
+
+    #[test]
+    fn test_idontwant_reduces_duplicate_gossip() {
+        // Without IDONTWANT each receiver forwards the message to the two others, which already
+        // have it from the publisher.
+        let (duplicates, announced_bytes) = gossip_duplicates(240, 19240, 4 * 1024 * 1024);
+        assert!(duplicates > 0);
+        assert_eq!(announced_bytes, 0);
+
+        let (idontwant_duplicates, idontwant_announced_bytes) = gossip_duplicates(250, 19250, 1000);
+        assert!(
+            idontwant_duplicates < duplicates,
+            "{idontwant_duplicates} duplicates with IDONTWANT, {duplicates} without"
+        );
+        assert!(idontwant_announced_bytes >= 256 * 1024);
+    }
 }
@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use libp2p::metrics::{Metrics, Recorder};
+use prometheus_client::encoding::{text::encode, EncodeLabelSet, EncodeLabelValue};
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
+use prometheus_client::metrics::histogram::{exponential_buckets, Histogram};
+use prometheus_client::registry::Registry;
+
+/// Prefix of the metrics recorded by the glue itself, next to the `libp2p` and `gossipsub` ones.
+const METRICS_PREFIX: &str = "zeam_network";
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, EncodeLabelValue)]
+pub enum RequestDirection {
+    Inbound,
+    Outbound,
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, EncodeLabelValue)]
+pub enum RequestOutcome {
+    Success,
+    Error,
+    Timeout,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, EncodeLabelValue)]
+pub enum GossipDirection {
+    Published,
+    Received,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct RequestLabels {
+    protocol: String,
+    direction: RequestDirection,
+    outcome: RequestOutcome,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct ProtocolLabels {
+    protocol: String,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct TopicLabels {
+    topic: String,
+    direction: GossipDirection,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct IdontwantLabels {
+    topic: String,
+}
+
+/// Prometheus registry of a network: the libp2p swarm, gossipsub and identify metrics plus the
+/// req/resp, reconnect, gossip and IDONTWANT counters recorded by the event loop.
+pub struct NetworkMetrics {
+    registry: Registry,
+    libp2p: Metrics,
+    requests: Family<RequestLabels, Counter>,
+    response_latency: Family<ProtocolLabels, Histogram, fn() -> Histogram>,
+    reconnect_attempts: Counter,
+    gossip_bytes: Family<TopicLabels, Counter>,
+    idontwant_sent: Family<IdontwantLabels, Counter>,
+    idontwant_announced_bytes: Family<IdontwantLabels, Counter>,
+    // Start of every outbound request still waiting for its end of stream
+    outbound_requests: HashMap<u64, Instant>,
+}
+
+impl Default for NetworkMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NetworkMetrics {
+    pub fn new() -> Self {
+        let mut registry = Registry::default();
+        let libp2p = Metrics::new(&mut registry);
+
+        let requests = Family::<RequestLabels, Counter>::default();
+        let response_latency =
+            Family::<ProtocolLabels, Histogram, fn() -> Histogram>::new_with_constructor(|| {
+                // 5ms up to ~20s
+                Histogram::new(exponential_buckets(0.005, 2.0, 13))
+            });
+        let reconnect_attempts = Counter::default();
+        let gossip_bytes = Family::<TopicLabels, Counter>::default();
+        let idontwant_sent = Family::<IdontwantLabels, Counter>::default();
+        let idontwant_announced_bytes = Family::<IdontwantLabels, Counter>::default();
+
+        let sub_registry = registry.sub_registry_with_prefix(METRICS_PREFIX);
+        sub_registry.register(
+            "reqresp_requests",
+            "Req/resp requests by protocol, direction and outcome",
+            requests.clone(),
+        );
+        sub_registry.register(
+            "reqresp_response_latency_seconds",
+            "Time from sending a request until the end of its response stream",
+            response_latency.clone(),
+        );
+        sub_registry.register(
+            "reconnect_attempts",
+            "Dials made to reconnect to a disconnected peer",
+            reconnect_attempts.clone(),
+        );
+        sub_registry.register(
+            "gossip_bytes",
+            "Gossip payload bytes by topic, published or received",
+            gossip_bytes.clone(),
+        );
+        sub_registry.register(
+            "gossip_idontwant_sent",
+            "IDONTWANT announcements of received gossip messages sent to mesh peers, by topic",
+            idontwant_sent.clone(),
+        );
+        sub_registry.register(
+            "gossip_idontwant_announced_bytes",
+            "Gossip payload bytes announced to mesh peers with IDONTWANT, an upper bound of the bandwidth saved",
+            idontwant_announced_bytes.clone(),
+        );
+
+        Self {
+            registry,
+            libp2p,
+            requests,
+            response_latency,
+            reconnect_attempts,
+            gossip_bytes,
+            idontwant_sent,
+            idontwant_announced_bytes,
+            outbound_requests: HashMap::new(),
+        }
+    }
+
+    /// Registry to register the metrics of behaviours such as gossipsub in.
+    pub fn registry_mut(&mut self) -> &mut Registry {
+        &mut self.registry
+    }
+
+    /// Records a swarm or behaviour event with the libp2p metrics.
+    pub fn record<E>(&self, event: &E)
+    where
+        Metrics: Recorder<E>,
+    {
+        self.libp2p.record(event);
+    }
+
+    pub fn outbound_request_sent(&mut self, request_id: u64) {
+        self.outbound_requests.insert(request_id, Instant::now());
+    }
+
+    /// Counts a finished outbound request. The latency is only observed for successful ones.
+    pub fn outbound_request_finished(
+        &mut self,
+        request_id: u64,
+        protocol: &str,
+        outcome: RequestOutcome,
+    ) {
+        let started = self.outbound_requests.remove(&request_id);
+        if let (RequestOutcome::Success, Some(started)) = (outcome, started) {
+            self.response_latency
+                .get_or_create(&ProtocolLabels {
+                    protocol: protocol.to_string(),
+                })
+                .observe(started.elapsed().as_secs_f64());
+        }
+        self.count_request(protocol, RequestDirection::Outbound, outcome);
+    }
+
+    pub fn inbound_request_finished(&self, protocol: &str, outcome: RequestOutcome) {
+        self.count_request(protocol, RequestDirection::Inbound, outcome);
+    }
+
+    fn count_request(&self, protocol: &str, direction: RequestDirection, outcome: RequestOutcome) {
+        self.requests
+            .get_or_create(&RequestLabels {
+                protocol: protocol.to_string(),
+                direction,
+                outcome,
+            })
+            .inc();
+    }
+
+    pub fn reconnect_attempted(&self) {
+        self.reconnect_attempts.inc();
+    }
+
+    pub fn gossip_bytes(&self, topic: &str, direction: GossipDirection, bytes: usize) {
+        self.gossip_bytes
+            .get_or_create(&TopicLabels {
+                topic: topic.to_string(),
+                direction,
+            })
+            .inc_by(bytes as u64);
+    }
+
+    /// Counts the IDONTWANT sent to `peers` mesh peers for a received message of `bytes` bytes.
+    pub fn idontwant_sent(&self, topic: &str, peers: usize, bytes: usize) {
+        let labels = IdontwantLabels {
+            topic: topic.to_string(),
+        };
+        self.idontwant_sent
+            .get_or_create(&labels)
+            .inc_by(peers as u64);
+        self.idontwant_announced_bytes
+            .get_or_create(&labels)
+            .inc_by((peers * bytes) as u64);
+    }
+
+    /// Drops the start times of requests that will never finish.
+    pub fn clear(&mut self) {
+        self.outbound_requests.clear();
+    }
+
+    /// Renders all metrics in the OpenMetrics text format.
+    pub fn encode(&self) -> Result<String, std::fmt::Error> {
+        let mut buffer = String::new();
+        encode(&mut buffer, &self.registry)?;
+        Ok(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_and_gossip_metrics_are_encoded() {
+        let mut metrics = NetworkMetrics::new();
+        let protocol = "/leanconsensus/req/status/1/ssz_snappy";
+
+        metrics.outbound_request_sent(1);
+        metrics.outbound_request_finished(1, protocol, RequestOutcome::Success);
+        metrics.outbound_request_sent(2);
+        metrics.outbound_request_finished(2, protocol, RequestOutcome::Timeout);
+        metrics.inbound_request_finished(protocol, RequestOutcome::Error);
+        metrics.reconnect_attempted();
+        metrics.gossip_bytes(
+            "/leanconsensus/devnet0/block/ssz_snappy",
+            GossipDirection::Received,
+            42,
+        );
+        metrics.idontwant_sent("/leanconsensus/devnet0/block/ssz_snappy", 3, 1000);
+
+        let text = metrics.encode().unwrap();
+        assert!(text.contains(&format!(
+            "zeam_network_reqresp_requests_total{{protocol=\"{protocol}\",direction=\"Outbound\",outcome=\"Success\"}} 1"
+        )));
+        assert!(text.contains(&format!(
+            "zeam_network_reqresp_requests_total{{protocol=\"{protocol}\",direction=\"Outbound\",outcome=\"Timeout\"}} 1"
+        )));
+        assert!(text.contains(&format!(
+            "zeam_network_reqresp_response_latency_seconds_count{{protocol=\"{protocol}\"}} 1"
+        )));
+        assert!(text.contains("zeam_network_reconnect_attempts_total 1"));
+        assert!(text.contains(
+            "zeam_network_gossip_bytes_total{topic=\"/leanconsensus/devnet0/block/ssz_snappy\",direction=\"Received\"} 42"
+        ));
+        assert!(text.contains(
+            "zeam_network_gossip_idontwant_announced_bytes_total{topic=\"/leanconsensus/devnet0/block/ssz_snappy\"} 3000"
+        ));
+        assert!(text.ends_with("# EOF\n"));
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use libp2p::swarm::ConnectionId;
+use libp2p::{Multiaddr, PeerId};
+use serde::Serialize;
+
+use crate::peer_manager::ConnectionDirection;
+
+/// Response of the `get_network_info` FFI.
+#[derive(Debug, Serialize)]
+pub struct NetworkInfo {
+    pub local_peer_id: String,
+    pub listen_addresses: Vec<String>,
+    pub peers: Vec<ConnectedPeerInfo>,
+    /// Mesh peers of every subscribed topic, keyed by topic.
+    pub mesh_peers: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConnectedPeerInfo {
+    pub peer_id: String,
+    pub direction: Option<ConnectionDirection>,
+    pub addresses: Vec<String>,
+    pub agent_version: Option<String>,
+    pub ping_rtt_ms: Option<f64>,
+    pub protocols: Vec<String>,
+}
+
+/// What the network learned about a connected peer from its connections, identify and ping.
+#[derive(Debug, Default)]
+pub struct PeerMetadata {
+    addresses: HashMap<ConnectionId, Multiaddr>,
+    agent_version: Option<String>,
+    protocols: Vec<String>,
+    ping_rtt: Option<Duration>,
+}
+
+/// Metadata of all connected peers. A peer is forgotten once its last connection closes.
+#[derive(Debug, Default)]
+pub struct PeerMetadataStore {
+    peers: HashMap<PeerId, PeerMetadata>,
+}
+
+impl PeerMetadataStore {
+    pub fn connection_established(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        remote_address: Multiaddr,
+    ) {
+        self.peers
+            .entry(peer_id)
+            .or_default()
+            .addresses
+            .insert(connection_id, remote_address);
+    }
+
+    pub fn connection_closed(&mut self, peer_id: &PeerId, connection_id: &ConnectionId) {
+        if let Some(peer) = self.peers.get_mut(peer_id) {
+            peer.addresses.remove(connection_id);
+            if peer.addresses.is_empty() {
+                self.peers.remove(peer_id);
+            }
+        }
+    }
+
+    pub fn on_identify(&mut self, peer_id: &PeerId, agent_version: String, protocols: Vec<String>) {
+        if let Some(peer) = self.peers.get_mut(peer_id) {
+            peer.agent_version = Some(agent_version);
+            peer.protocols = protocols;
+        }
+    }
+
+    pub fn on_ping(&mut self, peer_id: &PeerId, rtt: Duration) {
+        if let Some(peer) = self.peers.get_mut(peer_id) {
+            peer.ping_rtt = Some(rtt);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.peers.clear();
+    }
+
+    /// Describes every connected peer, taking the direction from `direction_of`.
+    pub fn connected_peers(
+        &self,
+        direction_of: impl Fn(&PeerId) -> Option<ConnectionDirection>,
+    ) -> Vec<ConnectedPeerInfo> {
+        let mut peers = self
+            .peers
+            .iter()
+            .map(|(peer_id, peer)| {
+                let mut addresses = peer
+                    .addresses
+                    .iter()
+                    .map(|(connection_id, addr)| (*connection_id, addr.to_string()))
+                    .collect::<Vec<_>>();
+                addresses.sort();
+                ConnectedPeerInfo {
+                    peer_id: peer_id.to_string(),
+                    direction: direction_of(peer_id),
+                    addresses: addresses.into_iter().map(|(_, addr)| addr).collect(),
+                    agent_version: peer.agent_version.clone(),
+                    ping_rtt_ms: peer.ping_rtt.map(|rtt| rtt.as_secs_f64() * 1000.0),
+                    protocols: peer.protocols.clone(),
+                }
+            })
+            .collect::<Vec<_>>();
+        peers.sort_by(|a, b| a.peer_id.cmp(&b.peer_id));
+        peers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peer_metadata_follows_connections() {
+        let mut store = PeerMetadataStore::default();
+        let peer_id = PeerId::random();
+        let first = ConnectionId::new_unchecked(1);
+        let second = ConnectionId::new_unchecked(2);
+
+        store.connection_established(peer_id, first, "/ip4/10.0.0.1/tcp/9000".parse().unwrap());
+        store.connection_established(peer_id, second, "/ip4/10.0.0.1/tcp/9001".parse().unwrap());
+        store.on_identify(
+            &peer_id,
+            "zeam/0.1.0".to_string(),
+            vec!["/meshsub/1.1.0".to_string()],
+        );
+        store.on_ping(&peer_id, Duration::from_millis(12));
+        // Events of unknown peers are ignored.
+        store.on_ping(&PeerId::random(), Duration::from_millis(1));
+
+        let peers = store.connected_peers(|_| Some(ConnectionDirection::Outbound));
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].peer_id, peer_id.to_string());
+        assert_eq!(
+            peers[0].addresses,
+            vec!["/ip4/10.0.0.1/tcp/9000", "/ip4/10.0.0.1/tcp/9001"]
+        );
+        assert_eq!(peers[0].agent_version.as_deref(), Some("zeam/0.1.0"));
+        assert_eq!(peers[0].ping_rtt_ms, Some(12.0));
+
+        store.connection_closed(&peer_id, &first);
+        assert_eq!(store.connected_peers(|_| None)[0].addresses.len(), 1);
+        store.connection_closed(&peer_id, &second);
+        assert!(store.connected_peers(|_| None).is_empty());
+    }
+}
@@ -0,0 +1,357 @@
+pub mod peerdb;
+
+pub use peerdb::{ConnectionDirection, PeerAction, PeerDB};
+
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use delay_map::HashMapDelay;
+use futures::StreamExt;
+use libp2p::{
+    core::{transport::PortUse, Endpoint},
+    swarm::{
+        behaviour::ConnectionEstablished, dummy, ConnectionClosed, ConnectionDenied, ConnectionId,
+        FromSwarm, NetworkBehaviour, THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+    },
+    Multiaddr, PeerId,
+};
+use serde::Serialize;
+use tracing::debug;
+
+use crate::req_resp::{error::ReqRespError, ResponseCode};
+
+/// Number of peers the node tries to keep. Connected peers above this count are pruned.
+pub const DEFAULT_TARGET_PEERS: usize = 50;
+
+/// Hard limit of connected peers. Further connections are denied, except from trusted peers.
+pub const DEFAULT_MAX_PEERS: usize = 60;
+
+/// Ban duration used when a peer crosses the ban threshold or no duration is given.
+pub const DEFAULT_BAN_DURATION: Duration = Duration::from_secs(30 * 60);
+
+/// Interval at which reputations decay and excess peers are pruned.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy)]
+pub struct PeerManagerConfig {
+    pub target_peers: usize,
+    pub max_peers: usize,
+}
+
+impl Default for PeerManagerConfig {
+    fn default() -> Self {
+        Self {
+            target_peers: DEFAULT_TARGET_PEERS,
+            max_peers: DEFAULT_MAX_PEERS,
+        }
+    }
+}
+
+impl PeerManagerConfig {
+    /// Builds a config from the values passed over FFI, where 0 selects the default.
+    pub fn from_limits(target_peers: u32, max_peers: u32) -> Self {
+        let defaults = Self::default();
+        let target_peers = match target_peers {
+            0 => defaults.target_peers,
+            n => n as usize,
+        };
+        let max_peers = match max_peers {
+            0 => defaults.max_peers.max(target_peers),
+            n => (n as usize).max(target_peers),
+        };
+        Self {
+            target_peers,
+            max_peers,
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PeerManagerError {
+    #[error("peer is banned")]
+    Banned,
+    #[error("peer limit of {0} reached")]
+    PeerLimitReached(usize),
+}
+
+/// Banned and pruned peers are not disconnected here: the network says goodbye to them first and
+/// then closes their connections.
+#[derive(Debug)]
+pub enum PeerManagerEvent {
+    Banned {
+        peer_id: PeerId,
+        duration: Duration,
+    },
+    Unbanned {
+        peer_id: PeerId,
+    },
+    /// To be disconnected to get back to the target peer count.
+    Pruned {
+        peer_id: PeerId,
+    },
+}
+
+/// Entry of the `list_peers` FFI response.
+#[derive(Debug, Serialize)]
+pub struct PeerSummary {
+    pub peer_id: String,
+    pub reputation: f64,
+    pub connections: usize,
+    pub direction: Option<ConnectionDirection>,
+    pub trusted: bool,
+    pub ban_remaining_secs: Option<u64>,
+}
+
+/// Network behaviour enforcing the peer limits and tracking peer reputation.
+///
+/// The peer manager does not open any streams itself. It denies connections from banned peers
+/// and beyond `max_peers`, disconnects the worst peers above `target_peers` and bans peers
+/// whose reputation falls below the ban threshold. Bans expire on their own.
+pub struct PeerManager {
+    config: PeerManagerConfig,
+    peers: PeerDB,
+    ban_expiries: HashMapDelay<PeerId, ()>,
+    heartbeat: tokio::time::Interval,
+    events: VecDeque<ToSwarm<PeerManagerEvent, Infallible>>,
+}
+
+impl PeerManager {
+    pub fn new(config: PeerManagerConfig) -> Self {
+        let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+        heartbeat.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        Self {
+            config,
+            peers: PeerDB::new(Instant::now()),
+            ban_expiries: HashMapDelay::new(DEFAULT_BAN_DURATION),
+            heartbeat,
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Marks a static peer: it is exempt from the peer limits and never pruned.
+    pub fn add_trusted_peer(&mut self, peer_id: PeerId) {
+        self.peers.set_trusted(peer_id);
+    }
+
+    pub fn is_banned(&self, peer_id: &PeerId) -> bool {
+        self.peers.is_banned(peer_id, Instant::now())
+    }
+
+    pub fn connected_peers(&self) -> usize {
+        self.peers.connected_peers()
+    }
+
+    /// True while fewer than `target_peers` peers are connected.
+    pub fn needs_peers(&self) -> bool {
+        self.peers.connected_peers() < self.config.target_peers
+    }
+
+    /// Direction of the oldest connection to the peer, None if it is not connected.
+    pub fn direction(&self, peer_id: &PeerId) -> Option<ConnectionDirection> {
+        self.peers.get(peer_id).and_then(|peer| peer.direction())
+    }
+
+    /// Number of connections to open to reach `target_peers`.
+    pub fn peers_wanted(&self) -> usize {
+        self.config
+            .target_peers
+            .saturating_sub(self.peers.connected_peers())
+    }
+
+    /// Lowers the reputation of a peer and bans it once it crosses the ban threshold.
+    pub fn report_peer(&mut self, peer_id: PeerId, action: PeerAction) {
+        debug!("PEER_MANAGER: reporting {peer_id} for {action:?}");
+        if self.peers.report(peer_id, action, Instant::now()) {
+            self.ban_peer(peer_id, DEFAULT_BAN_DURATION);
+        }
+    }
+
+    /// Bans a peer for `duration`. The network disconnects it on the `Banned` event.
+    pub fn ban_peer(&mut self, peer_id: PeerId, duration: Duration) {
+        self.peers.ban(peer_id, Instant::now() + duration);
+        self.ban_expiries.insert_at(peer_id, (), duration);
+        self.events
+            .push_back(ToSwarm::GenerateEvent(PeerManagerEvent::Banned {
+                peer_id,
+                duration,
+            }));
+    }
+
+    /// Lifts a ban and resets the peer's reputation. Returns false if the peer was not banned.
+    pub fn unban_peer(&mut self, peer_id: &PeerId) -> bool {
+        self.ban_expiries.remove(peer_id);
+        if !self.peers.unban(peer_id, true) {
+            return false;
+        }
+        self.events
+            .push_back(ToSwarm::GenerateEvent(PeerManagerEvent::Unbanned {
+                peer_id: *peer_id,
+            }));
+        true
+    }
+
+    pub fn peer_summaries(&self) -> Vec<PeerSummary> {
+        let now = Instant::now();
+        self.peers
+            .iter()
+            .map(|(peer_id, peer)| PeerSummary {
+                peer_id: peer_id.to_string(),
+                reputation: peer.reputation,
+                connections: peer.connections.len(),
+                direction: peer.direction(),
+                trusted: peer.trusted,
+                ban_remaining_secs: peer
+                    .banned_until
+                    .filter(|until| *until > now)
+                    .map(|until| (until - now).as_secs()),
+            })
+            .collect()
+    }
+
+    fn check_connection(&self, peer_id: &PeerId) -> Result<(), ConnectionDenied> {
+        if self.is_banned(peer_id) {
+            return Err(ConnectionDenied::new(PeerManagerError::Banned));
+        }
+        if !self.peers.is_connected(peer_id)
+            && !self.peers.is_trusted(peer_id)
+            && self.peers.connected_peers() >= self.config.max_peers
+        {
+            return Err(ConnectionDenied::new(PeerManagerError::PeerLimitReached(
+                self.config.max_peers,
+            )));
+        }
+        Ok(())
+    }
+
+    fn on_heartbeat(&mut self) {
+        self.peers.heartbeat(Instant::now());
+
+        for peer_id in self.peers.peers_to_prune(self.config.target_peers) {
+            self.events
+                .push_back(ToSwarm::GenerateEvent(PeerManagerEvent::Pruned { peer_id }));
+        }
+    }
+}
+
+/// Maps a failed req/resp exchange to the penalty of the remote peer, if it is to blame.
+pub fn reqresp_error_action(err: &ReqRespError) -> Option<PeerAction> {
+    match err {
+        ReqRespError::InvalidData(_) => Some(PeerAction::LowTolerance),
+        ReqRespError::IncompleteStream => Some(PeerAction::MidTolerance),
+        ReqRespError::StreamTimedOut => Some(PeerAction::HighTolerance),
+        ReqRespError::RawError(_) => Some(PeerAction::HighTolerance),
+        // A well-formed error answer is not held against the peer, a reserved code is
+        ReqRespError::ErrorResponse { code, .. } => match ResponseCode::from_u8(*code) {
+            Some(_) => None,
+            None => Some(PeerAction::LowTolerance),
+        },
+        // Outbound requests only fail with `InvalidRequest` when our own request is out of bounds
+        ReqRespError::IoError(_) | ReqRespError::Disconnected | ReqRespError::InvalidRequest(_) => {
+            None
+        }
+    }
+}
+
+impl NetworkBehaviour for PeerManager {
+    type ConnectionHandler = dummy::ConnectionHandler;
+
+    type ToSwarm = PeerManagerEvent;
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        _connection_id: ConnectionId,
+        peer: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.check_connection(&peer)?;
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        _connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        _addresses: &[Multiaddr],
+        _effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        if maybe_peer.is_some_and(|peer_id| self.is_banned(&peer_id)) {
+            return Err(ConnectionDenied::new(PeerManagerError::Banned));
+        }
+        Ok(Vec::new())
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        _connection_id: ConnectionId,
+        peer: PeerId,
+        _addr: &Multiaddr,
+        _role_override: Endpoint,
+        _port_use: PortUse,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.check_connection(&peer)?;
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        match event {
+            FromSwarm::ConnectionEstablished(ConnectionEstablished {
+                peer_id,
+                connection_id,
+                endpoint,
+                ..
+            }) => {
+                let direction = if endpoint.is_dialer() {
+                    ConnectionDirection::Outbound
+                } else {
+                    ConnectionDirection::Inbound
+                };
+                self.peers
+                    .connection_established(peer_id, connection_id, direction);
+            }
+            FromSwarm::ConnectionClosed(ConnectionClosed {
+                peer_id,
+                connection_id,
+                ..
+            }) => {
+                self.peers.connection_closed(&peer_id, &connection_id);
+            }
+            _ => {}
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        _peer_id: PeerId,
+        _connection_id: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        match event {}
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        while self.heartbeat.poll_tick(cx).is_ready() {
+            self.on_heartbeat();
+        }
+
+        while let Poll::Ready(Some(Ok((peer_id, ())))) = self.ban_expiries.poll_next_unpin(cx) {
+            if self.peers.unban(&peer_id, false) {
+                self.events
+                    .push_back(ToSwarm::GenerateEvent(PeerManagerEvent::Unbanned {
+                        peer_id,
+                    }));
+            }
+        }
+
+        if let Some(event) = self.events.pop_front() {
+            return Poll::Ready(event);
+        }
+
+        Poll::Pending
+    }
+}
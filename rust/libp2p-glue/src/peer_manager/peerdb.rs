@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use libp2p::swarm::ConnectionId;
+use libp2p::PeerId;
+use serde::Serialize;
+
+/// Lowest and highest reputation a peer can reach.
+pub const MIN_REPUTATION: f64 = -100.0;
+pub const MAX_REPUTATION: f64 = 100.0;
+
+/// Peers at or below this reputation are banned.
+pub const BAN_THRESHOLD: f64 = -50.0;
+
+/// Reputation decays towards zero with this half-life, so old offences are forgotten.
+pub const REPUTATION_HALF_LIFE: Duration = Duration::from_secs(10 * 60);
+
+/// Reputations closer to zero than this are treated as neutral when pruning the database.
+const NEUTRAL_REPUTATION_EPSILON: f64 = 0.5;
+
+/// How badly a peer misbehaved. The tolerance is the number of times the same offence is
+/// accepted before the peer gets banned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerAction {
+    /// Immediately ban the peer.
+    Fatal,
+    /// About 5 occurrences before a ban.
+    LowTolerance,
+    /// About 10 occurrences before a ban.
+    MidTolerance,
+    /// About 50 occurrences before a ban.
+    HighTolerance,
+}
+
+impl PeerAction {
+    fn penalty(self) -> f64 {
+        match self {
+            PeerAction::Fatal => MAX_REPUTATION - MIN_REPUTATION,
+            PeerAction::LowTolerance => 10.0,
+            PeerAction::MidTolerance => 5.0,
+            PeerAction::HighTolerance => 1.0,
+        }
+    }
+}
+
+/// Direction of a connection, using the same encoding as the Zig peer events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionDirection {
+    Inbound = 0,
+    Outbound = 1,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PeerInfo {
+    pub reputation: f64,
+    pub connections: HashMap<ConnectionId, ConnectionDirection>,
+    pub banned_until: Option<Instant>,
+    /// Static peers are never pruned to make room for others.
+    pub trusted: bool,
+}
+
+impl PeerInfo {
+    pub fn is_connected(&self) -> bool {
+        !self.connections.is_empty()
+    }
+
+    /// Direction of the oldest known connection.
+    pub fn direction(&self) -> Option<ConnectionDirection> {
+        self.connections
+            .iter()
+            .min_by_key(|(connection_id, _)| **connection_id)
+            .map(|(_, direction)| *direction)
+    }
+
+    fn is_neutral(&self) -> bool {
+        !self.is_connected()
+            && !self.trusted
+            && self.banned_until.is_none()
+            && self.reputation.abs() < NEUTRAL_REPUTATION_EPSILON
+    }
+}
+
+/// Bookkeeping of every peer the peer manager knows about: connections, reputation and bans.
+///
+/// All time-dependent methods take the current instant so the logic can be tested without
+/// waiting on a real clock.
+#[derive(Debug)]
+pub struct PeerDB {
+    peers: HashMap<PeerId, PeerInfo>,
+    last_decay: Instant,
+}
+
+impl PeerDB {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            peers: HashMap::new(),
+            last_decay: now,
+        }
+    }
+
+    pub fn get(&self, peer_id: &PeerId) -> Option<&PeerInfo> {
+        self.peers.get(peer_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&PeerId, &PeerInfo)> {
+        self.peers.iter()
+    }
+
+    pub fn set_trusted(&mut self, peer_id: PeerId) {
+        self.peers.entry(peer_id).or_default().trusted = true;
+    }
+
+    pub fn is_trusted(&self, peer_id: &PeerId) -> bool {
+        self.peers.get(peer_id).is_some_and(|peer| peer.trusted)
+    }
+
+    pub fn is_connected(&self, peer_id: &PeerId) -> bool {
+        self.peers.get(peer_id).is_some_and(PeerInfo::is_connected)
+    }
+
+    pub fn connected_peers(&self) -> usize {
+        self.peers
+            .values()
+            .filter(|peer| peer.is_connected())
+            .count()
+    }
+
+    pub fn is_banned(&self, peer_id: &PeerId, now: Instant) -> bool {
+        self.peers
+            .get(peer_id)
+            .and_then(|peer| peer.banned_until)
+            .is_some_and(|until| until > now)
+    }
+
+    pub fn connection_established(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        direction: ConnectionDirection,
+    ) {
+        self.peers
+            .entry(peer_id)
+            .or_default()
+            .connections
+            .insert(connection_id, direction);
+    }
+
+    pub fn connection_closed(&mut self, peer_id: &PeerId, connection_id: &ConnectionId) {
+        if let Some(peer) = self.peers.get_mut(peer_id) {
+            peer.connections.remove(connection_id);
+        }
+    }
+
+    /// Applies the penalty of `action` to the peer. Returns true if the peer crossed the ban
+    /// threshold and is not banned yet.
+    pub fn report(&mut self, peer_id: PeerId, action: PeerAction, now: Instant) -> bool {
+        let already_banned = self.is_banned(&peer_id, now);
+        let peer = self.peers.entry(peer_id).or_default();
+        peer.reputation = (peer.reputation - action.penalty()).max(MIN_REPUTATION);
+        !already_banned && peer.reputation <= BAN_THRESHOLD
+    }
+
+    pub fn ban(&mut self, peer_id: PeerId, until: Instant) {
+        self.peers.entry(peer_id).or_default().banned_until = Some(until);
+    }
+
+    /// Lifts a ban. A manual unban also forgives the peer's past behaviour, otherwise the next
+    /// small offence would ban it again straight away. Returns false if the peer was not banned.
+    pub fn unban(&mut self, peer_id: &PeerId, reset_reputation: bool) -> bool {
+        let Some(peer) = self.peers.get_mut(peer_id) else {
+            return false;
+        };
+        if peer.banned_until.take().is_none() {
+            return false;
+        }
+        if reset_reputation {
+            peer.reputation = 0.0;
+        }
+        true
+    }
+
+    /// Decays every reputation towards zero and forgets peers that are neither connected,
+    /// trusted, banned nor carry a meaningful reputation anymore.
+    pub fn heartbeat(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_decay);
+        self.last_decay = now;
+
+        let factor = 0.5_f64.powf(elapsed.as_secs_f64() / REPUTATION_HALF_LIFE.as_secs_f64());
+        for peer in self.peers.values_mut() {
+            peer.reputation *= factor;
+        }
+        self.peers.retain(|_, peer| !peer.is_neutral());
+    }
+
+    /// Picks the connected peers to drop so that at most `target` peers remain. Trusted peers
+    /// are kept; inbound peers go before outbound ones, and within each group the lowest
+    /// reputation goes first.
+    pub fn peers_to_prune(&self, target: usize) -> Vec<PeerId> {
+        let connected = self.connected_peers();
+        if connected <= target {
+            return Vec::new();
+        }
+
+        let mut candidates = self
+            .peers
+            .iter()
+            .filter(|(_, peer)| peer.is_connected() && !peer.trusted)
+            .map(|(peer_id, peer)| {
+                let outbound = peer.direction() == Some(ConnectionDirection::Outbound);
+                (*peer_id, peer.reputation, outbound)
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_by(|a, b| a.2.cmp(&b.2).then(a.1.total_cmp(&b.1)));
+
+        candidates
+            .into_iter()
+            .take(connected - target)
+            .map(|(peer_id, _, _)| peer_id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_offences_cross_ban_threshold() {
+        let now = Instant::now();
+        let mut db = PeerDB::new(now);
+        let peer_id = PeerId::random();
+
+        for _ in 0..4 {
+            assert!(!db.report(peer_id, PeerAction::LowTolerance, now));
+        }
+        assert!(db.report(peer_id, PeerAction::LowTolerance, now));
+
+        db.ban(peer_id, now + Duration::from_secs(60));
+        assert!(db.is_banned(&peer_id, now));
+        assert!(!db.is_banned(&peer_id, now + Duration::from_secs(61)));
+        // Further offences while banned do not request another ban.
+        assert!(!db.report(peer_id, PeerAction::Fatal, now));
+
+        assert!(db.unban(&peer_id, true));
+        assert!(!db.unban(&peer_id, true));
+        assert_eq!(db.get(&peer_id).unwrap().reputation, 0.0);
+    }
+
+    #[test]
+    fn test_reputation_decays_and_neutral_peers_are_forgotten() {
+        let now = Instant::now();
+        let mut db = PeerDB::new(now);
+        let peer_id = PeerId::random();
+        db.report(peer_id, PeerAction::MidTolerance, now);
+
+        db.heartbeat(now + REPUTATION_HALF_LIFE);
+        assert!((db.get(&peer_id).unwrap().reputation + 2.5).abs() < 1e-9);
+
+        db.heartbeat(now + REPUTATION_HALF_LIFE * 10);
+        assert!(db.get(&peer_id).is_none());
+    }
+
+    #[test]
+    fn test_prune_prefers_low_reputation_inbound_untrusted_peers() {
+        let now = Instant::now();
+        let mut db = PeerDB::new(now);
+        let trusted = PeerId::random();
+        let inbound = PeerId::random();
+        let outbound_bad = PeerId::random();
+        let outbound_good = PeerId::random();
+
+        db.set_trusted(trusted);
+        db.connection_established(
+            trusted,
+            ConnectionId::new_unchecked(0),
+            ConnectionDirection::Inbound,
+        );
+        db.connection_established(
+            inbound,
+            ConnectionId::new_unchecked(1),
+            ConnectionDirection::Inbound,
+        );
+        db.connection_established(
+            outbound_bad,
+            ConnectionId::new_unchecked(2),
+            ConnectionDirection::Outbound,
+        );
+        db.connection_established(
+            outbound_good,
+            ConnectionId::new_unchecked(3),
+            ConnectionDirection::Outbound,
+        );
+        db.report(trusted, PeerAction::Fatal, now);
+        db.report(outbound_bad, PeerAction::MidTolerance, now);
+
+        assert!(db.peers_to_prune(4).is_empty());
+        assert_eq!(db.peers_to_prune(2), vec![inbound, outbound_bad]);
+        assert_eq!(db.peers_to_prune(0).len(), 3);
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use libp2p::gossipsub::{self, PeerScoreParams, PeerScoreThresholds, TopicScoreParams};
+use serde::Deserialize;
+
+/// Gossipsub peer scoring configuration supplied by Zig as JSON at network start.
+///
+/// Every field is optional; missing fields fall back to the defaults below. Topic parameters are
+/// configured per topic kind and applied to every subscribed topic of that kind, e.g. all
+/// `attestation_<subnet>` topics share the `attestation` parameters.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PeerScoreConfig {
+    pub enabled: bool,
+    pub topics: TopicsScoreConfig,
+    pub thresholds: ThresholdsConfig,
+    pub topic_score_cap: f64,
+    pub app_specific_weight: f64,
+    pub ip_colocation_factor_weight: f64,
+    pub ip_colocation_factor_threshold: f64,
+    pub behaviour_penalty_weight: f64,
+    pub behaviour_penalty_threshold: f64,
+    pub behaviour_penalty_decay: f64,
+    pub decay_interval_ms: u64,
+    pub decay_to_zero: f64,
+    pub retain_score_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TopicsScoreConfig {
+    pub block: TopicScoreConfig,
+    pub attestation: TopicScoreConfig,
+    pub aggregation: TopicScoreConfig,
+}
+
+/// Mirrors `gossipsub::TopicScoreParams` with durations expressed in milliseconds.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TopicScoreConfig {
+    pub topic_weight: f64,
+    pub time_in_mesh_weight: f64,
+    pub time_in_mesh_quantum_ms: u64,
+    pub time_in_mesh_cap: f64,
+    pub first_message_deliveries_weight: f64,
+    pub first_message_deliveries_decay: f64,
+    pub first_message_deliveries_cap: f64,
+    pub mesh_message_deliveries_weight: f64,
+    pub mesh_message_deliveries_decay: f64,
+    pub mesh_message_deliveries_cap: f64,
+    pub mesh_message_deliveries_threshold: f64,
+    pub mesh_message_deliveries_window_ms: u64,
+    pub mesh_message_deliveries_activation_ms: u64,
+    pub mesh_failure_penalty_weight: f64,
+    pub mesh_failure_penalty_decay: f64,
+    pub invalid_message_deliveries_weight: f64,
+    pub invalid_message_deliveries_decay: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThresholdsConfig {
+    pub gossip_threshold: f64,
+    pub publish_threshold: f64,
+    pub graylist_threshold: f64,
+    pub accept_px_threshold: f64,
+    pub opportunistic_graft_threshold: f64,
+}
+
+impl Default for PeerScoreConfig {
+    fn default() -> Self {
+        let params = PeerScoreParams::default();
+        Self {
+            enabled: true,
+            topics: TopicsScoreConfig::default(),
+            thresholds: ThresholdsConfig::default(),
+            topic_score_cap: params.topic_score_cap,
+            app_specific_weight: params.app_specific_weight,
+            ip_colocation_factor_weight: params.ip_colocation_factor_weight,
+            ip_colocation_factor_threshold: params.ip_colocation_factor_threshold,
+            behaviour_penalty_weight: params.behaviour_penalty_weight,
+            behaviour_penalty_threshold: params.behaviour_penalty_threshold,
+            behaviour_penalty_decay: params.behaviour_penalty_decay,
+            decay_interval_ms: params.decay_interval.as_millis() as u64,
+            decay_to_zero: params.decay_to_zero,
+            retain_score_ms: params.retain_score.as_millis() as u64,
+        }
+    }
+}
+
+impl Default for TopicScoreConfig {
+    fn default() -> Self {
+        let params = TopicScoreParams::default();
+        Self {
+            topic_weight: params.topic_weight,
+            time_in_mesh_weight: params.time_in_mesh_weight,
+            time_in_mesh_quantum_ms: params.time_in_mesh_quantum.as_millis() as u64,
+            time_in_mesh_cap: params.time_in_mesh_cap,
+            first_message_deliveries_weight: params.first_message_deliveries_weight,
+            first_message_deliveries_decay: params.first_message_deliveries_decay,
+            first_message_deliveries_cap: params.first_message_deliveries_cap,
+            // Mesh delivery penalties stay off by default: devnet topics (especially attestation
+            // subnets) are too quiet for delivery-rate expectations and would prune honest peers.
+            mesh_message_deliveries_weight: 0.0,
+            mesh_message_deliveries_decay: params.mesh_message_deliveries_decay,
+            mesh_message_deliveries_cap: params.mesh_message_deliveries_cap,
+            mesh_message_deliveries_threshold: params.mesh_message_deliveries_threshold,
+            mesh_message_deliveries_window_ms: params.mesh_message_deliveries_window.as_millis()
+                as u64,
+            mesh_message_deliveries_activation_ms: params
+                .mesh_message_deliveries_activation
+                .as_millis() as u64,
+            mesh_failure_penalty_weight: 0.0,
+            mesh_failure_penalty_decay: params.mesh_failure_penalty_decay,
+            invalid_message_deliveries_weight: params.invalid_message_deliveries_weight,
+            invalid_message_deliveries_decay: params.invalid_message_deliveries_decay,
+        }
+    }
+}
+
+impl Default for ThresholdsConfig {
+    fn default() -> Self {
+        let thresholds = PeerScoreThresholds::default();
+        Self {
+            gossip_threshold: thresholds.gossip_threshold,
+            publish_threshold: thresholds.publish_threshold,
+            graylist_threshold: thresholds.graylist_threshold,
+            accept_px_threshold: thresholds.accept_px_threshold,
+            opportunistic_graft_threshold: thresholds.opportunistic_graft_threshold,
+        }
+    }
+}
+
+impl TopicScoreConfig {
+    pub fn to_params(&self) -> TopicScoreParams {
+        TopicScoreParams {
+            topic_weight: self.topic_weight,
+            time_in_mesh_weight: self.time_in_mesh_weight,
+            time_in_mesh_quantum: Duration::from_millis(self.time_in_mesh_quantum_ms),
+            time_in_mesh_cap: self.time_in_mesh_cap,
+            first_message_deliveries_weight: self.first_message_deliveries_weight,
+            first_message_deliveries_decay: self.first_message_deliveries_decay,
+            first_message_deliveries_cap: self.first_message_deliveries_cap,
+            mesh_message_deliveries_weight: self.mesh_message_deliveries_weight,
+            mesh_message_deliveries_decay: self.mesh_message_deliveries_decay,
+            mesh_message_deliveries_cap: self.mesh_message_deliveries_cap,
+            mesh_message_deliveries_threshold: self.mesh_message_deliveries_threshold,
+            mesh_message_deliveries_window: Duration::from_millis(
+                self.mesh_message_deliveries_window_ms,
+            ),
+            mesh_message_deliveries_activation: Duration::from_millis(
+                self.mesh_message_deliveries_activation_ms,
+            ),
+            mesh_failure_penalty_weight: self.mesh_failure_penalty_weight,
+            mesh_failure_penalty_decay: self.mesh_failure_penalty_decay,
+            invalid_message_deliveries_weight: self.invalid_message_deliveries_weight,
+            invalid_message_deliveries_decay: self.invalid_message_deliveries_decay,
+        }
+    }
+}
+
+impl PeerScoreConfig {
+    /// Parses the JSON supplied by Zig. An empty string selects the defaults.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        if json.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        serde_json::from_str(json).map_err(|e| format!("invalid peer score config: {e}"))
+    }
+
+    /// Returns the score parameters for `topic`, based on its kind, or `None` for topics that are
+    /// not part of the lean gossip domain.
+    pub fn topic_params(&self, topic: &str) -> Option<TopicScoreParams> {
+        let config = match topic_kind(topic)? {
+            "block" => &self.topics.block,
+            "attestation" => &self.topics.attestation,
+            "aggregation" => &self.topics.aggregation,
+            _ => return None,
+        };
+        Some(config.to_params())
+    }
+
+    /// Builds and validates the gossipsub scoring parameters for the given subscribed topics.
+    pub fn to_params(
+        &self,
+        topics: &[String],
+    ) -> Result<(PeerScoreParams, PeerScoreThresholds), String> {
+        let topic_params = topics
+            .iter()
+            .filter_map(|topic| {
+                self.topic_params(topic)
+                    .map(|params| (gossipsub::IdentTopic::new(topic.clone()).hash(), params))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let params = PeerScoreParams {
+            topics: topic_params,
+            topic_score_cap: self.topic_score_cap,
+            app_specific_weight: self.app_specific_weight,
+            ip_colocation_factor_weight: self.ip_colocation_factor_weight,
+            ip_colocation_factor_threshold: self.ip_colocation_factor_threshold,
+            behaviour_penalty_weight: self.behaviour_penalty_weight,
+            behaviour_penalty_threshold: self.behaviour_penalty_threshold,
+            behaviour_penalty_decay: self.behaviour_penalty_decay,
+            decay_interval: Duration::from_millis(self.decay_interval_ms),
+            decay_to_zero: self.decay_to_zero,
+            retain_score: Duration::from_millis(self.retain_score_ms),
+            ..PeerScoreParams::default()
+        };
+        params.validate()?;
+
+        let thresholds = PeerScoreThresholds {
+            gossip_threshold: self.thresholds.gossip_threshold,
+            publish_threshold: self.thresholds.publish_threshold,
+            graylist_threshold: self.thresholds.graylist_threshold,
+            accept_px_threshold: self.thresholds.accept_px_threshold,
+            opportunistic_graft_threshold: self.thresholds.opportunistic_graft_threshold,
+        };
+        thresholds.validate()?;
+
+        Ok((params, thresholds))
+    }
+}
+
+/// Extracts the topic kind from a lean topic string `/leanconsensus/<network>/<name>/<encoding>`,
+/// folding `attestation_<subnet>` names into `attestation`.
+fn topic_kind(topic: &str) -> Option<&str> {
+    let name = topic.split('/').nth(3)?;
+    if name.starts_with("attestation_") {
+        return Some("attestation");
+    }
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK_TOPIC: &str = "/leanconsensus/devnet0/block/ssz_snappy";
+    const ATTESTATION_TOPIC: &str = "/leanconsensus/devnet0/attestation_3/ssz_snappy";
+
+    #[test]
+    fn test_empty_json_uses_defaults() {
+        let config = PeerScoreConfig::from_json("").unwrap();
+        assert!(config.enabled);
+
+        let (params, _) = config
+            .to_params(&[BLOCK_TOPIC.to_string(), ATTESTATION_TOPIC.to_string()])
+            .unwrap();
+        assert_eq!(params.topics.len(), 2);
+    }
+
+    #[test]
+    fn test_partial_json_overrides_single_topic_kind() {
+        let config = PeerScoreConfig::from_json(
+            r#"{"topics": {"attestation": {"topic_weight": 0.25}}, "thresholds": {"gossip_threshold": -100.0}}"#,
+        )
+        .unwrap();
+
+        let attestation = config.topic_params(ATTESTATION_TOPIC).unwrap();
+        let block = config.topic_params(BLOCK_TOPIC).unwrap();
+        assert_eq!(attestation.topic_weight, 0.25);
+        assert_eq!(block.topic_weight, TopicScoreParams::default().topic_weight);
+        assert_eq!(config.thresholds.gossip_threshold, -100.0);
+        assert!(config.topic_params("/meshsub/other").is_none());
+    }
+
+    #[test]
+    fn test_invalid_config_is_rejected() {
+        assert!(PeerScoreConfig::from_json(r#"{"unknown_field": 1}"#).is_err());
+
+        let config = PeerScoreConfig::from_json(
+            r#"{"topics": {"block": {"invalid_message_deliveries_weight": 1.0}}}"#,
+        )
+        .unwrap();
+        assert!(config.to_params(&[BLOCK_TOPIC.to_string()]).is_err());
+    }
+}
@@ -0,0 +1,297 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use libp2p::core::multiaddr::{Multiaddr, Protocol};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+
+/// Peers not seen for this long are dropped from the store.
+pub const PEER_STORE_MAX_AGE: Duration = Duration::from_secs(3 * 24 * 60 * 60);
+
+/// Upper bound on the stored peers, the least recently seen ones are dropped first.
+const MAX_STORED_PEERS: usize = 256;
+
+/// Dialable addresses kept per peer, the most recently dialed first.
+const MAX_ADDRESSES_PER_PEER: usize = 4;
+
+/// Current unix time in seconds, the unit of `last_seen`.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// A peer as written to the peer store file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct StoredPeer {
+    peer_id: String,
+    addresses: Vec<String>,
+    agent_version: Option<String>,
+    protocols: Vec<String>,
+    last_seen: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PeerStoreFile {
+    peers: Vec<StoredPeer>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct PeerRecord {
+    addresses: Vec<Multiaddr>,
+    agent_version: Option<String>,
+    protocols: Vec<String>,
+    last_seen: u64,
+}
+
+/// Peers the node successfully dialed with their addresses and identify info, persisted as
+/// JSON so a restarted node can dial them again next to its static peers. Without a path the
+/// store only lives in memory.
+#[derive(Debug)]
+pub struct PeerStore {
+    path: Option<PathBuf>,
+    max_age: Duration,
+    peers: HashMap<PeerId, PeerRecord>,
+    // Whether the store changed since it was last saved
+    dirty: bool,
+}
+
+impl PeerStore {
+    pub fn new(path: Option<PathBuf>, max_age: Duration) -> Self {
+        Self {
+            path,
+            max_age,
+            peers: HashMap::new(),
+            dirty: false,
+        }
+    }
+
+    pub fn in_memory() -> Self {
+        Self::new(None, PEER_STORE_MAX_AGE)
+    }
+
+    /// Reads the peers of the store file that were seen within the max age and returns how many
+    /// were loaded. A missing file is an empty store, entries that do not parse are skipped.
+    pub fn load(&mut self, now: u64) -> Result<usize, String> {
+        let Some(path) = &self.path else {
+            return Ok(0);
+        };
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(format!("failed to read {}: {e}", path.display())),
+        };
+        let file: PeerStoreFile = serde_json::from_str(&contents)
+            .map_err(|e| format!("invalid peer store {}: {e}", path.display()))?;
+
+        for peer in file.peers {
+            let Ok(peer_id) = peer.peer_id.parse::<PeerId>() else {
+                continue;
+            };
+            let addresses = peer
+                .addresses
+                .iter()
+                .filter_map(|addr| addr.parse::<Multiaddr>().ok())
+                .collect::<Vec<_>>();
+            if addresses.is_empty() {
+                continue;
+            }
+            self.peers.insert(
+                peer_id,
+                PeerRecord {
+                    addresses,
+                    agent_version: peer.agent_version,
+                    protocols: peer.protocols,
+                    last_seen: peer.last_seen,
+                },
+            );
+        }
+        self.prune(now);
+        Ok(self.peers.len())
+    }
+
+    /// Records an address the peer was successfully dialed on.
+    pub fn on_dialed(&mut self, peer_id: PeerId, mut address: Multiaddr, now: u64) {
+        if let Some(Protocol::P2p(_)) = address.iter().last() {
+            address.pop();
+        }
+        let record = self.peers.entry(peer_id).or_default();
+        record.addresses.retain(|known| *known != address);
+        record.addresses.insert(0, address);
+        record.addresses.truncate(MAX_ADDRESSES_PER_PEER);
+        record.last_seen = now;
+        self.dirty = true;
+    }
+
+    /// Records the identify info of a stored peer. Peers that were never dialed are ignored,
+    /// there is no address to dial them on.
+    pub fn on_identify(&mut self, peer_id: &PeerId, agent_version: String, protocols: Vec<String>) {
+        if let Some(record) = self.peers.get_mut(peer_id) {
+            record.agent_version = Some(agent_version);
+            record.protocols = protocols;
+            self.dirty = true;
+        }
+    }
+
+    /// Marks a stored peer as seen, while it is connected or when it disconnects.
+    pub fn seen(&mut self, peer_id: &PeerId, now: u64) {
+        if let Some(record) = self.peers.get_mut(peer_id) {
+            record.last_seen = now;
+            self.dirty = true;
+        }
+    }
+
+    pub fn remove(&mut self, peer_id: &PeerId) {
+        if self.peers.remove(peer_id).is_some() {
+            self.dirty = true;
+        }
+    }
+
+    /// Drops the peers not seen within the max age, then the least recently seen ones above
+    /// `MAX_STORED_PEERS`.
+    fn prune(&mut self, now: u64) {
+        let max_age = self.max_age.as_secs();
+        let before = self.peers.len();
+        self.peers
+            .retain(|_, record| now.saturating_sub(record.last_seen) <= max_age);
+        if self.peers.len() > MAX_STORED_PEERS {
+            let mut by_last_seen = self
+                .peers
+                .iter()
+                .map(|(peer_id, record)| (record.last_seen, *peer_id))
+                .collect::<Vec<_>>();
+            by_last_seen.sort_unstable_by_key(|(last_seen, _)| Reverse(*last_seen));
+            for (_, peer_id) in by_last_seen.split_off(MAX_STORED_PEERS) {
+                self.peers.remove(&peer_id);
+            }
+        }
+        if self.peers.len() != before {
+            self.dirty = true;
+        }
+    }
+
+    /// Stored peers and their addresses to dial, the most recently seen first.
+    pub fn dial_candidates(&self) -> Vec<(PeerId, Vec<Multiaddr>)> {
+        let mut peers = self.peers.iter().collect::<Vec<_>>();
+        peers.sort_by_key(|(_, record)| Reverse(record.last_seen));
+        peers
+            .into_iter()
+            .map(|(peer_id, record)| (*peer_id, record.addresses.clone()))
+            .collect()
+    }
+
+    /// Writes the store to its file if it changed. The file is replaced through a rename so a
+    /// crash while saving leaves the previous version in place.
+    pub fn save(&mut self, now: u64) -> Result<(), String> {
+        self.prune(now);
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let mut peers = self
+            .peers
+            .iter()
+            .map(|(peer_id, record)| StoredPeer {
+                peer_id: peer_id.to_string(),
+                addresses: record
+                    .addresses
+                    .iter()
+                    .map(|addr| addr.to_string())
+                    .collect(),
+                agent_version: record.agent_version.clone(),
+                protocols: record.protocols.clone(),
+                last_seen: record.last_seen,
+            })
+            .collect::<Vec<_>>();
+        peers.sort_by_key(|peer| Reverse(peer.last_seen));
+        let json = serde_json::to_string_pretty(&PeerStoreFile { peers })
+            .map_err(|e| format!("failed to encode the peer store: {e}"))?;
+
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("failed to create {}: {e}", dir.display()))?;
+        }
+        let mut tmp_path = path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        fs::write(&tmp_path, json)
+            .and_then(|()| fs::rename(&tmp_path, path))
+            .map_err(|e| format!("failed to write {}: {e}", path.display()))?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peers_survive_a_restart() {
+        let path = std::env::temp_dir()
+            .join(format!("zeam-peer-store-{}", std::process::id()))
+            .join("peers.json");
+        let peer_id = PeerId::random();
+        let now = 1_000_000;
+
+        let mut store = PeerStore::new(Some(path.clone()), PEER_STORE_MAX_AGE);
+        assert_eq!(store.load(now), Ok(0));
+        let address: Multiaddr = "/ip4/10.0.0.1/tcp/9000".parse().unwrap();
+        store.on_dialed(peer_id, address.clone().with(Protocol::P2p(peer_id)), now);
+        store.on_identify(
+            &peer_id,
+            "zeam/0.1.0".to_string(),
+            vec!["/meshsub/1.2.0".to_string()],
+        );
+        // Inbound peers were never dialed and are not stored
+        store.on_identify(&PeerId::random(), "other".to_string(), Vec::new());
+        store.save(now).unwrap();
+
+        let mut restarted = PeerStore::new(Some(path.clone()), PEER_STORE_MAX_AGE);
+        assert_eq!(restarted.load(now + 60), Ok(1));
+        assert_eq!(restarted.dial_candidates(), vec![(peer_id, vec![address])]);
+        assert_eq!(
+            restarted.peers[&peer_id].agent_version.as_deref(),
+            Some("zeam/0.1.0")
+        );
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_old_peers_age_out() {
+        let mut store = PeerStore::new(None, Duration::from_secs(100));
+        let (old, recent) = (PeerId::random(), PeerId::random());
+        let address: Multiaddr = "/ip4/10.0.0.1/tcp/9000".parse().unwrap();
+
+        store.on_dialed(old, address.clone(), 1_000);
+        store.on_dialed(recent, address.clone(), 1_050);
+        store.prune(1_120);
+        assert_eq!(
+            store.dial_candidates(),
+            vec![(recent, vec![address.clone()])]
+        );
+
+        // Seeing a peer again keeps it
+        store.seen(&recent, 1_200);
+        store.prune(1_250);
+        assert_eq!(store.dial_candidates().len(), 1);
+
+        // Above the cap the least recently seen peers go first
+        let mut store = PeerStore::new(None, PEER_STORE_MAX_AGE);
+        let oldest = PeerId::random();
+        store.on_dialed(oldest, address.clone(), 1_000);
+        for i in 1..=MAX_STORED_PEERS as u64 {
+            store.on_dialed(PeerId::random(), address.clone(), 1_000 + i);
+        }
+        store.prune(2_000);
+        assert_eq!(store.peers.len(), MAX_STORED_PEERS);
+        assert!(!store.peers.contains_key(&oldest));
+    }
+}
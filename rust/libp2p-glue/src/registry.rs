@@ -0,0 +1,289 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::NetworkCommand;
+
+/// Per-network entry kept in the registry.
+///
+/// The swarm itself is owned by the network's event loop; the registry only keeps what the FFI
+/// entry points need to reach it: the Zig handler used for callbacks/logging and the sender side
+/// of the event loop's command channel. The sender is only published once the network is ready.
+struct NetworkEntry {
+    zig_handler: u64,
+    commands: Option<UnboundedSender<NetworkCommand>>,
+}
+
+#[derive(Default)]
+struct Networks {
+    entries: HashMap<u32, NetworkEntry>,
+    // Networks whose last start failed, until a waiter is told or they are registered again
+    failed: HashSet<u32>,
+}
+
+/// Outcome of waiting for a network to start, passed over FFI as its discriminant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum Readiness {
+    Ready = 0,
+    TimedOut = 1,
+    /// The network will not become ready, the reason was logged through its Zig handler.
+    Failed = 2,
+}
+
+/// Thread-safe registry of running networks keyed by `network_id`.
+///
+/// Any number of networks can be registered. Removing an entry drops the command sender, which
+/// makes the owning event loop exit and release its swarm together with all request, response
+/// and reconnect state.
+pub(crate) struct NetworkRegistry {
+    networks: Mutex<Networks>,
+    changed: Condvar,
+}
+
+lazy_static::lazy_static! {
+    pub(crate) static ref NETWORKS: NetworkRegistry = NetworkRegistry::new();
+}
+
+impl NetworkRegistry {
+    fn new() -> Self {
+        Self {
+            networks: Mutex::new(Networks::default()),
+            changed: Condvar::new(),
+        }
+    }
+
+    /// Registers a new network. Returns false if the network id is already in use.
+    pub fn register(&self, network_id: u32, zig_handler: u64) -> bool {
+        let mut networks = self.networks.lock().unwrap();
+        if networks.entries.contains_key(&network_id) {
+            return false;
+        }
+        networks.failed.remove(&network_id);
+        networks.entries.insert(
+            network_id,
+            NetworkEntry {
+                zig_handler,
+                commands: None,
+            },
+        );
+        true
+    }
+
+    /// Publishes the command sender of a network and wakes up everyone waiting for it.
+    pub fn mark_ready(&self, network_id: u32, commands: UnboundedSender<NetworkCommand>) {
+        let mut networks = self.networks.lock().unwrap();
+        if let Some(entry) = networks.entries.get_mut(&network_id) {
+            entry.commands = Some(commands);
+        }
+        self.changed.notify_all();
+    }
+
+    /// Records that a registered network failed to start and wakes up everyone waiting for it.
+    /// The entry stays registered until the network's thread removes it.
+    pub fn mark_failed(&self, network_id: u32) {
+        self.networks.lock().unwrap().failed.insert(network_id);
+        self.changed.notify_all();
+    }
+
+    /// Hands `command` to the event loop and withdraws the command sender, so no further commands
+    /// are accepted while the network shuts down. Returns false if the network is not running.
+    pub fn request_stop(&self, network_id: u32, command: NetworkCommand) -> bool {
+        let mut networks = self.networks.lock().unwrap();
+        match networks
+            .entries
+            .get_mut(&network_id)
+            .and_then(|entry| entry.commands.take())
+        {
+            Some(commands) => commands.send(command).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Blocks until the network has been removed from the registry.
+    pub fn wait_until_removed(&self, network_id: u32) {
+        let mut networks = self.networks.lock().unwrap();
+        while networks.entries.contains_key(&network_id) {
+            networks = self.changed.wait(networks).unwrap();
+        }
+    }
+
+    /// Removes a network from the registry. Returns false if it was not registered.
+    pub fn remove(&self, network_id: u32) -> bool {
+        let removed = self
+            .networks
+            .lock()
+            .unwrap()
+            .entries
+            .remove(&network_id)
+            .is_some();
+        self.changed.notify_all();
+        removed
+    }
+
+    pub fn zig_handler(&self, network_id: u32) -> Option<u64> {
+        self.networks
+            .lock()
+            .unwrap()
+            .entries
+            .get(&network_id)
+            .map(|entry| entry.zig_handler)
+    }
+
+    /// Forwards a command to the event loop of the given network.
+    ///
+    /// Returns the command back if the network is unknown, not ready yet or already shutting down.
+    pub fn send(&self, network_id: u32, command: NetworkCommand) -> Result<(), NetworkCommand> {
+        let networks = self.networks.lock().unwrap();
+        match networks
+            .entries
+            .get(&network_id)
+            .and_then(|entry| entry.commands.as_ref())
+        {
+            Some(commands) => commands.send(command).map_err(|err| err.0),
+            None => Err(command),
+        }
+    }
+
+    /// Blocks until the network is ready, failed to start or the timeout elapses. A failed start
+    /// is only reported to the first waiter.
+    pub fn wait_until_ready(&self, network_id: u32, timeout: Duration) -> Readiness {
+        let deadline = Instant::now() + timeout;
+
+        let mut networks = self.networks.lock().unwrap();
+        loop {
+            if networks
+                .entries
+                .get(&network_id)
+                .is_some_and(|entry| entry.commands.is_some())
+            {
+                return Readiness::Ready;
+            }
+            if networks.failed.remove(&network_id) {
+                return Readiness::Failed;
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Readiness::TimedOut;
+            }
+
+            let (guard, timeout_result) =
+                self.changed.wait_timeout(networks, deadline - now).unwrap();
+            networks = guard;
+
+            if timeout_result.timed_out() {
+                return Readiness::TimedOut;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    #[test]
+    fn test_registry_supports_many_networks() {
+        let registry = NetworkRegistry::new();
+        let mut receivers = Vec::new();
+
+        for network_id in 0..32 {
+            assert!(registry.register(network_id, 1000 + network_id as u64));
+            let (tx, rx) = unbounded_channel();
+            registry.mark_ready(network_id, tx);
+            receivers.push(rx);
+        }
+
+        for network_id in 0..32 {
+            assert_eq!(
+                registry.wait_until_ready(network_id, Duration::from_millis(10)),
+                Readiness::Ready
+            );
+            assert_eq!(
+                registry.zig_handler(network_id),
+                Some(1000 + network_id as u64)
+            );
+        }
+        assert!(!registry.register(7, 1));
+    }
+
+    #[test]
+    fn test_registry_removes_single_network() {
+        let registry = NetworkRegistry::new();
+        let (tx0, _rx0) = unbounded_channel();
+        let (tx1, mut rx1) = unbounded_channel();
+        registry.register(0, 10);
+        registry.register(1, 11);
+        registry.mark_ready(0, tx0);
+        registry.mark_ready(1, tx1);
+
+        assert!(registry.remove(1));
+        assert!(!registry.remove(1));
+        assert_eq!(
+            registry.wait_until_ready(1, Duration::ZERO),
+            Readiness::TimedOut
+        );
+        assert_eq!(
+            registry.wait_until_ready(0, Duration::ZERO),
+            Readiness::Ready
+        );
+        assert_eq!(registry.zig_handler(1), None);
+
+        // Dropping the sender closes the event loop's command channel.
+        assert!(rx1.blocking_recv().is_none());
+    }
+
+    #[test]
+    fn test_registry_request_stop_withdraws_sender() {
+        let registry = NetworkRegistry::new();
+        let (tx, mut rx) = unbounded_channel();
+        registry.register(3, 13);
+        registry.mark_ready(3, tx);
+
+        assert!(registry.request_stop(3, NetworkCommand::Shutdown));
+        assert!(matches!(rx.blocking_recv(), Some(NetworkCommand::Shutdown)));
+        assert!(rx.blocking_recv().is_none());
+
+        // The entry stays registered until the event loop has exited.
+        assert!(!registry.request_stop(3, NetworkCommand::Shutdown));
+        assert_eq!(
+            registry.wait_until_ready(3, Duration::ZERO),
+            Readiness::TimedOut
+        );
+        assert_eq!(registry.zig_handler(3), Some(13));
+
+        registry.remove(3);
+        registry.wait_until_removed(3);
+    }
+
+    #[test]
+    fn test_registry_reports_failed_start_once() {
+        let registry = NetworkRegistry::new();
+        registry.register(4, 14);
+        registry.mark_failed(4);
+        registry.remove(4);
+
+        assert_eq!(
+            registry.wait_until_ready(4, Duration::from_secs(5)),
+            Readiness::Failed
+        );
+        assert_eq!(
+            registry.wait_until_ready(4, Duration::ZERO),
+            Readiness::TimedOut
+        );
+
+        // Registering the network again forgets the failure of the previous start
+        registry.register(4, 14);
+        registry.mark_failed(4);
+        registry.remove(4);
+        registry.register(4, 14);
+        assert_eq!(
+            registry.wait_until_ready(4, Duration::ZERO),
+            Readiness::TimedOut
+        );
+    }
+}
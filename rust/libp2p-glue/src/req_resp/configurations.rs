@@ -5,6 +5,12 @@ use std::time::Duration;
 /// Maximum allowed size for a single RPC payload (compressed).
 pub const MAX_MESSAGE_SIZE: usize = 4 * 1024 * 1024; // 4 MiB
 
+/// Maximum number of blocks requested by a single blocks_by_root or blocks_by_range request.
+pub const MAX_REQUEST_BLOCKS: u64 = 1024;
+
+/// Maximum length of the `ErrorMessage` carried by an error response chunk.
+pub const MAX_ERROR_MESSAGE_SIZE: usize = 256;
+
 /// Timeout applied to reading requests and responses from a substream.
 pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
 
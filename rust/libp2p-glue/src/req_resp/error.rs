@@ -8,6 +8,12 @@ pub enum ReqRespError {
     IoError(String),
     #[error("Invalid data: {0}")]
     InvalidData(String),
+    /// The request is outside the bounds of its protocol.
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+    /// Error chunk of the peer, with the text of its `ErrorMessage`.
+    #[error("Error response (code {code}): {message}")]
+    ErrorResponse { code: u8, message: String },
     #[error("Incomplete stream")]
     IncompleteStream,
     #[error("Stream timed out")]
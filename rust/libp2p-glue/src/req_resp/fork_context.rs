@@ -0,0 +1,34 @@
+/// Length of the context bytes that precede the payload of a success chunk.
+pub const CONTEXT_BYTES_LEN: usize = 4;
+
+pub type ForkDigest = [u8; CONTEXT_BYTES_LEN];
+
+/// Fork digests written and accepted as context bytes by the protocols that carry them, passed
+/// from Zig with the req/resp config. Without them such protocols can neither send nor accept
+/// success chunks.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ForkContext {
+    current: Option<ForkDigest>,
+    known: Vec<ForkDigest>,
+}
+
+impl ForkContext {
+    /// `current` is the digest of the active fork, `previous` the digests of earlier forks whose
+    /// blocks may still be served.
+    pub fn new(current: ForkDigest, previous: Vec<ForkDigest>) -> Self {
+        let mut known = previous;
+        known.push(current);
+        Self {
+            current: Some(current),
+            known,
+        }
+    }
+
+    pub fn current_digest(&self) -> Option<ForkDigest> {
+        self.current
+    }
+
+    pub fn is_known(&self, digest: &ForkDigest) -> bool {
+        self.known.contains(digest)
+    }
+}
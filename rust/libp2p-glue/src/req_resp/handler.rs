@@ -1,6 +1,6 @@
 /// The code originally comes from Ream https://github.com/ReamLabs/ream/blob/5a4b3cb42d5646a0d12ec1825ace03645dbfd59b/crates/networking/p2p/src/req_resp/handler.rs
 /// as we still need rust-libp2p until we fully migrate to zig-libp2p. It needs the custom RPC protocol implementation.
-use std::collections::{hash_map::Entry, HashMap, VecDeque};
+use std::collections::{hash_map::Entry, HashMap, HashSet, VecDeque};
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
@@ -91,6 +91,9 @@ pub struct ReqRespConnectionHandler {
     outbound_streams: HashMap<u64, OutboundStream>,
     outbound_stream_timeouts: HashSetDelay<u64>,
     pending_outbound_streams: Vec<OutboundOpenInfo>,
+    // Requests whose substream is being opened, and those of them cancelled in the meantime
+    opening_requests: HashSet<u64>,
+    cancelled_requests: HashSet<u64>,
     connection_state: ConnectionState,
 }
 
@@ -99,6 +102,8 @@ impl ReqRespConnectionHandler {
         Self {
             listen_protocol,
             pending_outbound_streams: vec![],
+            opening_requests: HashSet::new(),
+            cancelled_requests: HashSet::new(),
             behaviour_events: vec![],
             inbound_stream_id: 0,
             outbound_stream_id: 0,
@@ -139,6 +144,12 @@ impl ReqRespConnectionHandler {
     ) {
         let OutboundOpenInfo { request_id, .. } = info;
 
+        self.opening_requests.remove(&request_id);
+        if self.cancelled_requests.remove(&request_id) {
+            // Dropping the substream resets it
+            return;
+        }
+
         self.outbound_stream_timeouts
             .insert(self.outbound_stream_id);
         self.outbound_streams.insert(
@@ -164,10 +175,20 @@ impl ReqRespConnectionHandler {
             error,
             info.request_id
         );
+        self.opening_requests.remove(&info.request_id);
+        if self.cancelled_requests.remove(&info.request_id) {
+            return;
+        }
+        // A request rejected by our own codec is reported as is, anything else means the
+        // stream could not be opened
+        let err = match error {
+            StreamUpgradeError::Apply(err @ ReqRespError::InvalidRequest(_)) => err,
+            _ => ReqRespError::Disconnected,
+        };
         self.behaviour_events
             .push(HandlerEvent::Err(ReqRespMessageError::Outbound {
                 request_id: info.request_id,
-                err: ReqRespError::Disconnected,
+                err,
             }));
     }
 
@@ -212,6 +233,40 @@ impl ReqRespConnectionHandler {
             .push_back(ResponseAction::CloseStream);
     }
 
+    /// Drops an outbound request and resets its substream. No further events are emitted for it.
+    fn cancel(&mut self, request_id: u64) {
+        self.pending_outbound_streams
+            .retain(|open_info| open_info.request_id != request_id);
+        if self.opening_requests.contains(&request_id) {
+            self.cancelled_requests.insert(request_id);
+        }
+
+        let stream_ids = self
+            .outbound_streams
+            .iter()
+            .filter(|(_, stream)| stream.request_id == request_id)
+            .map(|(stream_id, _)| *stream_id)
+            .collect::<Vec<_>>();
+        for stream_id in stream_ids {
+            // Dropping the substream without closing it resets it
+            self.outbound_streams.remove(&stream_id);
+            self.outbound_stream_timeouts.remove(&stream_id);
+        }
+
+        self.behaviour_events.retain(|event| match event {
+            HandlerEvent::Ok(message) => !matches!(
+                message.as_ref(),
+                ReqRespMessageReceived::Response { request_id: id, .. }
+                    | ReqRespMessageReceived::EndOfStream { request_id: id }
+                    if *id == request_id
+            ),
+            HandlerEvent::Err(ReqRespMessageError::Outbound { request_id: id, .. }) => {
+                *id != request_id
+            }
+            _ => true,
+        });
+    }
+
     fn shutdown(&mut self) {
         if matches!(
             self.connection_state,
@@ -457,10 +512,12 @@ impl ConnectionHandler for ReqRespConnectionHandler {
         }
 
         if let Some(open_info) = self.pending_outbound_streams.pop() {
+            self.opening_requests.insert(open_info.request_id);
             return Poll::Ready(ConnectionHandlerEvent::OutboundSubstreamRequest {
                 protocol: SubstreamProtocol::new(
                     OutboundReqRespProtocol {
                         request: open_info.message.clone(),
+                        fork_context: self.listen_protocol.upgrade().fork_context.clone(),
                     },
                     open_info,
                 ),
@@ -490,6 +547,7 @@ impl ConnectionHandler for ReqRespConnectionHandler {
                 self.response(stream_id, *message)
             }
             ConnectionRequest::CloseStream { stream_id } => self.close_stream(stream_id),
+            ConnectionRequest::Cancel { request_id } => self.cancel(request_id),
             ConnectionRequest::Shutdown => self.shutdown(),
         }
     }
@@ -570,5 +628,8 @@ pub enum ConnectionRequest {
     CloseStream {
         stream_id: u64,
     },
+    Cancel {
+        request_id: u64,
+    },
     Shutdown,
 }
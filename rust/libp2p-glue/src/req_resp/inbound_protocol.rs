@@ -2,16 +2,18 @@
 /// as we still need rust-libp2p until we fully migrate to zig-libp2p. It needs the custom RPC protocol implementation.
 /// we changed the encode/decode logic to delegate the framing to zig side, but we still need to inspect the varint prefix to determine the frame length.
 use std::pin::Pin;
+use std::sync::Arc;
 
-use super::varint::{calculate_snappy_frame_size, decode_varint_prefix};
+use super::varint::{calculate_snappy_frame_size, decode_varint_prefix, encode_error_response};
 use crate::req_resp::{
-    configurations::{max_message_size, REQUEST_TIMEOUT},
+    configurations::{MAX_ERROR_MESSAGE_SIZE, REQUEST_TIMEOUT},
     error::ReqRespError,
-    messages::{RequestMessage, ResponseMessage},
-    protocol_id::ProtocolId,
+    fork_context::{ForkContext, CONTEXT_BYTES_LEN},
+    messages::{RequestMessage, ResponseCode, ResponseMessage},
+    protocol_id::{ProtocolId, RpcLimits},
 };
 use bytes::BytesMut;
-use futures::StreamExt;
+use futures::{SinkExt, StreamExt};
 use libp2p::core::UpgradeInfo;
 use libp2p::InboundUpgrade;
 use tokio::time::timeout;
@@ -24,6 +26,7 @@ use tokio_util::{
 #[derive(Clone)]
 pub struct InboundReqRespProtocol {
     pub protocols: Vec<ProtocolId>,
+    pub fork_context: Arc<ForkContext>,
 }
 
 pub type InboundOutput<S> = (RequestMessage, InboundFramed<S>);
@@ -44,13 +47,21 @@ where
 
             let mut stream = Framed::new(
                 Box::pin(timed_socket),
-                InboundCodec {
-                    protocol: info.clone(),
-                },
+                InboundCodec::new(info.clone(), self.fork_context),
             );
 
             match timeout(REQUEST_TIMEOUT, stream.next()).await {
                 Ok(Some(Ok(message))) => Ok((message, stream)),
+                Ok(Some(Err(ReqRespError::InvalidRequest(reason)))) => {
+                    // Let the peer know why the request is rejected before dropping the stream
+                    let response = ResponseMessage::new(
+                        info,
+                        encode_error_response(ResponseCode::InvalidRequest, &reason),
+                    );
+                    let _ = stream.send(response).await;
+                    let _ = stream.close().await;
+                    Err(ReqRespError::InvalidRequest(reason))
+                }
                 Ok(Some(Err(err))) => Err(err),
                 Ok(None) => Err(ReqRespError::IncompleteStream),
                 Err(_) => Err(ReqRespError::StreamTimedOut),
@@ -71,6 +82,21 @@ impl UpgradeInfo for InboundReqRespProtocol {
 #[derive(Clone)]
 pub struct InboundCodec {
     protocol: ProtocolId,
+    // Set from the request once it is decoded
+    max_response_chunks: u64,
+    sent_chunks: u64,
+    fork_context: Arc<ForkContext>,
+}
+
+impl InboundCodec {
+    pub fn new(protocol: ProtocolId, fork_context: Arc<ForkContext>) -> Self {
+        Self {
+            protocol,
+            max_response_chunks: 0,
+            sent_chunks: 0,
+            fork_context,
+        }
+    }
 }
 
 impl Encoder<ResponseMessage> for InboundCodec {
@@ -93,11 +119,19 @@ impl Encoder<ResponseMessage> for InboundCodec {
         let (uncompressed_len, prefix_len) = decode_varint_prefix(&item.payload[1..])?
             .ok_or_else(|| ReqRespError::InvalidData("Incomplete response length prefix".into()))?;
 
-        if uncompressed_len > max_message_size() {
+        let is_success = item.payload[0] == ResponseCode::Success as u8;
+        let limits = if is_success {
+            self.protocol.response_limits()
+        } else {
+            RpcLimits::new(0, MAX_ERROR_MESSAGE_SIZE)
+        };
+        if limits.is_out_of_bounds(uncompressed_len) {
             return Err(ReqRespError::InvalidData(format!(
-                "Message size exceeds maximum: {} > {}",
+                "Response size {} outside of the {} bounds [{}, {}]",
                 uncompressed_len,
-                max_message_size()
+                self.protocol.as_str(),
+                limits.min,
+                limits.max
             )));
         }
 
@@ -124,8 +158,45 @@ impl Encoder<ResponseMessage> for InboundCodec {
             )));
         }
 
+        // Success chunks of protocols with context bytes carry the fork digest after the code
+        let context_bytes = if is_success && self.protocol.has_context_bytes() {
+            let digest = item
+                .context_bytes
+                .or(self.fork_context.current_digest())
+                .ok_or_else(|| {
+                    ReqRespError::InvalidData(format!(
+                        "No fork digest for the context bytes of {}",
+                        self.protocol.as_str()
+                    ))
+                })?;
+            if !self.fork_context.is_known(&digest) {
+                return Err(ReqRespError::InvalidData(format!(
+                    "Unknown fork digest 0x{}",
+                    hex::encode(digest)
+                )));
+            }
+            Some(digest)
+        } else {
+            None
+        };
+
+        if is_success {
+            if self.sent_chunks >= self.max_response_chunks {
+                return Err(ReqRespError::InvalidData(format!(
+                    "Response exceeds the requested number of chunks ({})",
+                    self.max_response_chunks
+                )));
+            }
+            self.sent_chunks += 1;
+        }
+
         dst.clear();
-        dst.extend_from_slice(&item.payload);
+        dst.reserve(item.payload.len() + CONTEXT_BYTES_LEN);
+        dst.extend_from_slice(&item.payload[..1]);
+        if let Some(digest) = context_bytes {
+            dst.extend_from_slice(&digest);
+        }
+        dst.extend_from_slice(&item.payload[1..]);
         Ok(())
     }
 }
@@ -145,11 +216,14 @@ impl Decoder for InboundCodec {
             None => return Ok(None),
         };
 
-        if uncompressed_len > max_message_size() {
-            return Err(ReqRespError::InvalidData(format!(
-                "Message size exceeds maximum: {} > {}",
+        let limits = self.protocol.request_limits();
+        if limits.is_out_of_bounds(uncompressed_len) {
+            return Err(ReqRespError::InvalidRequest(format!(
+                "Request size {} outside of the {} bounds [{}, {}]",
                 uncompressed_len,
-                max_message_size()
+                self.protocol.as_str(),
+                limits.min,
+                limits.max
             )));
         }
 
@@ -170,6 +244,37 @@ impl Decoder for InboundCodec {
         }
 
         let payload = src.split_to(total_len).to_vec();
-        Ok(Some(RequestMessage::new(self.protocol.clone(), payload)))
+        let request = RequestMessage::new(self.protocol.clone(), payload);
+        self.max_response_chunks = request.max_response_chunks()?;
+        Ok(Some(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::req_resp::{protocol_id::LeanSupportedProtocol, varint::encode_varint};
+
+    #[test]
+    fn test_inbound_codec_rejects_requests_outside_protocol_bounds() {
+        let mut codec = InboundCodec::new(LeanSupportedProtocol::StatusV1.into(), Arc::default());
+
+        // A status request is exactly 80 bytes, the length prefix alone is enough to reject it
+        let mut prefix = Vec::new();
+        encode_varint(4 * 1024, &mut prefix);
+        let mut src = BytesMut::from(&prefix[..]);
+        assert!(matches!(
+            codec.decode(&mut src),
+            Err(ReqRespError::InvalidRequest(_))
+        ));
+
+        // Error chunks are not counted against the response chunks
+        let error = ResponseMessage::new(
+            LeanSupportedProtocol::StatusV1.into(),
+            encode_error_response(ResponseCode::InvalidRequest, "bad request"),
+        );
+        let mut dst = BytesMut::new();
+        codec.encode(error, &mut dst).unwrap();
+        assert_eq!(dst[0], ResponseCode::InvalidRequest as u8);
     }
 }
@@ -2,7 +2,50 @@
 /// as we still need rust-libp2p until we fully migrate to zig-libp2p. It needs the custom RPC protocol implementation.
 /// we changed the `RequestMessage` and `ResponseMessage` to keep the payload as raw bytes and delegate the framing to zig side. The caller is expected to
 /// interpret the contents based on the associated `ProtocolId`.
-use crate::req_resp::protocol_id::ProtocolId;
+use crate::req_resp::{
+    error::ReqRespError,
+    fork_context::ForkDigest,
+    protocol_id::{
+        LeanSupportedProtocol, ProtocolId, BLOCKS_BY_RANGE_REQUEST_SSZ_LEN,
+        BLOCKS_BY_ROOT_OFFSET_LEN, ROOT_LEN,
+    },
+    varint::{decode_snappy_payload, decode_varint_prefix},
+};
+
+/// Result code at the start of every response chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ResponseCode {
+    Success = 0,
+    InvalidRequest = 1,
+    ServerError = 2,
+    ResourceUnavailable = 3,
+}
+
+impl ResponseCode {
+    /// The known result codes. Unknown ones are reserved and must be treated as errors.
+    pub fn from_u8(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(ResponseCode::Success),
+            1 => Some(ResponseCode::InvalidRequest),
+            2 => Some(ResponseCode::ServerError),
+            3 => Some(ResponseCode::ResourceUnavailable),
+            _ => None,
+        }
+    }
+}
+
+/// Reason of a Goodbye request. Peers may send any other value, which is passed on as is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u64)]
+pub enum GoodbyeReason {
+    ClientShutdown = 1,
+    IrrelevantNetwork = 2,
+    Fault = 3,
+    TooManyPeers = 129,
+    BadScore = 250,
+    Banned = 251,
+}
 
 /// Represents an outbound or inbound req/resp payload.
 ///
@@ -12,18 +55,121 @@ use crate::req_resp::protocol_id::ProtocolId;
 pub struct RequestMessage {
     pub protocol: ProtocolId,
     pub payload: Vec<u8>,
+    /// Older versions of the request, each encoded for its own protocol, offered after
+    /// `protocol` in preference order.
+    pub fallbacks: Vec<RequestMessage>,
 }
 
 impl RequestMessage {
     pub fn new(protocol: ProtocolId, payload: Vec<u8>) -> Self {
-        Self { protocol, payload }
+        Self {
+            protocol,
+            payload,
+            fallbacks: vec![],
+        }
+    }
+
+    pub fn with_fallback(mut self, fallback: RequestMessage) -> Self {
+        self.fallbacks.push(fallback);
+        self
     }
 
-    /// Returns the protocols that can satisfy this request. For now we only
-    /// support a single protocol per request but we keep the API mirroring
-    /// libp2p's expectations for future extensibility.
+    /// Returns the protocols that can satisfy this request, in the preference order they are
+    /// offered during multistream-select.
     pub fn supported_protocols(&self) -> Vec<ProtocolId> {
-        vec![self.protocol.clone()]
+        std::iter::once(self.protocol.clone())
+            .chain(
+                self.fallbacks
+                    .iter()
+                    .map(|fallback| fallback.protocol.clone()),
+            )
+            .collect()
+    }
+
+    /// The version of the request to send once `protocol` is negotiated.
+    pub fn into_negotiated(self, protocol: &ProtocolId) -> Option<RequestMessage> {
+        if &self.protocol == protocol {
+            return Some(RequestMessage::new(self.protocol, self.payload));
+        }
+        self.fallbacks
+            .into_iter()
+            .find(|fallback| &fallback.protocol == protocol)
+    }
+
+    /// Maximum number of successful response chunks a peer may send for this request: the
+    /// number of requested blocks, capped by the protocol.
+    pub fn max_response_chunks(&self) -> Result<u64, ReqRespError> {
+        let Some(protocol) = self.protocol.lean_protocol() else {
+            return Ok(u64::MAX);
+        };
+
+        let requested = match protocol {
+            LeanSupportedProtocol::BlocksByRootV1 | LeanSupportedProtocol::BlocksByRootV2 => {
+                // The roots are fixed size, so their count follows from the SSZ length
+                let (ssz_len, _) = decode_varint_prefix(&self.payload)?.ok_or_else(|| {
+                    ReqRespError::InvalidRequest("Incomplete request length prefix".into())
+                })?;
+                let roots_len = ssz_len.checked_sub(BLOCKS_BY_ROOT_OFFSET_LEN);
+                match roots_len {
+                    Some(roots_len) if roots_len % ROOT_LEN == 0 => (roots_len / ROOT_LEN) as u64,
+                    _ => {
+                        return Err(ReqRespError::InvalidRequest(format!(
+                            "Invalid blocks_by_root request length {ssz_len}"
+                        )))
+                    }
+                }
+            }
+            LeanSupportedProtocol::BlocksByRangeV1 | LeanSupportedProtocol::BlocksByRangeV2 => {
+                let ssz_bytes = decode_snappy_payload(&self.payload)
+                    .map_err(|err| ReqRespError::InvalidRequest(err.to_string()))?;
+                BlocksByRangeRequest::from_ssz_bytes(&ssz_bytes)?.count
+            }
+            LeanSupportedProtocol::StatusV1 => 1,
+            LeanSupportedProtocol::GoodbyeV1 => 0,
+        };
+        Ok(requested.min(protocol.max_response_chunks()))
+    }
+}
+
+/// The blocks_by_range request, the only one the glue decodes itself to bound the response
+/// stream. It is an SSZ container of three `uint64`s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlocksByRangeRequest {
+    pub start_slot: u64,
+    pub count: u64,
+    pub step: u64,
+}
+
+impl BlocksByRangeRequest {
+    pub const SSZ_LEN: usize = BLOCKS_BY_RANGE_REQUEST_SSZ_LEN;
+
+    pub fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, ReqRespError> {
+        if bytes.len() != Self::SSZ_LEN {
+            return Err(ReqRespError::InvalidRequest(format!(
+                "Invalid blocks_by_range request length: expected {}, got {}",
+                Self::SSZ_LEN,
+                bytes.len()
+            )));
+        }
+
+        let read_u64 = |offset: usize| {
+            let mut word = [0u8; 8];
+            word.copy_from_slice(&bytes[offset..offset + 8]);
+            u64::from_le_bytes(word)
+        };
+        Ok(Self {
+            start_slot: read_u64(0),
+            count: read_u64(8),
+            step: read_u64(16),
+        })
+    }
+
+    pub fn to_ssz_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::SSZ_LEN);
+        bytes.extend_from_slice(&self.start_slot.to_le_bytes());
+        bytes.extend_from_slice(&self.count.to_le_bytes());
+        bytes.extend_from_slice(&self.step.to_le_bytes());
+        bytes
     }
 }
 
@@ -32,10 +178,110 @@ impl RequestMessage {
 pub struct ResponseMessage {
     pub protocol: ProtocolId,
     pub payload: Vec<u8>,
+    /// Fork digest of a success chunk of a protocol with context bytes. The payload keeps the
+    /// `response_code + varint + snappy frame` layout without them.
+    pub context_bytes: Option<ForkDigest>,
 }
 
 impl ResponseMessage {
     pub fn new(protocol: ProtocolId, payload: Vec<u8>) -> Self {
-        Self { protocol, payload }
+        Self {
+            protocol,
+            payload,
+            context_bytes: None,
+        }
+    }
+
+    pub fn with_context_bytes(mut self, context_bytes: ForkDigest) -> Self {
+        self.context_bytes = Some(context_bytes);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::req_resp::{configurations::MAX_REQUEST_BLOCKS, varint::encode_varint};
+
+    fn encode_request(ssz_bytes: &[u8]) -> Vec<u8> {
+        let mut payload = Vec::new();
+        encode_varint(ssz_bytes.len(), &mut payload);
+        let mut encoder = snap::write::FrameEncoder::new(Vec::new());
+        encoder.write_all(ssz_bytes).unwrap();
+        payload.extend(encoder.into_inner().unwrap());
+        payload
+    }
+
+    #[test]
+    fn test_request_offers_fallback_versions_in_order() {
+        let v2 = ProtocolId::from(LeanSupportedProtocol::BlocksByRootV2);
+        let v1 = ProtocolId::from(LeanSupportedProtocol::BlocksByRootV1);
+        // Only v2 carries the fork digest of the blocks
+        assert!(v2.has_context_bytes() && !v1.has_context_bytes());
+        let request = RequestMessage::new(v2.clone(), vec![2])
+            .with_fallback(RequestMessage::new(v1.clone(), vec![1]));
+        assert_eq!(request.supported_protocols(), vec![v2.clone(), v1.clone()]);
+
+        let negotiated = request.clone().into_negotiated(&v2).unwrap();
+        assert_eq!(negotiated.payload, vec![2]);
+        assert!(negotiated.fallbacks.is_empty());
+        assert_eq!(
+            request.clone().into_negotiated(&v1).unwrap().payload,
+            vec![1]
+        );
+        assert!(request
+            .into_negotiated(&LeanSupportedProtocol::BlocksByRangeV2.into())
+            .is_none());
+    }
+
+    #[test]
+    fn test_blocks_by_range_request_bounds_response_chunks() {
+        let request = BlocksByRangeRequest {
+            start_slot: 100,
+            count: 4096,
+            step: 1,
+        };
+        assert_eq!(
+            BlocksByRangeRequest::from_ssz_bytes(&request.to_ssz_bytes()).unwrap(),
+            request
+        );
+
+        let message = RequestMessage::new(
+            LeanSupportedProtocol::BlocksByRangeV1.into(),
+            encode_request(&request.to_ssz_bytes()),
+        );
+        assert_eq!(message.max_response_chunks().unwrap(), MAX_REQUEST_BLOCKS);
+
+        let truncated = RequestMessage::new(
+            LeanSupportedProtocol::BlocksByRangeV1.into(),
+            encode_request(&request.to_ssz_bytes()[..16]),
+        );
+        assert!(truncated.max_response_chunks().is_err());
+
+        let status = RequestMessage::new(LeanSupportedProtocol::StatusV1.into(), vec![]);
+        assert_eq!(status.max_response_chunks().unwrap(), 1);
+        let goodbye = RequestMessage::new(LeanSupportedProtocol::GoodbyeV1.into(), vec![]);
+        assert_eq!(goodbye.max_response_chunks().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_blocks_by_root_response_chunks_follow_root_count() {
+        let roots = vec![0u8; BLOCKS_BY_ROOT_OFFSET_LEN + 3 * ROOT_LEN];
+        let message = RequestMessage::new(
+            LeanSupportedProtocol::BlocksByRootV1.into(),
+            encode_request(&roots),
+        );
+        assert_eq!(message.max_response_chunks().unwrap(), 3);
+
+        let misaligned = RequestMessage::new(
+            LeanSupportedProtocol::BlocksByRootV1.into(),
+            encode_request(&roots[..roots.len() - 1]),
+        );
+        assert!(matches!(
+            misaligned.max_response_chunks(),
+            Err(ReqRespError::InvalidRequest(_))
+        ));
     }
 }
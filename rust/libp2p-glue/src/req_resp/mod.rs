@@ -2,22 +2,30 @@
 /// as we still need rust-libp2p until we fully migrate to zig-libp2p. It needs the custom RPC protocol implementation.
 pub mod configurations;
 pub mod error;
+pub mod fork_context;
 pub mod handler;
 pub mod inbound_protocol;
 pub mod messages;
 pub mod outbound_protocol;
+pub mod outbound_queue;
 pub mod protocol_id;
+pub mod rate_limiter;
 pub(crate) mod varint;
 
+pub use fork_context::{ForkContext, ForkDigest};
 pub use handler::{
     ConnectionRequest, HandlerEvent, ReqRespConnectionHandler, ReqRespMessageError,
     ReqRespMessageReceived,
 };
-pub use messages::{RequestMessage, ResponseMessage};
-pub use protocol_id::{LeanSupportedProtocol, ProtocolId};
+pub use messages::{RequestMessage, ResponseCode, ResponseMessage};
+pub use protocol_id::{LeanProtocolFamily, LeanSupportedProtocol, ProtocolId};
+pub use rate_limiter::{Quota, RateLimiterConfig};
 
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
+use delay_map::HashMapDelay;
+use futures::StreamExt;
 use handler::HandlerEvent as ConnectionHandlerEventWrapper;
 use inbound_protocol::InboundReqRespProtocol;
 use libp2p::{
@@ -29,9 +37,12 @@ use libp2p::{
     },
     Multiaddr, PeerId,
 };
+use rate_limiter::RateLimiter;
 use tracing::{debug, trace};
+use varint::encode_error_response;
 
-/// Maximum number of concurrent requests per protocol ID that a client may issue.
+/// Default maximum number of concurrent outbound requests per peer and protocol ID. Further
+/// requests wait in the `OutboundQueue`.
 pub const MAX_CONCURRENT_REQUESTS: usize = 2;
 
 #[derive(Debug)]
@@ -44,13 +55,25 @@ pub struct ReqRespMessage {
 pub struct ReqResp {
     pub events: Vec<ToSwarm<ReqRespMessage, ConnectionRequest>>,
     pub protocols: Vec<ProtocolId>,
+    rate_limiter: RateLimiter,
+    // Disconnected peers whose rate limiter buckets are dropped once they would be full again,
+    // so reconnecting does not reset the quotas
+    disconnected_peers: HashMapDelay<PeerId, ()>,
+    fork_context: Arc<ForkContext>,
 }
 
 impl ReqResp {
-    pub fn new(protocols: Vec<ProtocolId>) -> Self {
+    pub fn new(
+        protocols: Vec<ProtocolId>,
+        rate_limiter_config: RateLimiterConfig,
+        fork_context: ForkContext,
+    ) -> Self {
         Self {
             events: vec![],
             protocols,
+            disconnected_peers: HashMapDelay::new(rate_limiter_config.refill_period()),
+            rate_limiter: RateLimiter::new(rate_limiter_config),
+            fork_context: Arc::new(fork_context),
         }
     }
 
@@ -95,6 +118,21 @@ impl ReqResp {
         });
     }
 
+    /// Cancels an outbound request on a connection of the peer. Requests are sent on any
+    /// connection, so this is meant to be called for each of them.
+    pub fn cancel_request(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        request_id: u64,
+    ) {
+        self.events.push(ToSwarm::NotifyHandler {
+            peer_id,
+            handler: NotifyHandler::One(connection_id),
+            event: ConnectionRequest::Cancel { request_id },
+        });
+    }
+
     pub fn shutdown(&mut self, peer_id: PeerId, connection_id: ConnectionId) {
         self.events.push(ToSwarm::NotifyHandler {
             peer_id,
@@ -117,9 +155,11 @@ impl NetworkBehaviour for ReqResp {
         _remote_addr: &Multiaddr,
     ) -> Result<THandler<Self>, ConnectionDenied> {
         debug!("REQRESP: inbound connection established {connection_id:?} {peer:?}");
+        self.disconnected_peers.remove(&peer);
         let listen_protocol = SubstreamProtocol::new(
             InboundReqRespProtocol {
                 protocols: self.protocols.clone(),
+                fork_context: self.fork_context.clone(),
             },
             (),
         );
@@ -135,9 +175,11 @@ impl NetworkBehaviour for ReqResp {
         _port_use: PortUse,
     ) -> Result<THandler<Self>, ConnectionDenied> {
         debug!("REQRESP: outbound connection established {connection_id:?} {peer:?}");
+        self.disconnected_peers.remove(&peer);
         let listen_protocol = SubstreamProtocol::new(
             InboundReqRespProtocol {
                 protocols: self.protocols.clone(),
+                fork_context: self.fork_context.clone(),
             },
             (),
         );
@@ -149,11 +191,14 @@ impl NetworkBehaviour for ReqResp {
             peer_id,
             connection_id,
             cause,
-            remaining_established: _,
+            remaining_established,
             ..
         }) = event
         {
             trace!("REQRESP: connection closed {peer_id} {connection_id:?} cause={cause:?}");
+            if remaining_established == 0 {
+                self.disconnected_peers.insert(peer_id, ());
+            }
         }
     }
 
@@ -165,6 +210,28 @@ impl NetworkBehaviour for ReqResp {
     ) {
         match event {
             ConnectionHandlerEventWrapper::Ok(message) => {
+                if let ReqRespMessageReceived::Request {
+                    stream_id,
+                    message: request,
+                } = message.as_ref()
+                {
+                    if let Err(err) = self.rate_limiter.allows(&peer_id, request) {
+                        debug!(
+                            "REQRESP: rate limited {} request from {peer_id}: {err:?}",
+                            request.protocol.as_str()
+                        );
+                        let response = ResponseMessage::new(
+                            request.protocol.clone(),
+                            encode_error_response(
+                                ResponseCode::ResourceUnavailable,
+                                "rate limited",
+                            ),
+                        );
+                        self.send_response(peer_id, connection_id, *stream_id, response);
+                        self.finish_response_stream(peer_id, connection_id, *stream_id);
+                        return;
+                    }
+                }
                 self.events.push(ToSwarm::GenerateEvent(ReqRespMessage {
                     peer_id,
                     connection_id,
@@ -187,8 +254,13 @@ impl NetworkBehaviour for ReqResp {
 
     fn poll(
         &mut self,
-        _cx: &mut Context<'_>,
+        cx: &mut Context<'_>,
     ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        while let Poll::Ready(Some(Ok((peer_id, ())))) = self.disconnected_peers.poll_next_unpin(cx)
+        {
+            self.rate_limiter.remove_peer(&peer_id);
+        }
+
         if !self.events.is_empty() {
             return Poll::Ready(self.events.remove(0));
         }
@@ -1,12 +1,15 @@
 /// The code originally comes from Ream https://github.com/ReamLabs/ream/blob/5a4b3cb42d5646a0d12ec1825ace03645dbfd59b/crates/networking/p2p/src/req_resp/outbound_protocol.rs
 /// as we still need rust-libp2p until we fully migrate to zig-libp2p. It needs the custom RPC protocol implementation.
 /// we changed the encode/decode logic to delegate the framing to zig side, but we still need to inspect the varint prefix to determine the frame length.
-use super::varint::{calculate_snappy_frame_size, decode_varint_prefix};
+use std::sync::Arc;
+
+use super::varint::{calculate_snappy_frame_size, decode_snappy_payload, decode_varint_prefix};
 use crate::req_resp::{
-    configurations::max_message_size,
+    configurations::MAX_ERROR_MESSAGE_SIZE,
     error::ReqRespError,
-    messages::{RequestMessage, ResponseMessage},
-    protocol_id::ProtocolId,
+    fork_context::{ForkContext, ForkDigest, CONTEXT_BYTES_LEN},
+    messages::{RequestMessage, ResponseCode, ResponseMessage},
+    protocol_id::{ProtocolId, RpcLimits},
 };
 use bytes::BytesMut;
 use futures::{FutureExt, SinkExt};
@@ -19,6 +22,7 @@ use tokio_util::{
 
 pub struct OutboundReqRespProtocol {
     pub request: RequestMessage,
+    pub fork_context: Arc<ForkContext>,
 }
 
 pub type OutboundFramed<S> = Framed<Compat<S>, OutboundCodec>;
@@ -32,10 +36,17 @@ where
     type Future = futures::future::BoxFuture<'static, Result<Self::Output, Self::Error>>;
 
     fn upgrade_outbound(self, socket: S, protocol: ProtocolId) -> Self::Future {
-        let mut socket = Framed::new(socket.compat(), OutboundCodec { protocol });
-
         async move {
-            socket.send(self.request).await?;
+            let request = self.request.into_negotiated(&protocol).ok_or_else(|| {
+                ReqRespError::InvalidRequest(format!(
+                    "No request for negotiated protocol {}",
+                    protocol.as_str()
+                ))
+            })?;
+            let codec =
+                OutboundCodec::new(protocol, request.max_response_chunks()?, self.fork_context);
+            let mut socket = Framed::new(socket.compat(), codec);
+            socket.send(request).await?;
             socket.close().await?;
             Ok(socket)
         }
@@ -48,12 +59,30 @@ impl UpgradeInfo for OutboundReqRespProtocol {
     type InfoIter = Vec<Self::Info>;
 
     fn protocol_info(&self) -> Self::InfoIter {
-        vec![self.request.protocol.clone()]
+        self.request.supported_protocols()
     }
 }
 
 pub struct OutboundCodec {
     protocol: ProtocolId,
+    max_response_chunks: u64,
+    received_chunks: u64,
+    fork_context: Arc<ForkContext>,
+}
+
+impl OutboundCodec {
+    pub fn new(
+        protocol: ProtocolId,
+        max_response_chunks: u64,
+        fork_context: Arc<ForkContext>,
+    ) -> Self {
+        Self {
+            protocol,
+            max_response_chunks,
+            received_chunks: 0,
+            fork_context,
+        }
+    }
 }
 
 impl Encoder<RequestMessage> for OutboundCodec {
@@ -70,11 +99,14 @@ impl Encoder<RequestMessage> for OutboundCodec {
         let (uncompressed_len, prefix_len) = decode_varint_prefix(&item.payload)?
             .ok_or_else(|| ReqRespError::InvalidData("Incomplete request length prefix".into()))?;
 
-        if uncompressed_len > max_message_size() {
-            return Err(ReqRespError::InvalidData(format!(
-                "Message size exceeds maximum: {} > {}",
+        let limits = self.protocol.request_limits();
+        if limits.is_out_of_bounds(uncompressed_len) {
+            return Err(ReqRespError::InvalidRequest(format!(
+                "Request size {} outside of the {} bounds [{}, {}]",
                 uncompressed_len,
-                max_message_size()
+                self.protocol.as_str(),
+                limits.min,
+                limits.max
             )));
         }
 
@@ -110,26 +142,59 @@ impl Decoder for OutboundCodec {
     type Error = ReqRespError;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        if src.len() < 2 {
+        if src.is_empty() {
             return Ok(None);
         }
 
-        // Response format: response_code (1 byte) + varint (uncompressed len) + snappy frame
-        let (uncompressed_len, prefix_len) = match decode_varint_prefix(&src[1..])? {
+        // Response format: response_code (1 byte) + context bytes (success chunks of protocols
+        // that carry them) + varint (uncompressed len) + snappy frame
+        let is_success = src[0] == ResponseCode::Success as u8;
+        let header_len = if is_success && self.protocol.has_context_bytes() {
+            1 + CONTEXT_BYTES_LEN
+        } else {
+            1
+        };
+        if src.len() <= header_len {
+            return Ok(None);
+        }
+
+        let context_bytes = if header_len > 1 {
+            let mut digest: ForkDigest = [0; CONTEXT_BYTES_LEN];
+            digest.copy_from_slice(&src[1..header_len]);
+            if !self.fork_context.is_known(&digest) {
+                return Err(ReqRespError::InvalidData(format!(
+                    "Unknown fork digest 0x{} in {} response",
+                    hex::encode(digest),
+                    self.protocol.as_str()
+                )));
+            }
+            Some(digest)
+        } else {
+            None
+        };
+
+        let (uncompressed_len, prefix_len) = match decode_varint_prefix(&src[header_len..])? {
             Some(result) => result,
             None => return Ok(None),
         };
 
-        if uncompressed_len > max_message_size() {
+        let limits = if is_success {
+            self.protocol.response_limits()
+        } else {
+            RpcLimits::new(0, MAX_ERROR_MESSAGE_SIZE)
+        };
+        if limits.is_out_of_bounds(uncompressed_len) {
             return Err(ReqRespError::InvalidData(format!(
-                "Message size exceeds maximum: {} > {}",
+                "Response size {} outside of the {} bounds [{}, {}]",
                 uncompressed_len,
-                max_message_size()
+                self.protocol.as_str(),
+                limits.min,
+                limits.max
             )));
         }
 
         // Now parse the snappy-framed data that follows to determine the actual frame size
-        let snappy_start = 1 + prefix_len;
+        let snappy_start = header_len + prefix_len;
         if src.len() <= snappy_start {
             return Ok(None);
         }
@@ -140,16 +205,106 @@ impl Decoder for OutboundCodec {
                 None => return Ok(None),
             };
 
-        let total_len = 1 + prefix_len + snappy_frame_size;
+        let total_len = header_len + prefix_len + snappy_frame_size;
 
         if src.len() < total_len {
             return Ok(None);
         }
 
-        let payload = src.split_to(total_len).to_vec();
+        if !is_success {
+            // An error chunk ends the response, its `ErrorMessage` is a plain byte list
+            let chunk = src.split_to(total_len);
+            let message = decode_snappy_payload(&chunk[1..])?;
+            return Err(ReqRespError::ErrorResponse {
+                code: chunk[0],
+                message: String::from_utf8_lossy(&message).into_owned(),
+            });
+        }
+
+        if self.received_chunks >= self.max_response_chunks {
+            return Err(ReqRespError::InvalidData(format!(
+                "Response exceeds the requested number of chunks ({})",
+                self.max_response_chunks
+            )));
+        }
+        self.received_chunks += 1;
+
+        // Zig gets the chunk without the context bytes, they are passed next to it
+        let chunk = src.split_to(total_len);
+        let mut payload = Vec::with_capacity(total_len - header_len + 1);
+        payload.push(chunk[0]);
+        payload.extend_from_slice(&chunk[header_len..]);
         Ok(Some(ResponseMessage {
             protocol: self.protocol.clone(),
             payload,
+            context_bytes,
         }))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::req_resp::{protocol_id::LeanSupportedProtocol, varint::encode_error_response};
+
+    // A success chunk carrying an all zero status message
+    fn status_chunk() -> Vec<u8> {
+        let mut chunk = vec![ResponseCode::Success as u8, 80];
+        chunk.extend_from_slice(&[0xff, 0x06, 0x00, 0x00, 0x73, 0x4e, 0x61, 0x50, 0x70, 0x59]);
+        chunk.extend_from_slice(&[0x01, 0x54, 0x00, 0x00, 0, 0, 0, 0]);
+        chunk.extend_from_slice(&[0u8; 80]);
+        chunk
+    }
+
+    #[test]
+    fn test_outbound_codec_bounds_response_chunks() {
+        let mut codec =
+            OutboundCodec::new(LeanSupportedProtocol::StatusV1.into(), 0, Arc::default());
+
+        let mut src =
+            BytesMut::from(&encode_error_response(ResponseCode::InvalidRequest, "bad request")[..]);
+        match codec.decode(&mut src) {
+            Err(ReqRespError::ErrorResponse { code, message }) => {
+                assert_eq!(code, ResponseCode::InvalidRequest as u8);
+                assert_eq!(message, "bad request");
+            }
+            other => panic!("expected an error response, got {other:?}"),
+        }
+        assert!(src.is_empty());
+
+        // Success chunks beyond the requested count are rejected
+        let mut src = BytesMut::from(&status_chunk()[..]);
+        assert!(matches!(
+            codec.decode(&mut src),
+            Err(ReqRespError::InvalidData(_))
+        ));
+    }
+
+    #[test]
+    fn test_outbound_codec_parses_context_bytes() {
+        let protocol = ProtocolId::from(LeanSupportedProtocol::StatusV1).with_context_bytes(true);
+        let digest = [1, 2, 3, 4];
+        let fork_context = Arc::new(ForkContext::new(digest, vec![]));
+        let chunk = status_chunk();
+
+        let mut wire = vec![chunk[0]];
+        wire.extend_from_slice(&digest);
+        wire.extend_from_slice(&chunk[1..]);
+
+        let mut codec = OutboundCodec::new(protocol.clone(), 1, fork_context);
+        let response = codec
+            .decode(&mut BytesMut::from(&wire[..]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.context_bytes, Some(digest));
+        assert_eq!(response.payload, chunk);
+
+        // Chunks of an unknown fork are rejected
+        let other_fork = Arc::new(ForkContext::new([9, 9, 9, 9], vec![]));
+        let mut codec = OutboundCodec::new(protocol, 1, other_fork);
+        assert!(matches!(
+            codec.decode(&mut BytesMut::from(&wire[..])),
+            Err(ReqRespError::InvalidData(_))
+        ));
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::{HashMap, VecDeque};
+
+use libp2p::{PeerId, StreamProtocol};
+
+use crate::req_resp::messages::RequestMessage;
+
+type QueueKey = (PeerId, StreamProtocol);
+
+/// Outbound requests per peer and protocol. At most `max_concurrent_requests` of them are in
+/// flight at a time, the others wait in order until a slot frees up.
+#[derive(Debug)]
+pub struct OutboundQueue {
+    max_concurrent_requests: usize,
+    active: HashMap<QueueKey, usize>,
+    queued: HashMap<QueueKey, VecDeque<(u64, RequestMessage)>>,
+    // Peer and protocol of every active or queued request
+    requests: HashMap<u64, QueueKey>,
+}
+
+impl OutboundQueue {
+    pub fn new(max_concurrent_requests: usize) -> Self {
+        Self {
+            max_concurrent_requests: max_concurrent_requests.max(1),
+            active: HashMap::new(),
+            queued: HashMap::new(),
+            requests: HashMap::new(),
+        }
+    }
+
+    /// Adds a request. It is handed back if it can be sent right away, otherwise it is queued.
+    pub fn push(
+        &mut self,
+        peer_id: PeerId,
+        request_id: u64,
+        message: RequestMessage,
+    ) -> Option<RequestMessage> {
+        let key = (peer_id, message.protocol.stream_protocol().clone());
+        self.requests.insert(request_id, key.clone());
+
+        let active = self.active.entry(key.clone()).or_default();
+        if *active < self.max_concurrent_requests {
+            *active += 1;
+            return Some(message);
+        }
+
+        self.queued
+            .entry(key)
+            .or_default()
+            .push_back((request_id, message));
+        None
+    }
+
+    /// Forgets a finished, failed or timed out request. If it held a slot, the next queued
+    /// request of the same peer and protocol takes it and is returned to be sent.
+    pub fn finish(&mut self, request_id: u64) -> Option<(PeerId, u64, RequestMessage)> {
+        let key = self.requests.remove(&request_id)?;
+
+        if let Some(queue) = self.queued.get_mut(&key) {
+            if let Some(position) = queue.iter().position(|(id, _)| *id == request_id) {
+                queue.remove(position);
+                if queue.is_empty() {
+                    self.queued.remove(&key);
+                }
+                return None;
+            }
+        }
+
+        if let Some(queue) = self.queued.get_mut(&key) {
+            if let Some((next_id, message)) = queue.pop_front() {
+                if queue.is_empty() {
+                    self.queued.remove(&key);
+                }
+                // The slot goes to the next request as is
+                return Some((key.0, next_id, message));
+            }
+        }
+
+        if let Some(active) = self.active.get_mut(&key) {
+            *active = active.saturating_sub(1);
+            if *active == 0 {
+                self.active.remove(&key);
+            }
+        }
+        None
+    }
+
+    pub fn peer_of(&self, request_id: u64) -> Option<PeerId> {
+        self.requests.get(&request_id).map(|(peer_id, _)| *peer_id)
+    }
+
+    pub fn is_queued(&self, request_id: u64) -> bool {
+        self.requests
+            .get(&request_id)
+            .and_then(|key| self.queued.get(key))
+            .is_some_and(|queue| queue.iter().any(|(id, _)| *id == request_id))
+    }
+
+    /// Drops the slots and queue of a disconnected peer. Its queued requests are not sent
+    /// anymore and run into their timeout.
+    pub fn remove_peer(&mut self, peer_id: &PeerId) {
+        self.active.retain(|(peer, _), _| peer != peer_id);
+        self.queued.retain(|(peer, _), _| peer != peer_id);
+        self.requests.retain(|_, (peer, _)| peer != peer_id);
+    }
+
+    pub fn clear(&mut self) {
+        self.active.clear();
+        self.queued.clear();
+        self.requests.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::req_resp::LeanSupportedProtocol;
+
+    fn request(protocol: LeanSupportedProtocol) -> RequestMessage {
+        RequestMessage::new(protocol.into(), vec![])
+    }
+
+    #[test]
+    fn test_requests_beyond_the_cap_are_queued() {
+        let mut queue = OutboundQueue::new(2);
+        let peer = PeerId::random();
+        let status = LeanSupportedProtocol::StatusV1;
+
+        assert!(queue.push(peer, 1, request(status)).is_some());
+        assert!(queue.push(peer, 2, request(status)).is_some());
+        assert!(queue.push(peer, 3, request(status)).is_none());
+        assert!(queue.push(peer, 4, request(status)).is_none());
+        assert!(queue.is_queued(3));
+
+        // Other peers and protocols have their own slots
+        assert!(queue.push(PeerId::random(), 5, request(status)).is_some());
+        assert_eq!(queue.peer_of(3), Some(peer));
+        assert!(queue
+            .push(peer, 6, request(LeanSupportedProtocol::BlocksByRootV1))
+            .is_some());
+
+        let (next_peer, next_id, _) = queue.finish(1).unwrap();
+        assert_eq!((next_peer, next_id), (peer, 3));
+        assert!(!queue.is_queued(3));
+
+        // A queued request that times out does not free a slot
+        assert!(queue.finish(4).is_none());
+        assert!(queue.finish(2).is_none());
+        assert!(queue.finish(3).is_none());
+        assert!(queue.push(peer, 7, request(status)).is_some());
+        assert!(queue.push(peer, 8, request(status)).is_some());
+        assert!(queue.push(peer, 9, request(status)).is_none());
+    }
+
+    #[test]
+    fn test_disconnected_peer_is_forgotten() {
+        let mut queue = OutboundQueue::new(1);
+        let peer = PeerId::random();
+        let status = LeanSupportedProtocol::StatusV1;
+
+        assert!(queue.push(peer, 1, request(status)).is_some());
+        assert!(queue.push(peer, 2, request(status)).is_none());
+
+        queue.remove_peer(&peer);
+        assert!(!queue.is_queued(2));
+        assert!(queue.finish(1).is_none());
+        assert!(queue.push(peer, 3, request(status)).is_some());
+    }
+}
@@ -4,20 +4,134 @@ use libp2p::StreamProtocol;
 use std::fmt;
 use std::hash::{Hash, Hasher};
 
+use crate::req_resp::configurations::{max_message_size, MAX_REQUEST_BLOCKS};
+use crate::status::STATUS_SSZ_LEN;
+
 const LEAN_BLOCKS_BY_ROOT_V1: &str = "/leanconsensus/req/blocks_by_root/1/ssz_snappy";
 const LEAN_STATUS_V1: &str = "/leanconsensus/req/status/1/ssz_snappy";
+const LEAN_BLOCKS_BY_RANGE_V1: &str = "/leanconsensus/req/blocks_by_range/1/ssz_snappy";
+const LEAN_BLOCKS_BY_ROOT_V2: &str = "/leanconsensus/req/blocks_by_root/2/ssz_snappy";
+const LEAN_BLOCKS_BY_RANGE_V2: &str = "/leanconsensus/req/blocks_by_range/2/ssz_snappy";
+const LEAN_GOODBYE_V1: &str = "/leanconsensus/req/goodbye/1/ssz_snappy";
+
+/// SSZ length of a `BlocksByRangeRequest`: start slot, count and step.
+pub const BLOCKS_BY_RANGE_REQUEST_SSZ_LEN: usize = 24;
+/// A `BlocksByRootRequest` is a container with one offset followed by the roots.
+pub const BLOCKS_BY_ROOT_OFFSET_LEN: usize = 4;
+pub const ROOT_LEN: usize = 32;
+/// SSZ length of a Goodbye reason, a `uint64`.
+pub const GOODBYE_SSZ_LEN: usize = 8;
 
+/// Inclusive bounds of the uncompressed SSZ length of a message.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RpcLimits {
+    pub min: usize,
+    pub max: usize,
+}
+
+impl RpcLimits {
+    pub const fn new(min: usize, max: usize) -> Self {
+        Self { min, max }
+    }
+
+    pub fn is_out_of_bounds(&self, length: usize) -> bool {
+        length < self.min || length > self.max
+    }
+}
+
+/// A req/resp message with all its protocol versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LeanProtocolFamily {
+    BlocksByRoot,
+    Status,
+    BlocksByRange,
+    Goodbye,
+}
+
+impl LeanProtocolFamily {
+    pub const ALL: [LeanProtocolFamily; 4] = [
+        LeanProtocolFamily::BlocksByRoot,
+        LeanProtocolFamily::Status,
+        LeanProtocolFamily::BlocksByRange,
+        LeanProtocolFamily::Goodbye,
+    ];
+
+    /// Versions of the message in preference order, newest first.
+    pub fn versions(&self) -> &'static [LeanSupportedProtocol] {
+        match self {
+            LeanProtocolFamily::BlocksByRoot => &[
+                LeanSupportedProtocol::BlocksByRootV2,
+                LeanSupportedProtocol::BlocksByRootV1,
+            ],
+            LeanProtocolFamily::Status => &[LeanSupportedProtocol::StatusV1],
+            LeanProtocolFamily::BlocksByRange => &[
+                LeanSupportedProtocol::BlocksByRangeV2,
+                LeanSupportedProtocol::BlocksByRangeV1,
+            ],
+            LeanProtocolFamily::Goodbye => &[LeanSupportedProtocol::GoodbyeV1],
+        }
+    }
+
+    /// Every supported protocol version, each family in preference order.
+    pub fn all_versions() -> Vec<LeanSupportedProtocol> {
+        Self::ALL
+            .iter()
+            .flat_map(|family| family.versions().iter().copied())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum LeanSupportedProtocol {
     BlocksByRootV1,
     StatusV1,
+    BlocksByRangeV1,
+    /// Sent by the glue before it disconnects a peer, never exchanged with Zig.
+    GoodbyeV1,
+    /// Like v1, with the fork digest of each block as context bytes.
+    BlocksByRootV2,
+    BlocksByRangeV2,
 }
 
 impl LeanSupportedProtocol {
+    pub const ALL: [LeanSupportedProtocol; 6] = [
+        LeanSupportedProtocol::BlocksByRootV1,
+        LeanSupportedProtocol::StatusV1,
+        LeanSupportedProtocol::BlocksByRangeV1,
+        LeanSupportedProtocol::GoodbyeV1,
+        LeanSupportedProtocol::BlocksByRootV2,
+        LeanSupportedProtocol::BlocksByRangeV2,
+    ];
+
+    pub fn from_protocol_id(protocol_id: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|protocol| protocol.protocol_id() == protocol_id)
+    }
+
+    pub fn family(&self) -> LeanProtocolFamily {
+        match self {
+            LeanSupportedProtocol::BlocksByRootV1 | LeanSupportedProtocol::BlocksByRootV2 => {
+                LeanProtocolFamily::BlocksByRoot
+            }
+            LeanSupportedProtocol::StatusV1 => LeanProtocolFamily::Status,
+            LeanSupportedProtocol::BlocksByRangeV1 | LeanSupportedProtocol::BlocksByRangeV2 => {
+                LeanProtocolFamily::BlocksByRange
+            }
+            LeanSupportedProtocol::GoodbyeV1 => LeanProtocolFamily::Goodbye,
+        }
+    }
+
     pub fn message_name(&self) -> &'static str {
         match self {
-            LeanSupportedProtocol::BlocksByRootV1 => "blocks_by_root",
+            LeanSupportedProtocol::BlocksByRootV1 | LeanSupportedProtocol::BlocksByRootV2 => {
+                "blocks_by_root"
+            }
             LeanSupportedProtocol::StatusV1 => "status",
+            LeanSupportedProtocol::BlocksByRangeV1 | LeanSupportedProtocol::BlocksByRangeV2 => {
+                "blocks_by_range"
+            }
+            LeanSupportedProtocol::GoodbyeV1 => "goodbye",
         }
     }
 
@@ -25,13 +139,22 @@ impl LeanSupportedProtocol {
         match self {
             LeanSupportedProtocol::BlocksByRootV1 => "1",
             LeanSupportedProtocol::StatusV1 => "1",
+            LeanSupportedProtocol::BlocksByRangeV1 => "1",
+            LeanSupportedProtocol::GoodbyeV1 => "1",
+            LeanSupportedProtocol::BlocksByRootV2 => "2",
+            LeanSupportedProtocol::BlocksByRangeV2 => "2",
         }
     }
 
+    /// Whether success chunks carry the fork digest of the block they hold.
     pub fn has_context_bytes(&self) -> bool {
         match self {
             LeanSupportedProtocol::BlocksByRootV1 => false,
             LeanSupportedProtocol::StatusV1 => false,
+            LeanSupportedProtocol::BlocksByRangeV1 => false,
+            LeanSupportedProtocol::GoodbyeV1 => false,
+            LeanSupportedProtocol::BlocksByRootV2 => true,
+            LeanSupportedProtocol::BlocksByRangeV2 => true,
         }
     }
 
@@ -39,6 +162,54 @@ impl LeanSupportedProtocol {
         match self {
             LeanSupportedProtocol::BlocksByRootV1 => LEAN_BLOCKS_BY_ROOT_V1,
             LeanSupportedProtocol::StatusV1 => LEAN_STATUS_V1,
+            LeanSupportedProtocol::BlocksByRangeV1 => LEAN_BLOCKS_BY_RANGE_V1,
+            LeanSupportedProtocol::GoodbyeV1 => LEAN_GOODBYE_V1,
+            LeanSupportedProtocol::BlocksByRootV2 => LEAN_BLOCKS_BY_ROOT_V2,
+            LeanSupportedProtocol::BlocksByRangeV2 => LEAN_BLOCKS_BY_RANGE_V2,
+        }
+    }
+
+    pub fn request_limits(&self) -> RpcLimits {
+        match self {
+            LeanSupportedProtocol::BlocksByRootV1 | LeanSupportedProtocol::BlocksByRootV2 => {
+                RpcLimits::new(
+                    BLOCKS_BY_ROOT_OFFSET_LEN,
+                    BLOCKS_BY_ROOT_OFFSET_LEN + ROOT_LEN * MAX_REQUEST_BLOCKS as usize,
+                )
+            }
+            LeanSupportedProtocol::StatusV1 => RpcLimits::new(STATUS_SSZ_LEN, STATUS_SSZ_LEN),
+            LeanSupportedProtocol::BlocksByRangeV1 | LeanSupportedProtocol::BlocksByRangeV2 => {
+                RpcLimits::new(
+                    BLOCKS_BY_RANGE_REQUEST_SSZ_LEN,
+                    BLOCKS_BY_RANGE_REQUEST_SSZ_LEN,
+                )
+            }
+            LeanSupportedProtocol::GoodbyeV1 => RpcLimits::new(GOODBYE_SSZ_LEN, GOODBYE_SSZ_LEN),
+        }
+    }
+
+    /// Bounds of a successful response chunk. Blocks have no useful lower bound.
+    pub fn response_limits(&self) -> RpcLimits {
+        match self {
+            LeanSupportedProtocol::BlocksByRootV1
+            | LeanSupportedProtocol::BlocksByRangeV1
+            | LeanSupportedProtocol::BlocksByRootV2
+            | LeanSupportedProtocol::BlocksByRangeV2 => RpcLimits::new(0, max_message_size()),
+            LeanSupportedProtocol::StatusV1 => RpcLimits::new(STATUS_SSZ_LEN, STATUS_SSZ_LEN),
+            // Goodbye has no response
+            LeanSupportedProtocol::GoodbyeV1 => RpcLimits::new(0, 0),
+        }
+    }
+
+    /// Upper bound of the successful response chunks, before the request narrows it down.
+    pub fn max_response_chunks(&self) -> u64 {
+        match self {
+            LeanSupportedProtocol::BlocksByRootV1
+            | LeanSupportedProtocol::BlocksByRangeV1
+            | LeanSupportedProtocol::BlocksByRootV2
+            | LeanSupportedProtocol::BlocksByRangeV2 => MAX_REQUEST_BLOCKS,
+            LeanSupportedProtocol::StatusV1 => 1,
+            LeanSupportedProtocol::GoodbyeV1 => 0,
         }
     }
 }
@@ -50,6 +221,9 @@ impl TryFrom<u32> for LeanSupportedProtocol {
         match value {
             0 => Ok(LeanSupportedProtocol::BlocksByRootV1),
             1 => Ok(LeanSupportedProtocol::StatusV1),
+            2 => Ok(LeanSupportedProtocol::BlocksByRangeV1),
+            3 => Ok(LeanSupportedProtocol::BlocksByRootV2),
+            4 => Ok(LeanSupportedProtocol::BlocksByRangeV2),
             _ => Err(()),
         }
     }
@@ -93,6 +267,23 @@ impl ProtocolId {
     pub fn stream_protocol(&self) -> &StreamProtocol {
         &self.protocol
     }
+
+    pub fn lean_protocol(&self) -> Option<LeanSupportedProtocol> {
+        LeanSupportedProtocol::from_protocol_id(self.as_str())
+    }
+
+    /// Request bounds of the protocol, only the size limit for protocols unknown to the glue.
+    pub fn request_limits(&self) -> RpcLimits {
+        self.lean_protocol()
+            .map(|protocol| protocol.request_limits())
+            .unwrap_or(RpcLimits::new(0, max_message_size()))
+    }
+
+    pub fn response_limits(&self) -> RpcLimits {
+        self.lean_protocol()
+            .map(|protocol| protocol.response_limits())
+            .unwrap_or(RpcLimits::new(0, max_message_size()))
+    }
 }
 
 impl From<LeanSupportedProtocol> for ProtocolId {
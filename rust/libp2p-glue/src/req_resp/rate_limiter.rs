@@ -0,0 +1,371 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use libp2p::PeerId;
+use serde::{Deserialize, Deserializer};
+
+use crate::req_resp::{
+    configurations::MAX_REQUEST_BLOCKS, messages::RequestMessage,
+    protocol_id::LeanSupportedProtocol,
+};
+
+/// Token bucket quota: up to `max_tokens` tokens, refilled linearly so that an empty bucket is
+/// full again after `replenish_all_every`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Quota {
+    pub max_tokens: u64,
+    #[serde(
+        rename = "replenish_all_every_ms",
+        deserialize_with = "duration_from_millis"
+    )]
+    pub replenish_all_every: Duration,
+}
+
+fn duration_from_millis<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    u64::deserialize(deserializer).map(Duration::from_millis)
+}
+
+impl Quota {
+    pub const fn n_every(max_tokens: u64, replenish_all_every: Duration) -> Self {
+        Self {
+            max_tokens,
+            replenish_all_every,
+        }
+    }
+}
+
+/// Inbound quotas per peer and protocol, supplied by Zig as part of the req/resp config.
+/// Missing quotas fall back to the defaults below.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimiterConfig {
+    pub status: Quota,
+    pub blocks_by_root: Quota,
+    pub blocks_by_range: Quota,
+    pub goodbye: Quota,
+    /// Blocks a peer may request per protocol, on top of the request quota of blocks_by_root
+    /// and blocks_by_range. Must allow at least `MAX_REQUEST_BLOCKS`.
+    pub requested_blocks: Quota,
+}
+
+impl Default for RateLimiterConfig {
+    fn default() -> Self {
+        Self {
+            status: Quota::n_every(5, Duration::from_secs(15)),
+            blocks_by_root: Quota::n_every(128, Duration::from_secs(10)),
+            blocks_by_range: Quota::n_every(32, Duration::from_secs(10)),
+            goodbye: Quota::n_every(1, Duration::from_secs(10)),
+            requested_blocks: Quota::n_every(2 * MAX_REQUEST_BLOCKS, Duration::from_secs(10)),
+        }
+    }
+}
+
+impl RateLimiterConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.requested_blocks.max_tokens < MAX_REQUEST_BLOCKS {
+            return Err(format!(
+                "requested_blocks must allow at least {MAX_REQUEST_BLOCKS} blocks"
+            ));
+        }
+        if self
+            .quotas()
+            .any(|quota| quota.replenish_all_every.is_zero())
+        {
+            return Err("replenish_all_every_ms must be positive".into());
+        }
+        Ok(())
+    }
+
+    /// Time after which every bucket is full again, buckets unused for longer can be dropped.
+    pub fn refill_period(&self) -> Duration {
+        self.quotas()
+            .map(|quota| quota.replenish_all_every)
+            .max()
+            .unwrap_or_default()
+    }
+
+    fn quotas(&self) -> impl Iterator<Item = &Quota> {
+        [
+            &self.status,
+            &self.blocks_by_root,
+            &self.blocks_by_range,
+            &self.goodbye,
+            &self.requested_blocks,
+        ]
+        .into_iter()
+    }
+
+    fn request_quota(&self, protocol: LeanSupportedProtocol) -> Quota {
+        match protocol {
+            LeanSupportedProtocol::StatusV1 => self.status,
+            LeanSupportedProtocol::BlocksByRootV1 | LeanSupportedProtocol::BlocksByRootV2 => {
+                self.blocks_by_root
+            }
+            LeanSupportedProtocol::BlocksByRangeV1 | LeanSupportedProtocol::BlocksByRangeV2 => {
+                self.blocks_by_range
+            }
+            LeanSupportedProtocol::GoodbyeV1 => self.goodbye,
+        }
+    }
+}
+
+/// Source of the current time, replaced by a manual clock in tests.
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitedErr {
+    /// The request needs more tokens than the quota ever holds.
+    TooLarge,
+    /// Enough tokens are available again after the given time.
+    TooSoon(Duration),
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: u64,
+    last_refill: Instant,
+}
+
+impl Bucket {
+    fn full(quota: &Quota, now: Instant) -> Self {
+        Self {
+            tokens: quota.max_tokens,
+            last_refill: now,
+        }
+    }
+
+    fn refill(&mut self, quota: &Quota, now: Instant) {
+        if self.tokens >= quota.max_tokens {
+            self.tokens = quota.max_tokens;
+            self.last_refill = now;
+            return;
+        }
+
+        let period = quota.replenish_all_every.as_nanos().max(1);
+        let elapsed = now.saturating_duration_since(self.last_refill).as_nanos();
+        let refilled = elapsed * quota.max_tokens as u128 / period;
+        if refilled == 0 {
+            return;
+        }
+
+        if self.tokens as u128 + refilled >= quota.max_tokens as u128 {
+            self.tokens = quota.max_tokens;
+            self.last_refill = now;
+        } else {
+            self.tokens += refilled as u64;
+            // Keep the time of the partially refilled token
+            let used = refilled * period / quota.max_tokens as u128;
+            self.last_refill += Duration::from_nanos(used as u64);
+        }
+    }
+
+    fn wait_for(&self, tokens: u64, quota: &Quota) -> Duration {
+        let missing = tokens.saturating_sub(self.tokens) as u128;
+        let period = quota.replenish_all_every.as_nanos();
+        let nanos = (missing * period).div_ceil(quota.max_tokens.max(1) as u128);
+        Duration::from_nanos(nanos as u64)
+    }
+}
+
+/// Inbound req/resp rate limiter keyed by peer and protocol. Every request takes one token of
+/// its protocol's request quota, block requests also take one token per requested block.
+pub struct RateLimiter<C: Clock = SystemClock> {
+    config: RateLimiterConfig,
+    clock: C,
+    requests: HashMap<(PeerId, LeanSupportedProtocol), Bucket>,
+    blocks: HashMap<(PeerId, LeanSupportedProtocol), Bucket>,
+}
+
+impl RateLimiter<SystemClock> {
+    pub fn new(config: RateLimiterConfig) -> Self {
+        Self::with_clock(config, SystemClock)
+    }
+}
+
+impl<C: Clock> RateLimiter<C> {
+    pub fn with_clock(config: RateLimiterConfig, clock: C) -> Self {
+        Self {
+            config,
+            clock,
+            requests: HashMap::new(),
+            blocks: HashMap::new(),
+        }
+    }
+
+    /// Takes the tokens of an inbound request if both of its quotas allow it. Protocols unknown
+    /// to the glue are not limited.
+    pub fn allows(
+        &mut self,
+        peer_id: &PeerId,
+        request: &RequestMessage,
+    ) -> Result<(), RateLimitedErr> {
+        let Some(protocol) = request.protocol.lean_protocol() else {
+            return Ok(());
+        };
+        let requested_blocks = match protocol {
+            LeanSupportedProtocol::StatusV1 | LeanSupportedProtocol::GoodbyeV1 => None,
+            // The codec already rejected requests it cannot size
+            LeanSupportedProtocol::BlocksByRootV1
+            | LeanSupportedProtocol::BlocksByRangeV1
+            | LeanSupportedProtocol::BlocksByRootV2
+            | LeanSupportedProtocol::BlocksByRangeV2 => Some(
+                request
+                    .max_response_chunks()
+                    .unwrap_or(MAX_REQUEST_BLOCKS)
+                    .max(1),
+            ),
+        };
+        self.allows_tokens(peer_id, protocol, requested_blocks)
+    }
+
+    fn allows_tokens(
+        &mut self,
+        peer_id: &PeerId,
+        protocol: LeanSupportedProtocol,
+        requested_blocks: Option<u64>,
+    ) -> Result<(), RateLimitedErr> {
+        let now = self.clock.now();
+        let key = (*peer_id, protocol);
+        let request_quota = self.config.request_quota(protocol);
+        let blocks_quota = self.config.requested_blocks;
+
+        if request_quota.max_tokens == 0
+            || requested_blocks.is_some_and(|blocks| blocks > blocks_quota.max_tokens)
+        {
+            return Err(RateLimitedErr::TooLarge);
+        }
+
+        let request_bucket = self
+            .requests
+            .entry(key)
+            .or_insert_with(|| Bucket::full(&request_quota, now));
+        request_bucket.refill(&request_quota, now);
+        let mut wait = request_bucket.wait_for(1, &request_quota);
+
+        if let Some(blocks) = requested_blocks {
+            let blocks_bucket = self
+                .blocks
+                .entry(key)
+                .or_insert_with(|| Bucket::full(&blocks_quota, now));
+            blocks_bucket.refill(&blocks_quota, now);
+            wait = wait.max(blocks_bucket.wait_for(blocks, &blocks_quota));
+        }
+
+        if !wait.is_zero() {
+            return Err(RateLimitedErr::TooSoon(wait));
+        }
+
+        // Both quotas allow the request, take the tokens
+        if let Some(bucket) = self.requests.get_mut(&key) {
+            bucket.tokens -= 1;
+        }
+        if let (Some(blocks), Some(bucket)) = (requested_blocks, self.blocks.get_mut(&key)) {
+            bucket.tokens -= blocks;
+        }
+        Ok(())
+    }
+
+    /// Forgets the buckets of a peer that has been disconnected for longer than the refill period.
+    pub fn remove_peer(&mut self, peer_id: &PeerId) {
+        self.requests.retain(|(peer, _), _| peer != peer_id);
+        self.blocks.retain(|(peer, _), _| peer != peer_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[derive(Clone)]
+    struct ManualClock(Rc<Cell<Instant>>);
+
+    impl ManualClock {
+        fn advance(&self, duration: Duration) {
+            self.0.set(self.0.get() + duration);
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> Instant {
+            self.0.get()
+        }
+    }
+
+    fn limiter() -> (RateLimiter<ManualClock>, ManualClock) {
+        let clock = ManualClock(Rc::new(Cell::new(Instant::now())));
+        let config = RateLimiterConfig {
+            status: Quota::n_every(2, Duration::from_secs(10)),
+            blocks_by_root: Quota::n_every(10, Duration::from_secs(10)),
+            blocks_by_range: Quota::n_every(10, Duration::from_secs(10)),
+            goodbye: Quota::n_every(1, Duration::from_secs(10)),
+            requested_blocks: Quota::n_every(100, Duration::from_secs(10)),
+        };
+        (RateLimiter::with_clock(config, clock.clone()), clock)
+    }
+
+    #[test]
+    fn test_request_quota_refills_over_time() {
+        let (mut limiter, clock) = limiter();
+        let peer = PeerId::random();
+        let status = LeanSupportedProtocol::StatusV1;
+
+        assert!(limiter.allows_tokens(&peer, status, None).is_ok());
+        assert!(limiter.allows_tokens(&peer, status, None).is_ok());
+        assert_eq!(
+            limiter.allows_tokens(&peer, status, None),
+            Err(RateLimitedErr::TooSoon(Duration::from_secs(5)))
+        );
+
+        // Other peers and protocols have their own buckets
+        assert!(limiter
+            .allows_tokens(&PeerId::random(), status, None)
+            .is_ok());
+        assert!(limiter
+            .allows_tokens(&peer, LeanSupportedProtocol::BlocksByRootV1, Some(1))
+            .is_ok());
+
+        clock.advance(Duration::from_secs(5));
+        assert!(limiter.allows_tokens(&peer, status, None).is_ok());
+        assert!(limiter.allows_tokens(&peer, status, None).is_err());
+
+        limiter.remove_peer(&peer);
+        assert!(limiter.allows_tokens(&peer, status, None).is_ok());
+    }
+
+    #[test]
+    fn test_requested_blocks_are_limited() {
+        let (mut limiter, clock) = limiter();
+        let peer = PeerId::random();
+        let range = LeanSupportedProtocol::BlocksByRangeV1;
+
+        assert_eq!(
+            limiter.allows_tokens(&peer, range, Some(101)),
+            Err(RateLimitedErr::TooLarge)
+        );
+        assert!(limiter.allows_tokens(&peer, range, Some(80)).is_ok());
+        // A rejected request does not take the request token either
+        assert_eq!(
+            limiter.allows_tokens(&peer, range, Some(40)),
+            Err(RateLimitedErr::TooSoon(Duration::from_secs(2)))
+        );
+
+        clock.advance(Duration::from_secs(2));
+        assert!(limiter.allows_tokens(&peer, range, Some(40)).is_ok());
+        assert_eq!(limiter.requests[&(peer, range)].tokens, 9);
+    }
+}
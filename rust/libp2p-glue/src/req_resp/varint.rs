@@ -1,4 +1,10 @@
-use crate::req_resp::error::ReqRespError;
+use std::io::{Read, Write};
+
+use crate::req_resp::{
+    configurations::{max_message_size, MAX_ERROR_MESSAGE_SIZE},
+    error::ReqRespError,
+    messages::ResponseCode,
+};
 use snap::raw::max_compress_len;
 use unsigned_varint::{decode, encode};
 
@@ -203,3 +209,92 @@ pub fn calculate_snappy_frame_size(
 
     Ok(None)
 }
+
+/// Decompresses a `varint (uncompressed len) + snappy frame` payload into the SSZ bytes it
+/// carries, for the few requests the glue has to look into.
+pub fn decode_snappy_payload(payload: &[u8]) -> Result<Vec<u8>, ReqRespError> {
+    let (uncompressed_len, prefix_len) = decode_varint_prefix(payload)?
+        .ok_or_else(|| ReqRespError::InvalidData("Incomplete length prefix".into()))?;
+
+    if uncompressed_len > max_message_size() {
+        return Err(ReqRespError::InvalidData(format!(
+            "Message size exceeds maximum: {} > {}",
+            uncompressed_len,
+            max_message_size()
+        )));
+    }
+
+    let mut ssz_bytes = Vec::with_capacity(uncompressed_len);
+    // Read one byte past the declared length to detect oversized frames
+    snap::read::FrameDecoder::new(&payload[prefix_len..])
+        .take(uncompressed_len as u64 + 1)
+        .read_to_end(&mut ssz_bytes)
+        .map_err(|err| ReqRespError::InvalidData(format!("Invalid snappy frame: {err}")))?;
+
+    if ssz_bytes.len() != uncompressed_len {
+        return Err(ReqRespError::InvalidData(format!(
+            "Decompressed length mismatch (expected {}, got {})",
+            uncompressed_len,
+            ssz_bytes.len()
+        )));
+    }
+
+    Ok(ssz_bytes)
+}
+
+/// Compresses SSZ bytes into the `varint (uncompressed len) + snappy frame` payload layout.
+pub fn encode_snappy_payload(ssz_bytes: &[u8]) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(MAX_VARINT_BYTES + max_compress_len(ssz_bytes.len()));
+    encode_varint(ssz_bytes.len(), &mut payload);
+
+    let mut encoder = snap::write::FrameEncoder::new(payload);
+    encoder
+        .write_all(ssz_bytes)
+        .expect("writing to a Vec cannot fail");
+    encoder
+        .into_inner()
+        .expect("flushing into a Vec cannot fail")
+}
+
+/// Builds an error response chunk: the response code followed by the snappy framed
+/// `ErrorMessage`, truncated to `MAX_ERROR_MESSAGE_SIZE` bytes.
+pub fn encode_error_response(code: ResponseCode, message: &str) -> Vec<u8> {
+    let message = &message.as_bytes()[..message.len().min(MAX_ERROR_MESSAGE_SIZE)];
+
+    let mut payload = Vec::with_capacity(1 + MAX_VARINT_BYTES + max_compress_len(message.len()));
+    payload.push(code as u8);
+    encode_varint(message.len(), &mut payload);
+
+    let mut encoder = snap::write::FrameEncoder::new(payload);
+    encoder
+        .write_all(message)
+        .expect("writing to a Vec cannot fail");
+    encoder
+        .into_inner()
+        .expect("flushing into a Vec cannot fail")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snappy_payload_round_trip_checks_crc() {
+        let ssz_bytes = vec![7u8; 300];
+        let mut payload = encode_snappy_payload(&ssz_bytes);
+        assert_eq!(decode_snappy_payload(&payload).unwrap(), ssz_bytes);
+
+        // The frame is still well formed, only the checksum of its data chunk is off
+        let (_, prefix_len) = decode_varint_prefix(&payload).unwrap().unwrap();
+        let crc_start = prefix_len + SNAPPY_STREAM_IDENTIFIER.len() + 4;
+        payload[crc_start] ^= 0xff;
+        assert_eq!(
+            calculate_snappy_frame_size(&payload[prefix_len..], ssz_bytes.len()).unwrap(),
+            Some(payload.len() - prefix_len)
+        );
+        assert!(matches!(
+            decode_snappy_payload(&payload),
+            Err(ReqRespError::InvalidData(_))
+        ));
+    }
+}
@@ -0,0 +1,130 @@
+use serde::Deserialize;
+
+use crate::req_resp::{
+    fork_context::CONTEXT_BYTES_LEN, ForkContext, ForkDigest, RateLimiterConfig,
+    MAX_CONCURRENT_REQUESTS,
+};
+
+/// Req/resp configuration supplied by Zig as JSON at network start.
+///
+/// Every field is optional. Without a fork digest the protocols with context bytes,
+/// blocks_by_root and blocks_by_range v2, can neither send nor accept blocks.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReqRespConfig {
+    /// Hex digest of the active fork, written as the context bytes of served blocks.
+    pub fork_digest: Option<String>,
+    /// Hex digests of earlier forks whose blocks are still accepted.
+    pub previous_fork_digests: Vec<String>,
+    /// Outbound requests in flight per peer and protocol, further ones are queued.
+    pub max_concurrent_requests: usize,
+    /// Quotas of inbound requests per peer and protocol.
+    pub rate_limits: RateLimiterConfig,
+}
+
+impl Default for ReqRespConfig {
+    fn default() -> Self {
+        Self {
+            fork_digest: None,
+            previous_fork_digests: Vec::new(),
+            max_concurrent_requests: MAX_CONCURRENT_REQUESTS,
+            rate_limits: RateLimiterConfig::default(),
+        }
+    }
+}
+
+impl ReqRespConfig {
+    /// Parses the JSON config passed over FFI. An empty document selects the defaults.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        if json.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        let config: Self =
+            serde_json::from_str(json).map_err(|e| format!("invalid req/resp config: {e}"))?;
+        config
+            .validate()
+            .map_err(|e| format!("invalid req/resp config: {e}"))?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.max_concurrent_requests == 0 {
+            return Err("max_concurrent_requests must be positive".into());
+        }
+        self.rate_limits.validate()?;
+        self.fork_context().map(|_| ())
+    }
+
+    pub fn fork_context(&self) -> Result<ForkContext, String> {
+        let Some(current) = &self.fork_digest else {
+            if !self.previous_fork_digests.is_empty() {
+                return Err("previous_fork_digests require a fork_digest".into());
+            }
+            return Ok(ForkContext::default());
+        };
+        let previous = self
+            .previous_fork_digests
+            .iter()
+            .map(|digest| parse_fork_digest(digest))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ForkContext::new(parse_fork_digest(current)?, previous))
+    }
+}
+
+fn parse_fork_digest(digest: &str) -> Result<ForkDigest, String> {
+    let bytes = hex::decode(digest.strip_prefix("0x").unwrap_or(digest))
+        .map_err(|e| format!("invalid fork digest {digest}: {e}"))?;
+    ForkDigest::try_from(bytes.as_slice())
+        .map_err(|_| format!("invalid fork digest {digest}: expected {CONTEXT_BYTES_LEN} bytes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::req_resp::Quota;
+
+    #[test]
+    fn test_fork_digests_build_the_fork_context() {
+        let config = ReqRespConfig::from_json("").unwrap();
+        assert_eq!(config.fork_context(), Ok(ForkContext::default()));
+        assert_eq!(config.max_concurrent_requests, MAX_CONCURRENT_REQUESTS);
+
+        let config = ReqRespConfig::from_json(
+            r#"{"fork_digest": "0x01020304", "previous_fork_digests": ["0a0b0c0d"]}"#,
+        )
+        .unwrap();
+        let fork_context = config.fork_context().unwrap();
+        assert_eq!(fork_context.current_digest(), Some([1, 2, 3, 4]));
+        assert!(fork_context.is_known(&[10, 11, 12, 13]));
+        assert!(!fork_context.is_known(&[0, 0, 0, 0]));
+
+        let config = ReqRespConfig::from_json(
+            r#"{"rate_limits": {"status": {"max_tokens": 2, "replenish_all_every_ms": 30000}}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.rate_limits.status,
+            Quota::n_every(2, Duration::from_secs(30))
+        );
+        assert_eq!(
+            config.rate_limits.goodbye,
+            RateLimiterConfig::default().goodbye
+        );
+        assert_eq!(config.rate_limits.refill_period(), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_invalid_fork_digests_are_errors() {
+        assert!(ReqRespConfig::from_json(r#"{"fork_digest": "0x010203"}"#).is_err());
+        assert!(ReqRespConfig::from_json(r#"{"fork_digest": "0xzz020304"}"#).is_err());
+        assert!(ReqRespConfig::from_json(r#"{"previous_fork_digests": ["0x01020304"]}"#).is_err());
+        assert!(ReqRespConfig::from_json(r#"{"fork": "0x01020304"}"#).is_err());
+        assert!(ReqRespConfig::from_json(r#"{"max_concurrent_requests": 0}"#).is_err());
+        assert!(ReqRespConfig::from_json(
+            r#"{"rate_limits": {"requested_blocks": {"max_tokens": 1, "replenish_all_every_ms": 1000}}}"#
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+
+use libp2p::PeerId;
+
+/// SSZ size of a `Status` message: two checkpoints of a 32-byte root and a u64 slot.
+pub const STATUS_SSZ_LEN: usize = 80;
+
+/// Chain status exchanged in the Status handshake, the same layout as the Zig `types.Status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Status {
+    pub finalized_root: [u8; 32],
+    pub finalized_slot: u64,
+    pub head_root: [u8; 32],
+    pub head_slot: u64,
+}
+
+impl Status {
+    pub fn to_ssz_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(STATUS_SSZ_LEN);
+        bytes.extend_from_slice(&self.finalized_root);
+        bytes.extend_from_slice(&self.finalized_slot.to_le_bytes());
+        bytes.extend_from_slice(&self.head_root);
+        bytes.extend_from_slice(&self.head_slot.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() != STATUS_SSZ_LEN {
+            return Err(format!(
+                "Status must be {} bytes, got {}",
+                STATUS_SSZ_LEN,
+                bytes.len()
+            ));
+        }
+
+        let root = |offset: usize| -> [u8; 32] {
+            bytes[offset..offset + 32]
+                .try_into()
+                .expect("slice has 32 bytes")
+        };
+        let slot = |offset: usize| {
+            u64::from_le_bytes(
+                bytes[offset..offset + 8]
+                    .try_into()
+                    .expect("slice has 8 bytes"),
+            )
+        };
+        Ok(Self {
+            finalized_root: root(0),
+            finalized_slot: slot(32),
+            head_root: root(40),
+            head_slot: slot(72),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Handshake {
+    peer_id: PeerId,
+    // Whether the peer answered with its status
+    received: bool,
+}
+
+/// Status handshakes in flight, keyed by the request id of the Status request sent to the peer
+/// when it connected.
+#[derive(Debug, Default)]
+pub struct StatusHandshakes {
+    pending: HashMap<u64, Handshake>,
+}
+
+impl StatusHandshakes {
+    pub fn start(&mut self, request_id: u64, peer_id: PeerId) {
+        self.pending.insert(
+            request_id,
+            Handshake {
+                peer_id,
+                received: false,
+            },
+        );
+    }
+
+    pub fn contains(&self, request_id: u64) -> bool {
+        self.pending.contains_key(&request_id)
+    }
+
+    pub fn is_pending_for(&self, peer_id: &PeerId) -> bool {
+        self.pending
+            .values()
+            .any(|handshake| handshake.peer_id == *peer_id)
+    }
+
+    /// Records the status of the peer. Returns false if it already sent one, a Status response
+    /// has exactly one chunk.
+    pub fn received(&mut self, request_id: u64) -> bool {
+        match self.pending.get_mut(&request_id) {
+            Some(handshake) if !handshake.received => {
+                handshake.received = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Ends a handshake and returns its peer and whether the peer sent its status.
+    pub fn finish(&mut self, request_id: u64) -> Option<(PeerId, bool)> {
+        self.pending
+            .remove(&request_id)
+            .map(|handshake| (handshake.peer_id, handshake.received))
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_ssz_round_trip() {
+        let status = Status {
+            finalized_root: [1; 32],
+            finalized_slot: 7,
+            head_root: [2; 32],
+            head_slot: 9,
+        };
+        let bytes = status.to_ssz_bytes();
+        assert_eq!(bytes.len(), STATUS_SSZ_LEN);
+        assert_eq!(bytes[32], 7);
+        assert_eq!(Status::from_ssz_bytes(&bytes), Ok(status));
+        assert!(Status::from_ssz_bytes(&bytes[1..]).is_err());
+    }
+
+    #[test]
+    fn test_handshake_takes_a_single_status() {
+        let mut handshakes = StatusHandshakes::default();
+        let peer = PeerId::random();
+
+        handshakes.start(1, peer);
+        assert!(handshakes.is_pending_for(&peer));
+        assert!(handshakes.received(1));
+        assert!(!handshakes.received(1));
+        assert_eq!(handshakes.finish(1), Some((peer, true)));
+        assert!(!handshakes.is_pending_for(&peer));
+
+        handshakes.start(2, peer);
+        assert_eq!(handshakes.finish(2), Some((peer, false)));
+        assert_eq!(handshakes.finish(2), None);
+    }
+}
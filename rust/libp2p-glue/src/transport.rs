@@ -0,0 +1,240 @@
+use std::io;
+use std::time::Duration;
+
+use futures::future::Either;
+use libp2p::core::{
+    muxing::StreamMuxerBox,
+    transport::Boxed,
+    upgrade::{SelectUpgrade, Version},
+};
+use libp2p::identity::Keypair;
+use libp2p::{noise, tls, yamux, PeerId, Transport};
+use serde::Deserialize;
+
+pub type BoxedTransport = Boxed<(PeerId, StreamMuxerBox)>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transports {
+    Tcp,
+    Quic,
+    Both,
+}
+
+/// Stream multiplexer of TCP connections, QUIC multiplexes streams itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Muxer {
+    Yamux,
+    Mplex,
+}
+
+/// Security protocol of TCP connections, QUIC always uses TLS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Security {
+    Noise,
+    Tls,
+}
+
+/// Transport configuration supplied by Zig as JSON at network start.
+///
+/// Every field is optional; missing fields fall back to the defaults below, TCP with noise and
+/// yamux or mplex next to QUIC.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TransportConfig {
+    pub transports: Transports,
+    /// Muxers offered on TCP connections, in preference order.
+    pub muxers: Vec<Muxer>,
+    pub mplex_max_buffer_size: usize,
+    /// Upper bound on setting up a connection: the QUIC handshake, or the security and muxer
+    /// negotiation on TCP.
+    pub dial_timeout_ms: u64,
+    pub security: Security,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        Self {
+            transports: Transports::Both,
+            muxers: vec![Muxer::Yamux, Muxer::Mplex],
+            mplex_max_buffer_size: 256,
+            dial_timeout_ms: 10_000,
+            security: Security::Noise,
+        }
+    }
+}
+
+impl TransportConfig {
+    /// Parses the JSON config passed over FFI. An empty document selects the defaults.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        if json.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        let config: Self =
+            serde_json::from_str(json).map_err(|e| format!("invalid transport config: {e}"))?;
+        config
+            .validate()
+            .map_err(|e| format!("invalid transport config: {e}"))?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.dial_timeout_ms == 0 {
+            return Err("dial_timeout_ms must be positive".into());
+        }
+        match self.muxers.as_slice() {
+            [] => return Err("muxers must not be empty".into()),
+            [first, second] if first == second => {
+                return Err(format!("muxer {:?} is listed twice", first))
+            }
+            [_] | [_, _] => {}
+            _ => return Err("a muxer is listed twice".into()),
+        }
+        if self.muxers.contains(&Muxer::Mplex) && self.mplex_max_buffer_size == 0 {
+            return Err("mplex_max_buffer_size must be positive".into());
+        }
+        Ok(())
+    }
+
+    fn dial_timeout(&self) -> Duration {
+        Duration::from_millis(self.dial_timeout_ms)
+    }
+}
+
+pub fn build_transport(
+    local_private_key: &Keypair,
+    config: &TransportConfig,
+) -> io::Result<BoxedTransport> {
+    let transport = match config.transports {
+        Transports::Tcp => build_tcp_transport(local_private_key, config)?,
+        Transports::Quic => build_quic_transport(local_private_key, config),
+        Transports::Both => build_tcp_transport(local_private_key, config)?
+            .or_transport(build_quic_transport(local_private_key, config))
+            .map(|either_output, _| match either_output {
+                Either::Left(output) => output,
+                Either::Right(output) => output,
+            })
+            .boxed(),
+    };
+
+    // Enables DNS over the transport.
+    let transport = libp2p::dns::tokio::Transport::system(transport)?.boxed();
+
+    Ok(transport)
+}
+
+fn build_tcp_transport(
+    local_private_key: &Keypair,
+    config: &TransportConfig,
+) -> io::Result<BoxedTransport> {
+    let mut mplex_config = libp2p_mplex::Config::new();
+    mplex_config.set_max_buffer_size(config.mplex_max_buffer_size);
+    mplex_config.set_max_buffer_behaviour(libp2p_mplex::MaxBufferBehaviour::Block);
+    let yamux_config = yamux::Config::default();
+
+    // Every security and muxer combination is an upgrade of its own type, so each one is
+    // built and boxed on its own.
+    macro_rules! tcp_transport {
+        ($security:expr, $muxer:expr) => {
+            libp2p::tcp::tokio::Transport::new(libp2p::tcp::Config::default().nodelay(true))
+                .upgrade(Version::V1)
+                .authenticate($security)
+                .multiplex($muxer)
+                .timeout(config.dial_timeout())
+                .boxed()
+        };
+    }
+    macro_rules! with_muxers {
+        ($security:expr) => {
+            match config.muxers.as_slice() {
+                [Muxer::Yamux] => tcp_transport!($security, yamux_config),
+                [Muxer::Mplex] => tcp_transport!($security, mplex_config),
+                [Muxer::Yamux, Muxer::Mplex] => {
+                    tcp_transport!($security, SelectUpgrade::new(yamux_config, mplex_config))
+                }
+                [Muxer::Mplex, Muxer::Yamux] => {
+                    tcp_transport!($security, SelectUpgrade::new(mplex_config, yamux_config))
+                }
+                muxers => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("unsupported muxers {:?}", muxers),
+                    ))
+                }
+            }
+        };
+    }
+
+    let transport = match config.security {
+        Security::Noise => with_muxers!(generate_noise_config(local_private_key)?),
+        Security::Tls => {
+            with_muxers!(tls::Config::new(local_private_key).map_err(io::Error::other)?)
+        }
+    };
+    Ok(transport)
+}
+
+fn build_quic_transport(local_private_key: &Keypair, config: &TransportConfig) -> BoxedTransport {
+    let mut quic_config = libp2p::quic::Config::new(local_private_key);
+    quic_config.handshake_timeout = config.dial_timeout();
+    libp2p::quic::tokio::Transport::new(quic_config)
+        .map(|(peer_id, muxer), _| (peer_id, StreamMuxerBox::new(muxer)))
+        .boxed()
+}
+
+/// Generate authenticated XX Noise config from identity keys
+fn generate_noise_config(identity_keypair: &Keypair) -> io::Result<noise::Config> {
+    noise::Config::new(identity_keypair).map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_selects_transports() {
+        assert_eq!(
+            TransportConfig::from_json("").unwrap(),
+            TransportConfig::default()
+        );
+
+        let config = TransportConfig::from_json(
+            r#"{"transports": "tcp", "muxers": ["mplex"], "security": "tls", "dial_timeout_ms": 5000}"#,
+        )
+        .unwrap();
+        assert_eq!(config.transports, Transports::Tcp);
+        assert_eq!(config.muxers, vec![Muxer::Mplex]);
+        assert_eq!(config.security, Security::Tls);
+        assert_eq!(config.dial_timeout(), Duration::from_secs(5));
+
+        assert!(TransportConfig::from_json(r#"{"transports": "udp"}"#).is_err());
+        assert!(TransportConfig::from_json(r#"{"muxers": []}"#).is_err());
+        assert!(TransportConfig::from_json(r#"{"muxers": ["yamux", "yamux"]}"#).is_err());
+        assert!(TransportConfig::from_json(r#"{"dial_timeout_ms": 0}"#).is_err());
+    }
+
+    #[test]
+    fn test_every_combination_builds() {
+        let keypair = Keypair::generate_secp256k1();
+        for transports in [Transports::Tcp, Transports::Quic, Transports::Both] {
+            for security in [Security::Noise, Security::Tls] {
+                for muxers in [
+                    vec![Muxer::Yamux],
+                    vec![Muxer::Mplex],
+                    vec![Muxer::Yamux, Muxer::Mplex],
+                    vec![Muxer::Mplex, Muxer::Yamux],
+                ] {
+                    let config = TransportConfig {
+                        transports,
+                        muxers,
+                        security,
+                        ..TransportConfig::default()
+                    };
+                    assert!(build_transport(&keypair, &config).is_ok(), "{config:?}");
+                }
+            }
+        }
+    }
+}